@@ -0,0 +1,392 @@
+use druid::Data;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::theme::ThemeSettings;
+use file_explorer::search;
+
+/// Settings persisted between runs as JSON in the OS config directory.
+/// Missing fields fall back to their defaults so older config files keep loading.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub theme: ThemeSettings,
+    // Show the results in a monospaced font.
+    pub monospace_results: bool,
+    // Show control characters in result names as escapes such as `\n`.
+    pub escape_controls: bool,
+    // Show only the last this many parts of each result's path; 0 shows all of it.
+    pub display_components: usize,
+    // Show the active filters as removable chips above the results.
+    pub show_filter_chips: bool,
+    // What clicking a result row does.
+    pub click_action: RowClickAction,
+    // Where folders go in the results, ahead of the sort order.
+    pub dirs_first: FolderPlacement,
+    // User command run on a result, e.g. `code {}`; `{}` is replaced with the path.
+    pub command_template: String,
+    // Command used to open a result with the system, in the same format as `command_template`.
+    pub open_command: String,
+    // Opens a content-search match at its line, e.g. `code -g {path}:{line}`; empty to just open.
+    pub editor_command: String,
+    // Compares two marked files, with `{a}` and `{b}` standing for them.
+    pub diff_command: String,
+    // Worker threads used to scan file contents.
+    pub thread_count: usize,
+    // Comma-separated folders never searched, see `SearchOptions::exclude_paths`.
+    pub exclude_paths: String,
+    // Content search reads at most this many bytes of each file; 0 reads whole files.
+    pub content_max_bytes: u64,
+    // "Open all" asks for confirmation when there are more results than this.
+    pub open_all_warn_threshold: usize,
+    // "Save in parts…" writes this many paths to each file.
+    pub segment_size: usize,
+    // Most progress updates a background task sends the UI per second; more are coalesced.
+    pub updates_per_second: usize,
+    // "Stale files only" lists entries not modified for at least this many days.
+    pub stale_days: u64,
+    // Retry a folder that couldn't be read this many times, see `SearchOptions::read_retries`.
+    pub read_retries: usize,
+    // Drop spaces around the search terms, see `SearchOptions::trim_terms`.
+    pub trim_terms: bool,
+    // Keep the search term and re-run it after drilling into a directory result.
+    pub rerun_on_drill_down: bool,
+    // "Copy listing" adds each file's size after its name.
+    pub listing_sizes: bool,
+    // Retry a search that finds nothing from the parent folder, up to a few levels up.
+    pub auto_widen: bool,
+    // Resolve `..` and symlinks in the chosen folder so searches run on its real absolute path.
+    pub canonicalize_root: bool,
+    // Leave the results listed when the folder changes.
+    pub keep_results_on_root_change: bool,
+    // Folder that searches and browsing are confined to, for shared machines; only set by
+    // editing the config file, so it can't be lifted from within the app.
+    pub sandbox_root: Option<PathBuf>,
+    // Ask before changing the folder while a loaded preset has unsaved changes.
+    pub confirm_root_change: bool,
+    // Ask before searching a whole disk with no depth limit or folders skipped.
+    pub warn_whole_disk: bool,
+    pub presets: Arc<Vec<SearchPreset>>,
+    // Named bundles of the theme, filters and view options, for switching between workflows.
+    pub workspaces: Arc<Vec<Workspace>>,
+    // Filters and view options remembered for particular folders, applied on switching to one.
+    pub folder_settings: Arc<Vec<FolderSettings>>,
+    // Pinned files and folders, shown as a row of buttons.
+    pub bookmarks: Arc<Vec<String>>,
+    // Files opened from the results, most recent first.
+    pub recent_files: Arc<Vec<String>>,
+    // Tags given to results with their row's Tag button, by path.
+    pub tags: Arc<BTreeMap<PathBuf, Vec<String>>>,
+    // Reopen with the folder, search and view options that were set on exit.
+    pub restore_session: bool,
+    // Those options, kept only while `restore_session` is on.
+    pub session: Option<SessionState>,
+    // List the starting folder's own entries on launch, so the window doesn't open empty.
+    pub list_on_start: bool,
+    // While watching, append the results to `results_log_path` every `results_log_minutes`.
+    pub results_log: bool,
+    pub results_log_minutes: u64,
+    // Remember the last search run in each folder while watching, and bring it back on
+    // switching to that folder with watch mode on.
+    pub remember_watch_terms: bool,
+    // Those searches, by folder, resolved so each folder has one entry however it was typed.
+    pub watch_terms: Arc<BTreeMap<String, String>>,
+    // Highlight the first result when a search finishes, ready for the keyboard.
+    pub auto_select_first: bool,
+    // Put the cursor back in the search box when a search the user ran finishes, to tweak and
+    // re-run it.
+    pub refocus_search_box: bool,
+}
+
+/// The search fields and view toggles restored on start with `restore_session`. Results and
+/// anything else transient are left out.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    pub root_path: String,
+    pub search_term: String,
+    pub or_terms: Arc<Vec<String>>,
+    pub other_roots: Arc<Vec<String>>,
+    pub search_contents: bool,
+    pub content_mode: usize,
+    pub match_full_path: bool,
+    pub case_sensitive: bool,
+    pub all_words: bool,
+    pub stem_only: bool,
+    pub approximate: bool,
+    pub max_edit_distance: usize,
+    pub largest_only: bool,
+    pub largest_count: usize,
+    pub exact_case_first: bool,
+    pub normalize_unicode: bool,
+    pub full_case_folding: bool,
+    pub resolve_links: bool,
+    pub match_link_targets: bool,
+    pub broken_links_only: bool,
+    pub empty_only: bool,
+    pub stale_only: bool,
+    pub skip_binary: bool,
+    pub stay_on_filesystem: bool,
+    pub low_impact: bool,
+    pub term_filters: bool,
+    pub show_match_line: bool,
+    pub extensions: String,
+    pub sniff_types: bool,
+    pub mime_types: String,
+    pub exclude_dirs: String,
+    pub show_hidden: bool,
+    pub recursive: bool,
+    pub max_depth: usize,
+    pub permission_filter: usize,
+    pub created_after: String,
+    pub created_before: String,
+    pub sort_key: SortKey,
+    pub collapse_prefix: bool,
+    pub shorten_paths: bool,
+    pub color_by_type: bool,
+    pub color_by_age: bool,
+    pub color_by_depth: bool,
+    pub mark_folders: bool,
+    pub columns: bool,
+    pub group_by_name: bool,
+}
+
+/// A named set of settings for one way of working, e.g. code search or disk cleanup: the theme
+/// plus the filters, view toggles and sort order. Loading one leaves the folders and search
+/// terms in `view` unused, so it changes how to search but not what for.
+#[derive(Clone, Default, Data, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    pub name: String,
+    pub theme: ThemeSettings,
+    #[data(same_fn = "PartialEq::eq")]
+    pub view: SessionState,
+}
+
+/// The options to use whenever `root` becomes the folder searched, like a workspace tied to a
+/// folder instead of a name. As with workspaces, the folders and terms in `view` are unused.
+#[derive(Clone, Default, Data, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FolderSettings {
+    // Resolved, so each folder has one entry however it was typed.
+    pub root: String,
+    #[data(same_fn = "PartialEq::eq")]
+    pub view: SessionState,
+}
+
+/// A named bundle of search fields that can be recalled later.
+#[derive(Clone, Default, Data, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchPreset {
+    pub name: String,
+    pub term: String,
+    pub search_contents: bool,
+    // Index into the content search modes offered next to "Search file contents".
+    pub content_mode: usize,
+    pub match_full_path: bool,
+    // Everything else that narrows the search; None for presets saved before it was kept.
+    #[data(same_fn = "PartialEq::eq")]
+    pub filters: Option<SearchFilters>,
+}
+
+/// The filters and flags a search preset keeps besides its term: what `SessionState` holds
+/// about the search, without the folders and view options, plus the excluded paths.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchFilters {
+    pub or_terms: Arc<Vec<String>>,
+    pub case_sensitive: bool,
+    pub all_words: bool,
+    pub stem_only: bool,
+    pub approximate: bool,
+    pub max_edit_distance: usize,
+    pub largest_only: bool,
+    pub largest_count: usize,
+    pub exact_case_first: bool,
+    pub normalize_unicode: bool,
+    pub full_case_folding: bool,
+    pub resolve_links: bool,
+    pub match_link_targets: bool,
+    pub broken_links_only: bool,
+    pub empty_only: bool,
+    pub stale_only: bool,
+    pub skip_binary: bool,
+    pub stay_on_filesystem: bool,
+    pub term_filters: bool,
+    pub extensions: String,
+    pub sniff_types: bool,
+    pub mime_types: String,
+    pub exclude_dirs: String,
+    pub exclude_paths: String,
+    pub show_hidden: bool,
+    pub recursive: bool,
+    pub max_depth: usize,
+    pub permission_filter: usize,
+    pub created_after: String,
+    pub created_before: String,
+}
+
+/// What clicking a result row does; the row's buttons offer the others.
+#[derive(Clone, Copy, Data, Default, PartialEq, Serialize, Deserialize)]
+pub enum RowClickAction {
+    // Open with the system, or at the matching line for content results.
+    #[default]
+    Open,
+    // Only highlight the row.
+    Select,
+    // Browse to the folder holding the result, with it highlighted.
+    Reveal,
+}
+
+/// Where folders go in the results list, before the sort order is applied within each part.
+#[derive(Clone, Copy, Data, Default, PartialEq, Serialize, Deserialize)]
+pub enum FolderPlacement {
+    Top,
+    Bottom,
+    // Folders and files sorted together.
+    #[default]
+    Mixed,
+}
+
+/// Order of the results list. Ties are broken by path so re-sorting never reshuffles them.
+#[derive(Clone, Copy, Data, Default, PartialEq, Serialize, Deserialize)]
+pub enum SortKey {
+    // The order the search found them in.
+    #[default]
+    Found,
+    Name,
+    // Largest first; folders last.
+    Size,
+    // Newest first.
+    Modified,
+    // Least recently modified first, for stale files.
+    Oldest,
+    // Most recently used first, see `ResultEntry::last_activity`.
+    Accessed,
+    // By full path, which the tie-break already does.
+    Path,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            theme: ThemeSettings::default(),
+            monospace_results: false,
+            escape_controls: true,
+            display_components: 0,
+            show_filter_chips: true,
+            click_action: RowClickAction::Open,
+            dirs_first: FolderPlacement::Mixed,
+            command_template: String::new(),
+            open_command: default_open_command(),
+            diff_command: default_diff_command(),
+            editor_command: String::new(),
+            thread_count: default_thread_count(),
+            exclude_paths: default_exclude_paths(),
+            content_max_bytes: search::DEFAULT_CONTENT_MAX_BYTES,
+            open_all_warn_threshold: 10,
+            segment_size: 1000,
+            updates_per_second: 30,
+            stale_days: 365,
+            read_retries: 2,
+            trim_terms: true,
+            rerun_on_drill_down: true,
+            listing_sizes: false,
+            auto_widen: false,
+            canonicalize_root: false,
+            keep_results_on_root_change: false,
+            sandbox_root: None,
+            confirm_root_change: false,
+            warn_whole_disk: true,
+            presets: Arc::new(Vec::new()),
+            workspaces: Arc::new(Vec::new()),
+            folder_settings: Arc::new(Vec::new()),
+            bookmarks: Arc::new(Vec::new()),
+            recent_files: Arc::new(Vec::new()),
+            tags: Arc::new(BTreeMap::new()),
+            restore_session: false,
+            list_on_start: false,
+            results_log: false,
+            results_log_minutes: 5,
+            remember_watch_terms: false,
+            watch_terms: Arc::new(BTreeMap::new()),
+            auto_select_first: false,
+            refocus_search_box: false,
+            session: None,
+        }
+    }
+}
+
+/// One worker per logical core, or a single one if that can't be determined.
+pub fn default_thread_count() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+/// The engine's system folders to skip, as the comma-separated text shown in the settings.
+pub fn default_exclude_paths() -> String {
+    let paths: Vec<String> = search::default_exclude_paths()
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    paths.join(", ")
+}
+
+/// The platform's own way of opening a file or folder with its default application.
+pub fn default_open_command() -> String {
+    if cfg!(target_os = "macos") {
+        "open {}"
+    } else if cfg!(target_os = "windows") {
+        "explorer {}"
+    } else {
+        "xdg-open {}"
+    }
+    .to_string()
+}
+
+/// A graphical diff tool likely to be around: FileMerge on macOS (with the Xcode tools),
+/// WinMerge on Windows and Meld elsewhere.
+pub fn default_diff_command() -> String {
+    if cfg!(target_os = "macos") {
+        "opendiff {a} {b}"
+    } else if cfg!(target_os = "windows") {
+        "WinMergeU {a} {b}"
+    } else {
+        "meld {a} {b}"
+    }
+    .to_string()
+}
+
+impl Config {
+    /// Loads the config file, or the defaults if it is missing or unreadable.
+    pub fn load() -> Config {
+        config_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = config_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, text)
+    }
+}
+
+/// Location of the config file, e.g. `~/.config/rust-file-explorer/config.json` on Linux.
+pub fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("rust-file-explorer").join("config.json"))
+}
+
+/// The log the results are appended to while watching, next to the config file. The one
+/// before it, once it grew too big, is kept as `results.log.1`.
+pub fn results_log_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("rust-file-explorer").join("results.log"))
+}
@@ -0,0 +1,54 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The SHA-256 of a file's contents as lowercase hex, read in fixed-size chunks so memory use
+/// stays bounded. `progress` is called with (bytes hashed, file size) after each chunk, and
+/// setting `cancel` stops between chunks with an `Interrupted` error.
+pub fn sha256_file(
+    path: &Path,
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(u64, u64),
+) -> io::Result<String> {
+    let mut file = open_without_atime(path)?;
+    let total = file.metadata()?.len();
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    let mut hashed = 0;
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        hashed += read as u64;
+        progress(hashed, total);
+    }
+    let digest = hasher.finalize();
+    Ok(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Opens `path` for reading without updating its access time, so hashing doesn't make every
+/// file look recently used. Linux only allows that on the user's own files, so others are
+/// opened normally; elsewhere the filesystem's own atime policy applies.
+#[cfg(target_os = "linux")]
+fn open_without_atime(path: &Path) -> io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    // `O_NOATIME` from <fcntl.h>, the same on every architecture Rust targets but a few.
+    const O_NOATIME: i32 = 0o1_000_000;
+    fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(O_NOATIME)
+        .open(path)
+        .or_else(|_| fs::File::open(path))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_without_atime(path: &Path) -> io::Result<fs::File> {
+    fs::File::open(path)
+}
@@ -0,0 +1,112 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// What "Open container" does with a result.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Container {
+    // A disk image this platform can mount, opened by mounting it.
+    DiskImage,
+    // An archive, opened with the system, which lists or unpacks it.
+    Archive,
+}
+
+impl Container {
+    /// The kind of container `path` is by its extension, if any.
+    pub fn of(path: &Path) -> Option<Container> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            Some(Container::DiskImage)
+        } else if ARCHIVE_EXTENSIONS.contains(&extension.as_str()) {
+            Some(Container::Archive)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+const IMAGE_EXTENSIONS: &[&str] = &["dmg", "iso", "img", "sparseimage"];
+#[cfg(windows)]
+const IMAGE_EXTENSIONS: &[&str] = &["iso", "vhd", "vhdx"];
+#[cfg(not(any(target_os = "macos", windows)))]
+const IMAGE_EXTENSIONS: &[&str] = &["iso", "img"];
+
+const ARCHIVE_EXTENSIONS: &[&str] = &["zip", "tar", "gz", "tgz", "bz2", "xz", "7z", "rar"];
+
+/// Mounts the disk image at `image` with `hdiutil`, returning where it was mounted.
+#[cfg(target_os = "macos")]
+pub fn mount(image: &Path) -> io::Result<PathBuf> {
+    // Lines are `device \t type \t mount point`; only the volumes have the last.
+    let output = run(Command::new("hdiutil").args(["attach", "-nobrowse"]).arg(image))?;
+    output
+        .lines()
+        .filter_map(|line| line.rsplit('\t').next())
+        .map(str::trim)
+        .find(|field| field.starts_with('/') && !field.starts_with("/dev/"))
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::other("it has no volume that could be mounted"))
+}
+
+/// Mounts the disk image at `image` with PowerShell's `Mount-DiskImage`, returning the drive
+/// it was given.
+#[cfg(windows)]
+pub fn mount(image: &Path) -> io::Result<PathBuf> {
+    // Single quotes in PowerShell strings are escaped by doubling them.
+    let script = format!(
+        "(Mount-DiskImage -ImagePath '{}' -PassThru | Get-Volume).DriveLetter",
+        image.display().to_string().replace('\'', "''")
+    );
+    let output = run(Command::new("powershell").args(["-NoProfile", "-Command", &script]))?;
+    let letter = output
+        .trim()
+        .chars()
+        .next()
+        .filter(char::is_ascii_alphabetic)
+        .ok_or_else(|| io::Error::other("it has no volume that could be mounted"))?;
+    Ok(PathBuf::from(format!("{}:\\", letter)))
+}
+
+/// Sets the disk image at `image` up as a read-only loop device and mounts it through udisks,
+/// which a desktop user may do without root, returning where it was mounted. Images with a
+/// partition table are mounted by their first partition.
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn mount(image: &Path) -> io::Result<PathBuf> {
+    // "Mapped file disk.iso as /dev/loop0."
+    let output = run(Command::new("udisksctl").args(["loop-setup", "-r", "-f"]).arg(image))?;
+    let device = output
+        .split_whitespace()
+        .find(|word| word.starts_with("/dev/"))
+        .map(|word| word.trim_end_matches('.').to_string())
+        .ok_or_else(|| io::Error::other("udisksctl didn't say which loop device it used"))?;
+    let mounted = run(Command::new("udisksctl").args(["mount", "-b", &device]))
+        .or_else(|_| run(Command::new("udisksctl").args(["mount", "-b", &format!("{}p1", device)])));
+    let mounted = match mounted {
+        Ok(mounted) => mounted,
+        Err(err) => {
+            // Nothing was mounted, so the loop device would only linger until the next reboot.
+            let _ = run(Command::new("udisksctl").args(["loop-delete", "-b", &device]));
+            return Err(err);
+        }
+    };
+    // "Mounted /dev/loop0 at /media/user/DISK."
+    mounted
+        .split_once(" at ")
+        .map(|(_, point)| PathBuf::from(point.trim().trim_end_matches('.')))
+        .ok_or_else(|| io::Error::other("udisksctl didn't say where it mounted the image"))
+}
+
+/// Runs a command to completion and returns what it printed, or an error carrying its
+/// complaint if it failed.
+fn run(command: &mut Command) -> io::Result<String> {
+    let output = command.output()?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+    let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(io::Error::other(if message.is_empty() {
+        format!("exited with {}", output.status)
+    } else {
+        message
+    }))
+}
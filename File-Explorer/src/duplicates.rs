@@ -0,0 +1,64 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Groups `paths` into clusters of files with identical contents.
+///
+/// Files are first bucketed by size, so only same-sized files are ever read and hashed, and
+/// then by SHA-256, so a cluster offered for deletion never rests on a chance collision.
+/// `progress` is called with (files hashed, files to hash) as the work advances.
+/// Directories and unreadable files are skipped. Each returned cluster has at least two files,
+/// and clusters are ordered by file size, largest first.
+pub fn find_duplicates(
+    paths: &[PathBuf],
+    mut progress: impl FnMut(usize, usize),
+) -> Vec<(u64, Vec<PathBuf>)> {
+    let mut by_size: HashMap<u64, Vec<&PathBuf>> = HashMap::new();
+    for path in paths {
+        if let Ok(metadata) = fs::metadata(path) {
+            if metadata.is_file() {
+                by_size.entry(metadata.len()).or_default().push(path);
+            }
+        }
+    }
+    by_size.retain(|_, group| group.len() > 1);
+
+    let total = by_size.values().map(Vec::len).sum();
+    let mut hashed = 0;
+    let mut clusters = Vec::new();
+    for (size, group) in by_size {
+        let mut by_hash: HashMap<[u8; 32], Vec<PathBuf>> = HashMap::new();
+        for path in group {
+            if let Ok(hash) = hash_file(path) {
+                by_hash.entry(hash).or_default().push(path.clone());
+            }
+            hashed += 1;
+            progress(hashed, total);
+        }
+        clusters.extend(
+            by_hash
+                .into_values()
+                .filter(|paths| paths.len() > 1)
+                .map(|paths| (size, paths)),
+        );
+    }
+    clusters.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    clusters
+}
+
+/// Hashes a file's contents in fixed-size chunks so memory use stays bounded.
+fn hash_file(path: &Path) -> io::Result<[u8; 32]> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().into())
+}
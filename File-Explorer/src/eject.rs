@@ -0,0 +1,87 @@
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::process::Command;
+
+/// Whether `path` is a folder another filesystem is mounted on: it sits on a different device
+/// than the folder holding it. The root of the disk never counts.
+pub fn is_mount_point(path: &Path) -> bool {
+    let Some(parent) = path.parent() else {
+        return false;
+    };
+    match (fs::metadata(path), fs::metadata(parent)) {
+        (Ok(dir), Ok(parent)) => dir.is_dir() && dir.dev() != parent.dev(),
+        _ => false,
+    }
+}
+
+// Where the system mounts removable drives and disk images for the desktop user; ejecting is
+// only offered below these, so system mounts like `/`, `/boot` or `/home` are never touched.
+#[cfg(target_os = "macos")]
+const REMOVABLE_MOUNT_DIRS: &[&str] = &["/Volumes"];
+#[cfg(not(target_os = "macos"))]
+const REMOVABLE_MOUNT_DIRS: &[&str] = &["/media", "/run/media", "/mnt"];
+
+/// Whether `mount_point` is somewhere drives the user plugged in get mounted, going by its path
+/// alone.
+pub fn is_removable(mount_point: &Path) -> bool {
+    REMOVABLE_MOUNT_DIRS
+        .iter()
+        .any(|dir| mount_point.starts_with(dir) && mount_point != Path::new(dir))
+}
+
+/// Ejects the drive mounted at `mount_point` with `diskutil eject`, which also unmounts it.
+#[cfg(target_os = "macos")]
+pub fn eject(mount_point: &Path) -> io::Result<()> {
+    run(Command::new("diskutil").arg("eject").arg(mount_point))
+}
+
+/// Unmounts what is mounted at `mount_point` through udisks, which lets a desktop user unmount
+/// removable drives without root, and then powers the drive off where it can be.
+#[cfg(not(target_os = "macos"))]
+pub fn eject(mount_point: &Path) -> io::Result<()> {
+    let device = mounted_device(mount_point)?;
+    run(Command::new("udisksctl").args(["unmount", "-b"]).arg(&device))?;
+    // Internal disks and partitions of a drive still in use can't be powered off; unmounted is
+    // enough for those.
+    let _ = Command::new("udisksctl").args(["power-off", "-b"]).arg(&device).output();
+    Ok(())
+}
+
+/// The device mounted at `mount_point`, e.g. `/dev/sdb1`, from `/proc/self/mounts`.
+#[cfg(not(target_os = "macos"))]
+fn mounted_device(mount_point: &Path) -> io::Result<String> {
+    let mounts = fs::read_to_string("/proc/self/mounts")?;
+    // Spaces and tabs in the paths there are written as octal escapes like `\040`.
+    let wanted = mount_point
+        .to_string_lossy()
+        .replace('\\', "\\134")
+        .replace(' ', "\\040")
+        .replace('\t', "\\011");
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            Some((fields.next()?, fields.next()?))
+        })
+        // The last mount on a folder is the one in effect.
+        .rev()
+        .find(|&(_, point)| point == wanted)
+        .map(|(device, _)| device.to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not a mounted drive"))
+}
+
+/// Runs a command to completion, turning a failure into an error carrying what it printed.
+fn run(command: &mut Command) -> io::Result<()> {
+    let output = command.output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(io::Error::other(if message.is_empty() {
+        format!("exited with {}", output.status)
+    } else {
+        message
+    }))
+}
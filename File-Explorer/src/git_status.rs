@@ -0,0 +1,154 @@
+//! Looking up where result files stand in the git working copies holding them.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where a file stands in the git working copy holding it.
+#[derive(Clone, Copy, PartialEq)]
+pub enum GitStatus {
+    Clean,
+    // Changed in the working tree since the last commit or `git add`.
+    Modified,
+    // Changes added to the index and nothing more since.
+    Staged,
+    Untracked,
+    Ignored,
+}
+
+impl GitStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            GitStatus::Clean => "clean",
+            GitStatus::Modified => "modified",
+            GitStatus::Staged => "staged",
+            GitStatus::Untracked => "untracked",
+            GitStatus::Ignored => "ignored",
+        }
+    }
+
+    /// A status from the two-letter code of `git status --porcelain`: index then working tree.
+    fn from_code(index: u8, worktree: u8) -> GitStatus {
+        match (index, worktree) {
+            (b'?', _) => GitStatus::Untracked,
+            (b'!', _) => GitStatus::Ignored,
+            (_, b' ') => GitStatus::Staged,
+            _ => GitStatus::Modified,
+        }
+    }
+}
+
+/// The status of each of `files` that is inside a git working copy, asking the `git` command
+/// once per repository and finding the repositories without it. Files outside any repository, and all of them when git isn't
+/// installed, are left out.
+pub fn statuses(files: &[PathBuf]) -> Vec<(PathBuf, GitStatus)> {
+    // Most results share a handful of folders, and the folders a handful of repositories.
+    let mut tops: HashMap<PathBuf, Option<PathBuf>> = HashMap::new();
+    let mut changes: HashMap<PathBuf, HashMap<PathBuf, GitStatus>> = HashMap::new();
+    let mut found = Vec::new();
+    for file in files {
+        let Some(dir) = file.parent() else {
+            continue;
+        };
+        let top = tops.entry(dir.to_path_buf()).or_insert_with(|| repository_top(dir));
+        let Some(top) = top.clone() else {
+            continue;
+        };
+        // Paths from git are relative to the real top, so symlinked folders are resolved first.
+        let Some(relative) = file
+            .canonicalize()
+            .ok()
+            .and_then(|real| real.strip_prefix(&top).ok().map(Path::to_path_buf))
+        else {
+            continue;
+        };
+        let repository = changes.entry(top.clone()).or_insert_with(|| changed_files(&top));
+        // Wholly untracked or ignored folders are listed once, as the folder.
+        let status = relative
+            .ancestors()
+            .find_map(|path| repository.get(path))
+            .copied()
+            .unwrap_or(GitStatus::Clean);
+        found.push((file.clone(), status));
+    }
+    found
+}
+
+/// The top folder of the working copy holding `dir`, resolved like `Path::canonicalize`: the
+/// nearest folder at or above it with a `.git` in it. That is a folder in a plain clone and a
+/// file in worktrees and submodules, so either counts.
+fn repository_top(dir: &Path) -> Option<PathBuf> {
+    let dir = dir.canonicalize().ok()?;
+    dir.ancestors().find(|folder| folder.join(".git").exists()).map(Path::to_path_buf)
+}
+
+/// Every file in the working copy at `top` that isn't clean, by its path relative to `top`.
+fn changed_files(top: &Path) -> HashMap<PathBuf, GitStatus> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(top)
+        .args(["status", "--porcelain=v1", "-z", "--untracked-files=normal", "--ignored"])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => parse_porcelain(&output.stdout),
+        _ => HashMap::new(),
+    }
+}
+
+/// The entries of `git status --porcelain=v1 -z` output, by path.
+fn parse_porcelain(output: &[u8]) -> HashMap<PathBuf, GitStatus> {
+    let mut changed = HashMap::new();
+    // Entries are `XY path`, NUL-terminated; a rename or copy is followed by its old path.
+    let mut entries = output.split(|&byte| byte == 0);
+    while let Some(entry) = entries.next() {
+        if entry.len() < 4 {
+            continue;
+        }
+        let status = GitStatus::from_code(entry[0], entry[1]);
+        let path = String::from_utf8_lossy(&entry[3..]);
+        changed.insert(PathBuf::from(path.as_ref()), status);
+        if matches!(entry[0], b'R' | b'C') {
+            entries.next();
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_read_index_then_worktree() {
+        assert!(GitStatus::from_code(b'?', b'?') == GitStatus::Untracked);
+        assert!(GitStatus::from_code(b'!', b'!') == GitStatus::Ignored);
+        assert!(GitStatus::from_code(b'M', b' ') == GitStatus::Staged);
+        assert!(GitStatus::from_code(b'A', b' ') == GitStatus::Staged);
+        assert!(GitStatus::from_code(b' ', b'M') == GitStatus::Modified);
+        assert!(GitStatus::from_code(b'M', b'M') == GitStatus::Modified);
+    }
+
+    #[test]
+    fn porcelain_entries_are_split_on_nul_and_renames_skip_their_old_path() {
+        let output = b" M src/main.rs\0R  new name.rs\0old name.rs\0?? notes/\0!! target/\0";
+        let changed = parse_porcelain(output);
+        assert_eq!(changed.len(), 4);
+        assert!(changed[Path::new("src/main.rs")] == GitStatus::Modified);
+        assert!(changed[Path::new("new name.rs")] == GitStatus::Staged);
+        assert!(changed[Path::new("notes/")] == GitStatus::Untracked);
+        assert!(changed[Path::new("target/")] == GitStatus::Ignored);
+        assert!(!changed.contains_key(Path::new("old name.rs")));
+    }
+
+    #[test]
+    fn the_repository_is_the_nearest_folder_with_a_git_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let top = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(top.join("repo/.git")).unwrap();
+        std::fs::create_dir_all(top.join("repo/sub/worktree")).unwrap();
+        std::fs::write(top.join("repo/sub/worktree/.git"), "gitdir: elsewhere").unwrap();
+        assert_eq!(repository_top(&top.join("repo/sub")), Some(top.join("repo")));
+        assert_eq!(repository_top(&top.join("repo/sub/worktree")), Some(top.join("repo/sub/worktree")));
+        assert_eq!(repository_top(&top), None);
+    }
+}
@@ -0,0 +1,96 @@
+use druid::widget::{Controller, Painter};
+use druid::{
+    Color, Env, Event, EventCtx, KbKey, LifeCycle, LifeCycleCtx, RenderContext, Widget,
+};
+use std::time::{Duration, Instant};
+
+use crate::theme;
+
+// Presses of the same control closer together than this are ignored, so hammering Enter on
+// e.g. "Open all" can't launch a pile of windows.
+const ACTIVATION_COOLDOWN: Duration = Duration::from_millis(400);
+
+/// Makes a control reachable with Tab / Shift+Tab and pressable with Enter or Space.
+///
+/// druid 0.8 only registers text boxes for focus and has no accessibility tree, so
+/// without this a keyboard-only user can't reach any button.
+///
+/// Held-down keys and presses within `ACTIVATION_COOLDOWN` of the last one don't run the
+/// action again.
+pub struct KeyboardActivate<T> {
+    action: fn(&mut EventCtx, &mut T),
+    last_activation: Option<Instant>,
+}
+
+impl<T> KeyboardActivate<T> {
+    pub fn new(action: fn(&mut EventCtx, &mut T)) -> Self {
+        KeyboardActivate {
+            action,
+            last_activation: None,
+        }
+    }
+
+    fn cooling_down(&self) -> bool {
+        self.last_activation.is_some_and(|at| at.elapsed() < ACTIVATION_COOLDOWN)
+    }
+}
+
+impl<T, W: Widget<T>> Controller<T, W> for KeyboardActivate<T> {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        if let Event::KeyDown(key) = event {
+            if ctx.is_focused() {
+                match &key.key {
+                    KbKey::Tab if key.mods.shift() => ctx.focus_prev(),
+                    KbKey::Tab => ctx.focus_next(),
+                    k if is_activation_key(k) => {
+                        if !key.repeat && !self.cooling_down() {
+                            self.last_activation = Some(Instant::now());
+                            (self.action)(ctx, data);
+                        }
+                    }
+                    _ => return child.event(ctx, event, data, env),
+                }
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &T,
+        env: &Env,
+    ) {
+        match event {
+            LifeCycle::BuildFocusChain => ctx.register_for_focus(),
+            // The focus ring is painted by `focus_ring_background`.
+            LifeCycle::FocusChanged(_) => ctx.request_paint(),
+            _ => {}
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
+
+fn is_activation_key(key: &KbKey) -> bool {
+    match key {
+        KbKey::Enter => true,
+        KbKey::Character(c) => c == " ",
+        _ => false,
+    }
+}
+
+/// Accent-colored button background that draws an outline while the control has keyboard focus.
+pub fn focus_ring_background<T>() -> Painter<T> {
+    Painter::new(|ctx, _data: &T, env| {
+        let rect = ctx.size().to_rect();
+        ctx.fill(rect, &env.get(theme::ACCENT));
+        if ctx.is_focused() {
+            let ring = rect.inset(-1.0).to_rounded_rect(4.0);
+            ctx.stroke(ring, &Color::rgb8(0x6c, 0xa6, 0xff), 2.0);
+        }
+    })
+}
@@ -0,0 +1,205 @@
+use druid::widget::prelude::*;
+use druid::{Data, Point, Rect, WidgetPod};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+// Rows built beyond each edge of the visible area, so short scrolls don't show gaps.
+const OVERSCAN: usize = 8;
+
+// Row height assumed until rows have been measured; no row is shorter than this.
+const MIN_ROW_HEIGHT: f64 = 40.0;
+
+type Row<S, T> = WidgetPod<(S, T), Box<dyn Widget<(S, T)>>>;
+
+/// A vertical list for use inside a `Scroll` that only builds the rows in view.
+///
+/// Its data is shared settings plus the items, and each row gets `(settings, item)`, like a
+/// `List` over `(S, Arc<Vec<T>>)`. Every row is given the height of the tallest row measured
+/// so far, which lets the list place any row without building the ones before it, so tens of
+/// thousands of items cost no more than a screenful.
+pub struct LazyList<S, T> {
+    make_row: Box<dyn Fn() -> Row<S, T>>,
+    rows: BTreeMap<usize, Row<S, T>>,
+    spacing: f64,
+    row_height: f64,
+    // The part of the list in view, in the list's own coordinates.
+    visible: Rect,
+    // Picks out the row to scroll into view, e.g. a highlighted result, and where it was.
+    is_marked: Option<fn(&S, &T) -> bool>,
+    marked: Option<usize>,
+}
+
+impl<S: Data, T: Data> LazyList<S, T> {
+    pub fn new<W: Widget<(S, T)> + 'static>(make_row: impl Fn() -> W + 'static) -> Self {
+        LazyList {
+            make_row: Box::new(move || WidgetPod::new(Box::new(make_row()))),
+            rows: BTreeMap::new(),
+            spacing: 0.0,
+            row_height: MIN_ROW_HEIGHT,
+            // Roughly a window's worth until the first scroll reports the real area.
+            visible: Rect::new(0.0, 0.0, 0.0, 1000.0),
+            is_marked: None,
+            marked: None,
+        }
+    }
+
+    pub fn with_spacing(mut self, spacing: f64) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Scrolls to the first row `is_marked` picks out whenever a different row or none was
+    /// picked out before, whether or not it has been built.
+    pub fn scroll_to_marked(mut self, is_marked: fn(&S, &T) -> bool) -> Self {
+        self.is_marked = Some(is_marked);
+        self
+    }
+
+    fn slot_height(&self) -> f64 {
+        self.row_height + self.spacing
+    }
+
+    fn slot_rect(&self, index: usize, width: f64) -> Rect {
+        let top = index as f64 * self.slot_height();
+        Rect::new(0.0, top, width, top + self.row_height)
+    }
+
+    /// Indices of the rows in or near the visible area.
+    fn wanted(&self, len: usize) -> Range<usize> {
+        let slot = self.slot_height();
+        let first = ((self.visible.y0 / slot).floor().max(0.0) as usize).saturating_sub(OVERSCAN);
+        let end = ((self.visible.y1 / slot).ceil().max(0.0) as usize + OVERSCAN).min(len);
+        first.min(end)..end
+    }
+
+    /// Builds the rows that came into range and drops the ones that left it. True if any did.
+    fn sync_rows(&mut self, len: usize) -> bool {
+        let wanted = self.wanted(len);
+        let before = self.rows.len();
+        self.rows.retain(|index, _| wanted.contains(index));
+        let mut changed = self.rows.len() != before;
+        for index in wanted {
+            self.rows.entry(index).or_insert_with(|| {
+                changed = true;
+                (self.make_row)()
+            });
+        }
+        changed
+    }
+}
+
+/// The data handed to row `index`.
+fn row_data<S: Clone, T: Clone>((settings, items): &(S, Arc<Vec<T>>), index: usize) -> (S, T) {
+    (settings.clone(), items[index].clone())
+}
+
+impl<S: Data, T: Data> Widget<(S, Arc<Vec<T>>)> for LazyList<S, T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut (S, Arc<Vec<T>>), env: &Env) {
+        // Rows only act through commands, so their copies of the data are never written back.
+        for (&index, row) in self.rows.iter_mut().filter(|(_, row)| row.is_initialized()) {
+            row.event(ctx, event, &mut row_data(data, index), env);
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &(S, Arc<Vec<T>>),
+        env: &Env,
+    ) {
+        let in_view_may_change = match event {
+            LifeCycle::WidgetAdded => true,
+            LifeCycle::ViewContextChanged(view) => {
+                self.visible = view.clip;
+                true
+            }
+            _ => false,
+        };
+        if in_view_may_change && self.sync_rows(data.1.len()) {
+            ctx.children_changed();
+            ctx.request_layout();
+        }
+        // Rows built since the last pass only take part once they've been added.
+        let adding = matches!(
+            event,
+            LifeCycle::WidgetAdded | LifeCycle::Internal(druid::InternalLifeCycle::RouteWidgetAdded)
+        );
+        for (&index, row) in self.rows.iter_mut() {
+            if adding || row.is_initialized() {
+                row.lifecycle(ctx, event, &row_data(data, index), env);
+            }
+        }
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &(S, Arc<Vec<T>>),
+        data: &(S, Arc<Vec<T>>),
+        env: &Env,
+    ) {
+        let changed = !old_data.0.same(&data.0) || !old_data.1.same(&data.1);
+        if let Some(is_marked) = self.is_marked.filter(|_| changed) {
+            let marked = data.1.iter().position(|item| is_marked(&data.0, item));
+            if let Some(index) = marked.filter(|_| marked != self.marked) {
+                // Placed by the heights measured so far, before they are reset below.
+                ctx.scroll_area_to_view(self.slot_rect(index, ctx.size().width));
+            }
+            self.marked = marked;
+        }
+        if changed {
+            // What the rows show may have changed, so measure them afresh.
+            self.row_height = MIN_ROW_HEIGHT;
+            ctx.request_layout();
+        }
+        if self.sync_rows(data.1.len()) {
+            ctx.children_changed();
+            ctx.request_layout();
+        }
+        for (&index, row) in self.rows.iter_mut().filter(|(_, row)| row.is_initialized()) {
+            row.update(ctx, &row_data(data, index), env);
+        }
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &(S, Arc<Vec<T>>),
+        env: &Env,
+    ) -> Size {
+        let mut width = bc.min().width;
+        // A row taller than the rest raises the height for all of them and they are laid out
+        // again. Heights only grow, so this settles after at most one extra pass.
+        loop {
+            let row_bc = BoxConstraints::new(
+                Size::new(bc.min().width, self.row_height),
+                Size::new(bc.max().width, f64::INFINITY),
+            );
+            let mut tallest = self.row_height;
+            for (&index, row) in self.rows.iter_mut().filter(|(_, row)| row.is_initialized()) {
+                let size = row.layout(ctx, &row_bc, &row_data(data, index), env);
+                tallest = tallest.max(size.height);
+                width = width.max(size.width);
+            }
+            if tallest <= self.row_height {
+                break;
+            }
+            self.row_height = tallest;
+        }
+        let slot = self.slot_height();
+        for (&index, row) in self.rows.iter_mut().filter(|(_, row)| row.is_initialized()) {
+            row.set_origin(ctx, Point::new(0.0, index as f64 * slot));
+        }
+        let height = (data.1.len() as f64 * slot - self.spacing).max(0.0);
+        bc.constrain(Size::new(width, height))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &(S, Arc<Vec<T>>), env: &Env) {
+        for (&index, row) in self.rows.iter_mut().filter(|(_, row)| row.is_initialized()) {
+            row.paint(ctx, &row_data(data, index), env);
+        }
+    }
+}
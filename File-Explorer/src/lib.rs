@@ -0,0 +1,9 @@
+//! The search engine behind the file explorer, and the file handling it can test without a
+//! window, kept free of any UI code so the window, the `--serve` endpoint and tests can all
+//! drive it.
+
+pub mod git_status;
+pub mod mime;
+pub mod rename;
+pub mod search;
+pub mod template;
@@ -1,202 +1,7962 @@
-use druid::widget::{Button, Flex, Label, List, Scroll, TextBox};
+use druid::widget::{
+    Button, Checkbox, Controller, CrossAxisAlignment, Either, EnvScope, FillStrat, Flex, Image,
+    Label, List, ListIter, Painter, Maybe, ProgressBar, RadioGroup, Scroll, SizedBox, TextBox,
+};
+use druid::text::ParseFormatter;
 use druid::{
-    AppDelegate, AppLauncher, Command, Data, DelegateCtx, Env, Lens, Selector, Target,
-    Widget, WidgetExt, WindowDesc, commands, FileDialogOptions, theme, Color,
+    lens, AppDelegate, AppLauncher, Command, Data, DelegateCtx, Env, Lens, Selector, Target,
+    Widget, WidgetExt, WindowDesc, WindowId, WidgetPod, Point, commands, FileDialogOptions, Color, ExtEventSink, LifeCycle,
+    LensExt, LifeCycleCtx, RenderContext, UpdateCtx, EventCtx, Event, TimerToken, HotKey, SysMods,
+    BoxConstraints, LayoutCtx, PaintCtx, Size, KbKey, ImageBuf, Menu, MenuItem, Vec2, SingleUse,
 };
-use regex::Regex;
+use std::any::Any;
+use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
-use walkdir::WalkDir;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant, SystemTime};
 use std::fs;
+use std::io::Read;
 
-#[cfg(target_os = "macos")]
-fn open_path(path: &str) {
-    std::process::Command::new("open")
-        .arg(path)
-        .spawn()
-        .expect("failed to open file");
+mod config;
+mod digest;
+mod disk_image;
+mod duplicates;
+#[cfg(unix)]
+mod eject;
+mod keyboard;
+mod lazy_list;
+mod palette;
+mod pinned;
+mod reveal;
+mod serve;
+mod theme;
+mod watch;
+
+use config::{
+    Config, FolderPlacement, FolderSettings, RowClickAction, SearchFilters, SearchPreset,
+    SessionState, SortKey, Workspace,
+};
+use file_explorer::git_status::{self, GitStatus};
+use file_explorer::rename;
+use file_explorer::search::{
+    self, parse_date, LinkTarget, PermissionFilter, SearchError, SearchMode, SearchOptions,
+    SearchResult,
+};
+use file_explorer::template;
+use keyboard::KeyboardActivate;
+use lazy_list::LazyList;
+use palette::{PaletteAction, PaletteState};
+use pinned::Pinned;
+use reveal::Revealed;
+use theme::ThemeSettings;
+use watch::DirWatcher;
+
+/// Opens `path` with the user's open command, falling back to the platform default
+/// when the setting is blank. Internet shortcuts open the page they point to instead.
+fn open_path(open_command: &str, path: &Path) {
+    let template = if open_command.trim().is_empty() {
+        config::default_open_command()
+    } else {
+        open_command.to_string()
+    };
+    let url = bookmark_url(path);
+    let target = url.as_deref().map_or(path, Path::new);
+    let launched = template::expand(&template, &[("{}", target.as_os_str())], &["{}"])
+        .and_then(|(program, args)| {
+            std::process::Command::new(&program).args(&args).spawn().map_err(|err| err.to_string())
+        });
+    if let Err(err) = launched {
+        eprintln!("failed to open {}: {}", path.display(), err);
+    }
+}
+
+/// The address in a Windows `.url` shortcut or a macOS `.webloc` bookmark, or None if the file
+/// isn't one or can't be read. Only XML `.webloc` files are understood; binary ones fall back to
+/// opening the file itself.
+fn bookmark_url(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    if extension != "url" && extension != "webloc" {
+        return None;
+    }
+    let text = fs::read_to_string(path).ok()?;
+    let url = if extension == "url" {
+        // An INI file, with the address as `URL=` under `[InternetShortcut]`.
+        text.lines()
+            .filter_map(|line| line.trim().split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("url"))
+            .map(|(_, url)| url.trim().to_string())?
+    } else {
+        // A property list: `<key>URL</key>` followed by `<string>…</string>`.
+        let after_key = &text[text.find("<key>URL</key>")? + "<key>URL</key>".len()..];
+        let start = after_key.find("<string>")? + "<string>".len();
+        let end = after_key[start..].find("</string>")? + start;
+        after_key[start..end]
+            .trim()
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&")
+    };
+    // Anything without a scheme, like a bare word, is more likely a damaged file than a link.
+    let scheme = url.split_once(':')?.0;
+    let valid = !scheme.is_empty()
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    Some(url).filter(|_| valid)
 }
 
-#[cfg(target_os = "windows")]
-fn open_path(path: &str) {
-    std::process::Command::new("explorer")
-        .arg(path)
-        .spawn()
-        .expect("failed to open file");
+/// Opens `path` at `line` with the editor command, or like `open_path` when none is set.
+fn open_at_line(editor_command: &str, open_command: &str, path: &Path, line: usize) {
+    if editor_command.trim().is_empty() {
+        return open_path(open_command, path);
+    }
+    let line_text = OsString::from(line.to_string());
+    let values = [("{path}", path.as_os_str()), ("{line}", line_text.as_os_str())];
+    let launched = template::expand(editor_command, &values, &["{path}"]).and_then(|(program, args)| {
+        std::process::Command::new(&program).args(&args).spawn().map_err(|err| err.to_string())
+    });
+    if let Err(err) = launched {
+        eprintln!("failed to open {} at line {}: {}", path.display(), line, err);
+    }
 }
 
+// Matches found so far by a running search and the directories it has walked, tagged with its
+// generation.
+const SEARCH_PROGRESS: Selector<(u64, usize, usize)> = Selector::new("search_progress");
+
+// How many directories a finished walk went through, by `coverage_key`, for estimating the
+// next one.
+const WALK_SIZE: Selector<(String, usize)> = Selector::new("walk_size");
+
+// How many folders and files a finished search could only read after retrying, for the
+// activity log.
+const WALK_RETRIES: Selector<usize> = Selector::new("walk_retries");
+
+// Reports a search that couldn't run, tagged with its generation like UPDATE_SEARCH_RESULTS.
+const SEARCH_FAILED: Selector<(u64, String)> = Selector::new("search_failed");
+
 // A selector for updating search results from a background thread.
-// Note: Now the payload is an Arc<Vec<String>>
-const UPDATE_SEARCH_RESULTS: Selector<Arc<Vec<String>>> =
+// The payload is tagged with the search generation it belongs to, so results from a
+// cancelled or superseded search can be dropped.
+const UPDATE_SEARCH_RESULTS: Selector<(u64, Arc<Vec<ResultEntry>>)> =
     Selector::new("update_search_results");
 
-#[derive(Clone, Data, Lens)]
-struct AppState {
-    pub root_path: String,
-    pub search_term: String,
-    // Change from im::Vector<String> to Arc<Vec<String>> for compatibility with ListIter
-    pub search_results: Arc<Vec<String>>,
+// Opens a file at a line, for content-search matches.
+const OPEN_AT_LINE: Selector<(PathBuf, usize)> = Selector::new("open_at_line");
+
+// Lists a directory in the in-app browse view.
+const BROWSE_TO: Selector<PathBuf> = Selector::new("browse_to");
+
+// Shows a result selected in the system file manager.
+const REVEAL_IN_FILE_MANAGER: Selector<PathBuf> = Selector::new("reveal_in_file_manager");
+
+// Highlights a result row without opening it.
+const SELECT_PATH: Selector<PathBuf> = Selector::new("select_path");
+
+// Browses to a result's parent directory with the result highlighted.
+const REVEAL_IN_APP: Selector<PathBuf> = Selector::new("reveal_in_app");
+
+// Progress of an "open all" batch as (opening now, total); None once the batch is finished.
+const OPEN_ALL_PROGRESS: Selector<Option<(usize, usize)>> = Selector::new("open_all_progress");
+
+// Pause between spawns so opening many results doesn't hammer the system.
+const OPEN_ALL_DELAY: Duration = Duration::from_millis(300);
+
+// Moves a result to the OS trash/recycle bin.
+const TRASH_PATH: Selector<PathBuf> = Selector::new("trash_path");
+
+// Permanently deletes a result, after confirmation.
+const DELETE_PATH: Selector<PathBuf> = Selector::new("delete_path");
+
+// Opens a result with the configured open command.
+const OPEN_PATH: Selector<PathBuf> = Selector::new("open_path");
+
+// Runs the user's command template on a result.
+const RUN_COMMAND: Selector<PathBuf> = Selector::new("run_command");
+
+// Adds a file to the ones marked for comparing, or takes it off.
+const TOGGLE_MARK: Selector<PathBuf> = Selector::new("toggle_mark");
+
+// Lists every entry under the root that is a hard link to the same inode.
+#[cfg(unix)]
+const FIND_HARD_LINKS: Selector<PathBuf> = Selector::new("find_hard_links");
+
+// Ejects the drive mounted at a directory result, on a background thread.
+#[cfg(unix)]
+const EJECT_DRIVE: Selector<PathBuf> = Selector::new("eject_drive");
+
+// Reports a drive `EJECT_DRIVE` ejected, by its mount point.
+#[cfg(unix)]
+const DRIVE_EJECTED: Selector<PathBuf> = Selector::new("drive_ejected");
+
+// Opens a disk image, archive or mount point result: mounting an image and browsing into it,
+// handing an archive to the system, and browsing into a mount point.
+const OPEN_CONTAINER: Selector<PathBuf> = Selector::new("open_container");
+
+// Reports a disk image `OPEN_CONTAINER` mounted, with where it was mounted.
+const DISK_IMAGE_MOUNTED: Selector<(PathBuf, PathBuf)> = Selector::new("disk_image_mounted");
+
+// Makes a directory result the new root, re-running the search there if enabled.
+const SEARCH_IN: Selector<PathBuf> = Selector::new("search_in");
+
+// Clears one filter from the chip bar, re-running the search if there is one.
+const CLEAR_FILTER: Selector<FilterKind> = Selector::new("clear_filter");
+
+// Adds the tag typed next to "Only tagged" to a result, or takes it off again.
+const TOGGLE_TAG: Selector<PathBuf> = Selector::new("toggle_tag");
+
+// Searches a file's folder for names sharing its stem, to show the files that go with it.
+const FIND_RELATED: Selector<PathBuf> = Selector::new("find_related");
+
+// Copies a saved preset's fields into the search controls.
+const LOAD_PRESET: Selector<SearchPreset> = Selector::new("load_preset");
+
+// Switches the theme, filters and view options to a saved workspace.
+const LOAD_WORKSPACE: Selector<Workspace> = Selector::new("load_workspace");
+
+// Goes back to the drill-down step at this index, dropping it and every step after it.
+const POP_SEARCH_STEP: Selector<usize> = Selector::new("pop_search_step");
+
+// Pins a file or folder to the bookmarks bar, or unpins it.
+const ADD_BOOKMARK: Selector<PathBuf> = Selector::new("add_bookmark");
+const REMOVE_BOOKMARK: Selector<String> = Selector::new("remove_bookmark");
+
+// Opens a bookmark: folders become the root, files open with the system.
+const OPEN_BOOKMARK: Selector<String> = Selector::new("open_bookmark");
+
+// Re-runs the current search (or listing) in place, e.g. when the watched directory changes.
+const REFRESH_SEARCH: Selector<()> = Selector::new("refresh_search");
+
+// A watcher set up in the background for `WatchRoot`, tagged with the setup it answers.
+const WATCHER_READY: Selector<SingleUse<(u64, Result<DirWatcher, String>)>> =
+    Selector::new("watcher_ready");
+
+// Runs a search like the Search button, from Enter in the search box.
+const RUN_SEARCH: Selector<()> = Selector::new("run_search");
+
+// Moves keyboard focus into the search box, see `refocus_search_box`.
+const FOCUS_SEARCH_BOX: Selector<()> = Selector::new("focus_search_box");
+
+// Entry counts of directory results, counted in the background for "Show folder item counts".
+const DIR_ITEM_COUNTS: Selector<Vec<(PathBuf, usize)>> = Selector::new("dir_item_counts");
+
+// Git status of file results inside a working copy, looked up in the background for "Git status".
+const GIT_STATUSES: Selector<(u64, Vec<(PathBuf, GitStatus)>)> = Selector::new("git_statuses");
+
+// A file row came into view without a checksum for its modification time, see `hash_next_file`.
+const WANT_CHECKSUM: Selector<(PathBuf, Option<SystemTime>)> = Selector::new("want_checksum");
+
+// A file's short checksum, for the modification time it was read at; empty if unreadable and
+// None if hashing was cancelled, when nothing is remembered.
+const CHECKSUM_READY: Selector<(PathBuf, Option<SystemTime>, Option<String>)> =
+    Selector::new("checksum_ready");
+
+// What the preview pane shows for a file, read in the background: a line of text or the file's
+// first lines, and for images the decoded picture.
+const FILE_PREVIEW: Selector<(PathBuf, String, Option<ImageBuf>)> = Selector::new("file_preview");
+
+// Reports a failure to the user; usable from background threads.
+const REPORT_ERROR: Selector<String> = Selector::new("report_error");
+
+// Progress of a duplicate scan as (files hashed, files to hash).
+const DUPLICATE_PROGRESS: Selector<(usize, usize)> = Selector::new("duplicate_progress");
+
+// Starts another explorer window rooted at a folder.
+const OPEN_IN_NEW_WINDOW: Selector<PathBuf> = Selector::new("open_in_new_window");
+
+// Asks for the name of a new file or folder to create inside a folder result.
+const NEW_ITEM_IN: Selector<PathBuf> = Selector::new("new_item_in");
+
+// Copies the names of a folder's entries to the clipboard, one per line.
+const COPY_LISTING: Selector<PathBuf> = Selector::new("copy_listing");
+
+// Computes the SHA-256 of a file on a background thread.
+const HASH_FILE: Selector<PathBuf> = Selector::new("hash_file");
+
+// (percent hashed, 100) while a file is being hashed.
+const HASH_PROGRESS: Selector<(usize, usize)> = Selector::new("hash_progress");
+
+// The hashed file and its hex digest, or why it couldn't be read.
+const FILE_HASHED: Selector<(PathBuf, Result<String, String>)> = Selector::new("file_hashed");
+
+// (destination, files moved there, "file: error" for the rest) once "Move into folder…" is done.
+const FILES_MOVED: Selector<(PathBuf, Vec<PathBuf>, Vec<String>)> = Selector::new("files_moved");
+
+// (destination, how many files were copied there, "file: error" for the rest) once
+// "Copy into folder…" is done.
+const FILES_COPIED: Selector<(PathBuf, usize, Vec<String>)> = Selector::new("files_copied");
+
+// Clusters of identical files found by a duplicate scan.
+const DUPLICATES_FOUND: Selector<Arc<Vec<DuplicateGroup>>> = Selector::new("duplicates_found");
+
+// Queues every file of a duplicate cluster except the first for trashing, pending review.
+const TRASH_DUPLICATES: Selector<Arc<Vec<ResultEntry>>> = Selector::new("trash_duplicates");
+
+// How long rows that appeared or disappeared in a watch-mode refresh stay tinted.
+const CHANGE_HIGHLIGHT: Duration = Duration::from_secs(3);
+
+// Repaint interval while change highlights are fading.
+const CHANGE_FADE_TICK: Duration = Duration::from_millis(100);
+
+// Rough number of characters that fit on a result row before shortened paths are cut.
+const PATH_MAX_CHARS: usize = 80;
+
+// Hard cap on a row's text, so one pathological path can't blow up the list layout.
+const ROW_TEXT_MAX_CHARS: usize = 400;
+
+// Directory entry counts stop here, so a huge folder can't hold up the others.
+const ITEM_COUNT_LIMIT: usize = 10_000;
+
+// Ages below which "Highlight recent changes" tints a row brightly or faintly.
+const RECENT_HOUR: Duration = Duration::from_secs(60 * 60);
+const RECENT_DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Size past which the results log is moved aside and a new one started.
+const RESULTS_LOG_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+// How much of a file the preview pane reads, how many of its lines it shows, and how tall it is.
+const PREVIEW_BYTES: u64 = 16 * 1024;
+const PREVIEW_LINES: usize = 40;
+const PREVIEW_HEIGHT: f64 = 200.0;
+
+// Extensions the preview pane shows as pictures instead of showing their bytes, and the largest
+// image file it decodes for that.
+const PREVIEW_IMAGE_TYPES: [&str; 7] = ["PNG", "JPG", "JPEG", "GIF", "BMP", "WEBP", "TIFF"];
+const PREVIEW_IMAGE_MAX_BYTES: u64 = 20 * 1024 * 1024;
+
+// Folders below the root at which "Color by depth" reaches its deepest hue.
+const DEPTH_COLOR_LEVELS: usize = 8;
+
+// Widths of the fixed columns in the column layout; the folder column takes the rest.
+const NAME_COLUMN_WIDTH: f64 = 240.0;
+const SIZE_COLUMN_WIDTH: f64 = 90.0;
+const MODIFIED_COLUMN_WIDTH: f64 = 160.0;
+const CHECKSUM_COLUMN_WIDTH: f64 = 90.0;
+
+// Hex digits of each file's SHA-256 shown by "Checksums"; enough to tell files apart by eye.
+const CHECKSUM_CHARS: usize = 8;
+
+// Checksums remembered at most; past this only those of the current results are kept.
+const CHECKSUM_CACHE_LIMIT: usize = 20_000;
+
+// Extensions listed by name in the summary; the rest are only counted.
+const SUMMARY_TYPES: usize = 6;
+
+// Files kept in the "Recent" list.
+const RECENT_FILES_LIMIT: usize = 10;
+
+// Entries kept in the activity log; older ones are dropped.
+const ACTIVITY_LIMIT: usize = 200;
+
+// Parent folders a search that finds nothing may widen to when `auto_widen` is on.
+const AUTO_WIDEN_LEVELS: usize = 3;
+
+// A row's full path and whether the pointer just entered (true) or left (false) it.
+const HOVER_PATH: Selector<(String, bool)> = Selector::new("hover_path");
+
+/// A search hit as shown in the results list.
+#[derive(Clone, Data)]
+struct ResultEntry {
+    // The folder holding the entry, shared by every result in it, and the entry's name there,
+    // see `path`. Both are kept as-is so names that aren't valid UTF-8 still open correctly.
+    // Large result lists from a few folders then carry each folder's path once, not per entry.
+    #[data(same_fn = "PartialEq::eq")]
+    pub parent: Arc<PathBuf>,
+    #[data(same_fn = "PartialEq::eq")]
+    pub name: OsString,
+    pub is_dir: bool,
+    // First line that matched in content search, already cleaned up for display.
+    pub match_line: Option<String>,
+    // 1-based number of that line, and how many lines matched in total.
+    pub match_line_number: Option<usize>,
+    pub match_count: usize,
+    // Typos between the term and the name in an approximate search; None otherwise.
+    pub edit_distance: Option<usize>,
+    // Size in bytes of a file; None for directories.
+    pub size: Option<u64>,
+    #[data(same_fn = "PartialEq::eq")]
+    pub modified: Option<SystemTime>,
+    #[data(same_fn = "PartialEq::eq")]
+    pub accessed: Option<SystemTime>,
+    // Set when a watch-mode refresh added or removed this entry, with the time it happened.
+    #[data(same_fn = "PartialEq::eq")]
+    pub change: Option<(RowChange, Instant)>,
+    // Where a symlink points, filled in only when resolving links is on.
+    #[data(same_fn = "PartialEq::eq")]
+    pub link: Option<LinkTarget>,
+    // The type sniffed from the contents, when the search filtered on it.
+    #[data(same_fn = "PartialEq::eq")]
+    pub mime: Option<&'static str>,
+    // A folder another drive is mounted on, worked out with the entry so rows never stat.
+    pub mount_point: bool,
+    // A zero-byte file or a folder with nothing in it, as "Empty only" keeps, see `is_empty`.
+    pub empty: bool,
 }
 
-fn build_ui() -> impl Widget<AppState> {
-    // Use string literals for the buttons instead of Label::new(...)
-    let choose_dir_btn = Button::new("Choose Directory")
-        .padding(8.0)
-        .background(Color::rgb8(0x44, 0x44, 0x44))
-        .on_click(|ctx, _data, _env| {
-            ctx.submit_command(Command::new(commands::SHOW_OPEN_PANEL, FileDialogOptions::default(), Target::Auto));
-        });
+/// One active filter in the chip bar above the results; clearing it resets just that filter.
+#[derive(Clone, Copy, Data, PartialEq)]
+enum FilterKind {
+    Term,
+    OrTerms,
+    CaseSensitive,
+    AllWords,
+    TermFilters,
+    StemOnly,
+    Approximate,
+    FullPath,
+    Contents,
+    Extensions,
+    MimeTypes,
+    ExcludeDirs,
+    HideHidden,
+    NotRecursive,
+    MaxDepth,
+    ModifiedSinceStart,
+    CreatedAfter,
+    CreatedBefore,
+    Permissions,
+    MatchLinkTargets,
+    BrokenLinksOnly,
+    EmptyOnly,
+    StaleOnly,
+    LargestOnly,
+    StayOnFilesystem,
+}
 
-    let search_btn = Button::new("Search")
-        .padding(8.0)
-        .background(Color::rgb8(0x44, 0x44, 0x44))
-        .on_click(|ctx, data: &mut AppState, _env| {
-            let root = data.root_path.clone();
-            let term = data.search_term.clone();
+impl FilterKind {
+    fn clear(self, data: &mut AppState) {
+        match self {
+            FilterKind::Term => data.search_term.clear(),
+            FilterKind::OrTerms => data.or_terms = Arc::new(Vec::new()),
+            FilterKind::CaseSensitive => data.case_sensitive = false,
+            FilterKind::AllWords => data.all_words = false,
+            FilterKind::TermFilters => data.term_filters = false,
+            FilterKind::StemOnly => data.stem_only = false,
+            FilterKind::Approximate => data.approximate = false,
+            FilterKind::FullPath => data.match_full_path = false,
+            FilterKind::Contents => data.search_contents = false,
+            FilterKind::Extensions => data.extensions.clear(),
+            FilterKind::MimeTypes => data.sniff_types = false,
+            FilterKind::ExcludeDirs => data.exclude_dirs.clear(),
+            FilterKind::HideHidden => data.show_hidden = true,
+            FilterKind::NotRecursive => data.recursive = true,
+            FilterKind::MaxDepth => data.max_depth = 0,
+            FilterKind::ModifiedSinceStart => data.modified_since_start = false,
+            FilterKind::CreatedAfter => data.created_after.clear(),
+            FilterKind::CreatedBefore => data.created_before.clear(),
+            FilterKind::Permissions => data.permission_filter = 0,
+            FilterKind::MatchLinkTargets => data.match_link_targets = false,
+            FilterKind::BrokenLinksOnly => data.broken_links_only = false,
+            FilterKind::EmptyOnly => data.empty_only = false,
+            FilterKind::StaleOnly => data.stale_only = false,
+            FilterKind::LargestOnly => data.largest_only = false,
+            FilterKind::StayOnFilesystem => data.stay_on_filesystem = false,
+        }
+    }
+}
 
-            // Clear any previous search results.
-            data.search_results = Arc::new(Vec::new());
+#[derive(Clone, Data, PartialEq)]
+struct FilterChip {
+    kind: FilterKind,
+    label: String,
+}
 
-            let sink = ctx.get_external_handle();
+/// The chips in the bar, worked out from the filters on every update. They are compared by
+/// what they say, so the bar isn't rebuilt each time the list is.
+#[derive(Clone, PartialEq)]
+struct FilterChips(Vec<FilterChip>);
 
-            thread::spawn(move || {
-                let results = search_files(&root, &term);
-                // Send the search results back to the UI thread.
-                sink.submit_command(UPDATE_SEARCH_RESULTS, results, Target::Auto)
-                    .expect("Failed to submit command");
-            });
+impl Data for FilterChips {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl ListIter<FilterChip> for FilterChips {
+    fn for_each(&self, mut cb: impl FnMut(&FilterChip, usize)) {
+        for (i, chip) in self.0.iter().enumerate() {
+            cb(chip, i);
+        }
+    }
+
+    fn for_each_mut(&mut self, mut cb: impl FnMut(&mut FilterChip, usize)) {
+        for (i, chip) in self.0.iter_mut().enumerate() {
+            cb(chip, i);
+        }
+    }
+
+    fn data_len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// How the last search ended, so an empty list can say why it is empty.
+#[derive(Clone, Data, PartialEq)]
+enum SearchOutcome {
+    NotRun,
+    Completed,
+    Failed(String),
+}
+
+fn sort_results(results: &mut [ResultEntry], key: SortKey, dirs: FolderPlacement) {
+    let placement = |a: &ResultEntry, b: &ResultEntry| match dirs {
+        FolderPlacement::Top => b.is_dir.cmp(&a.is_dir),
+        FolderPlacement::Bottom => a.is_dir.cmp(&b.is_dir),
+        FolderPlacement::Mixed => std::cmp::Ordering::Equal,
+    };
+    let primary = |a: &ResultEntry, b: &ResultEntry| match key {
+        SortKey::Found => std::cmp::Ordering::Equal,
+        SortKey::Name => a.name.cmp(&b.name),
+        SortKey::Size => b.size.cmp(&a.size),
+        SortKey::Modified => b.modified.cmp(&a.modified),
+        SortKey::Oldest => a.modified.cmp(&b.modified),
+        SortKey::Accessed => b.last_activity().cmp(&a.last_activity()),
+        SortKey::Path => std::cmp::Ordering::Equal,
+    };
+    if key == SortKey::Found {
+        // Stable, so each part keeps the order the search found it in.
+        results.sort_by(placement);
+    } else {
+        results.sort_by(|a, b| {
+            placement(a, b)
+                .then_with(|| primary(a, b))
+                .then_with(|| a.cmp_path(b))
         });
+    }
+}
 
-    // TextBox: dark background and white text; uses lens for state binding
-    let directory_box = TextBox::new()
-        .with_placeholder("Enter directory path")
-        .with_text_size(14.0)
-        .with_text_color(Color::WHITE)
-        .padding(8.0)
-        .background(Color::rgb8(0x33, 0x33, 0x33))
-        .lens(AppState::root_path);
+/// Whether the files in `results` look like their access times aren't kept: none of them
+/// was accessed after it was modified. False when there are no files to tell by.
+fn access_times_look_stale(results: &[ResultEntry]) -> bool {
+    let mut files = results.iter().filter(|r| !r.is_dir).peekable();
+    files.peek().is_some()
+        && files.all(|r| match (r.accessed, r.modified) {
+            (Some(accessed), Some(modified)) => accessed <= modified,
+            (None, _) => true,
+            (Some(_), None) => false,
+        })
+}
 
-    let search_box = TextBox::new()
-        .with_placeholder("Enter search term")
-        .with_text_size(14.0)
-        .with_text_color(Color::WHITE)
-        .padding(8.0)
-        .background(Color::rgb8(0x33, 0x33, 0x33))
-        .lens(AppState::search_term);
+/// How a watch-mode refresh changed a result.
+#[derive(Clone, Copy, PartialEq)]
+enum RowChange {
+    Added,
+    // Removed entries stay in the list until their highlight has faded.
+    Removed,
+}
 
-    // List: style each item with white text, padding, dark background, border, and rounded corners.
-    let results_list = List::new(|| {
-        Label::new(|item: &String, _env: &_| format!("{}", item))
-            .with_text_size(14.0)
-            .with_text_color(Color::WHITE)
-            .padding(8.0)
-            .background(Color::rgb8(0x33, 0x33, 0x33))
-            .border(Color::rgb8(0x55, 0x55, 0x55), 1.0)
-            .rounded(4.0)
-            .on_click(|_ctx, item: &mut String, _env| {
-                open_path(item);
-            })
-    })
-    .with_spacing(4.0)
-    .lens(AppState::search_results);
+/// Hands out one shared copy of each folder path while a batch of results is built, see
+/// `ResultEntry::parent`.
+#[derive(Default)]
+struct SharedParents {
+    parents: HashMap<PathBuf, Arc<PathBuf>>,
+}
 
-    let scroll = Scroll::new(results_list)
-        .background(Color::BLACK)
-        .expand();
+impl SharedParents {
+    /// `path` split into its folder, shared with earlier paths in the same one, and its name.
+    /// A path without a name, like `/`, is kept whole as the folder with an empty name.
+    fn split(&mut self, path: PathBuf) -> (Arc<PathBuf>, OsString) {
+        let (parent, name) = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => (parent.to_path_buf(), name.to_os_string()),
+            _ => (path, OsString::new()),
+        };
+        let shared = self.parents.entry(parent).or_insert_with_key(|parent| Arc::new(parent.clone()));
+        (shared.clone(), name)
+    }
+}
 
-    // Main layout with black background
-    Flex::column()
-        .with_child(choose_dir_btn)
-        .with_child(directory_box)
-        .with_child(search_box)
-        .with_child(search_btn)
-        .with_flex_child(scroll, 1.0)
-        .padding(12.0)
-        .background(Color::BLACK)
-}
-
-/// Searches files and directories under the given directory whose names match the search term (case-insensitive)
-/// and returns an Arc<Vec<String>>.
-fn search_files(root_path: &str, search_term: &str) -> Arc<Vec<String>> {
-    let regex = Regex::new(&format!(r"(?i){}", search_term)).unwrap();
-    let root = PathBuf::from(root_path);
-    let results = search_files_recursive(&root, &regex);
-    Arc::new(results)
-}
-
-fn search_files_recursive(dir: &Path, regex: &Regex) -> Vec<String> {
-    let mut results = Vec::new();
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir).expect("read_dir call failed") {
-            if let Ok(entry) = entry {
-                if entry.path().is_file() || entry.path().is_dir() {
-                    if let Some(name) = entry.path().file_name().and_then(|n| n.to_str()) {
-                        if regex.is_match(name) {
-                            results.push(entry.path().display().to_string());
-                        }
-                    }
-                }
-            }
+impl ResultEntry {
+    /// An entry for `path`, with its folder not shared with any other. Use `from_search` for
+    /// whole result lists.
+    fn new(path: PathBuf) -> Self {
+        let metadata = fs::metadata(&path).ok();
+        let is_dir = metadata.as_ref().is_some_and(fs::Metadata::is_dir);
+        let size = metadata.as_ref().filter(|_| !is_dir).map(fs::Metadata::len);
+        let modified = metadata.as_ref().and_then(|metadata| metadata.modified().ok());
+        let accessed = metadata.and_then(|metadata| metadata.accessed().ok());
+        let mount_point = is_dir && is_mount_point(&path);
+        let empty = is_empty(&path, is_dir, size);
+        let (parent, name) = SharedParents::default().split(path);
+        ResultEntry {
+            mount_point,
+            empty,
+            parent,
+            name,
+            is_dir,
+            size,
+            modified,
+            accessed,
+            match_line: None,
+            match_line_number: None,
+            match_count: 0,
+            edit_distance: None,
+            change: None,
+            link: None,
+            mime: None,
+        }
+    }
+
+    /// An entry for a search result, sharing its folder through `parents` with the others
+    /// built from the same search.
+    fn from_search(result: SearchResult, parents: &mut SharedParents) -> Self {
+        let mount_point = result.is_dir && is_mount_point(&result.path);
+        let empty = is_empty(&result.path, result.is_dir, result.size);
+        let (parent, name) = parents.split(result.path);
+        ResultEntry {
+            mount_point,
+            empty,
+            parent,
+            name,
+            is_dir: result.is_dir,
+            match_line: result.match_line,
+            match_line_number: result.match_line_number,
+            match_count: result.match_count,
+            edit_distance: result.edit_distance,
+            size: result.size,
+            modified: result.modified,
+            accessed: result.accessed,
+            change: None,
+            link: result.link,
+            mime: result.mime,
+        }
+    }
+
+    /// The full path, joined from the folder and the name each time it is asked for.
+    fn path(&self) -> PathBuf {
+        if self.name.is_empty() {
+            self.parent.to_path_buf()
+        } else {
+            self.parent.join(&self.name)
+        }
+    }
+
+    /// Whether this is the entry at `path`, without building the full path.
+    fn is_at(&self, path: &Path) -> bool {
+        match path.file_name() {
+            Some(name) => name == self.name && path.parent() == Some(self.parent.as_path()),
+            None => self.name.is_empty() && path == self.parent.as_path(),
+        }
+    }
+
+    /// Orders entries as their full paths would be ordered, component by component.
+    fn cmp_path(&self, other: &ResultEntry) -> std::cmp::Ordering {
+        self.components().cmp(other.components())
+    }
+
+    fn components(&self) -> impl Iterator<Item = &OsStr> {
+        let name = Some(self.name.as_os_str()).filter(|name| !name.is_empty());
+        self.parent.components().map(|c| c.as_os_str()).chain(name)
+    }
+
+    /// Lossy UTF-8 rendering of `path`, for display only.
+    fn display(&self) -> String {
+        self.path().to_string_lossy().into_owned()
+    }
+
+    /// The path to act on when opening: a resolved symlink's target, else the path itself.
+    fn open_target(&self) -> PathBuf {
+        match &self.link {
+            Some(LinkTarget::Resolved(target)) => target.clone(),
+            _ => self.path(),
         }
     }
-    results
+
+    /// When the entry was last read or written: the later of its access and modification
+    /// times. Filesystems mounted `noatime` never move the access time past the modification
+    /// time, and some platforms don't report one, so this falls back to the modification time.
+    fn last_activity(&self) -> Option<SystemTime> {
+        self.accessed.max(self.modified)
+    }
+
+    fn is_removed(&self) -> bool {
+        matches!(self.change, Some((RowChange::Removed, _)))
+    }
 }
 
-/// A delegate to handle commands coming from the background thread.
-struct Delegate;
+/// Where a search ran before "Search here" narrowed it, so the breadcrumbs can go back to it.
+#[derive(Clone, Data)]
+struct SearchStep {
+    pub root: String,
+    pub term: String,
+}
 
-impl AppDelegate<AppState> for Delegate {
-    fn command(
-        &mut self,
-        _ctx: &mut DelegateCtx,
-        _target: Target,
-        cmd: &Command,
-        data: &mut AppState,
-        _env: &Env,
-    ) -> druid::Handled {
-        if let Some(results) = cmd.get(UPDATE_SEARCH_RESULTS) {
-            data.search_results = results.clone();
-            return druid::Handled::Yes;
+impl SearchStep {
+    /// Breadcrumb text: the folder's name, and the term searched for there if there was one.
+    fn label(&self) -> String {
+        let path = Path::new(&self.root);
+        let name = path.file_name().map_or(self.root.clone(), |name| {
+            name.to_string_lossy().to_string()
+        });
+        if self.term.is_empty() {
+            name
+        } else {
+            format!("{} “{}”", name, self.term)
         }
-        if cmd.is(commands::SHOW_OPEN_PANEL) {
-            let dialog = rfd::FileDialog::new();
-            if let Some(folder) = dialog.pick_folder() {
-                data.root_path = folder.to_string_lossy().to_string();
-                data.search_results = Arc::new(Vec::new());
-                return druid::Handled::Yes;
+    }
+}
+
+/// Files found to have identical contents.
+#[derive(Clone, Data, Lens)]
+struct DuplicateGroup {
+    // Size of each file in the group, in bytes.
+    pub size: u64,
+    pub files: Arc<Vec<ResultEntry>>,
+}
+
+/// Destructive operations on several paths, listed for review before anything happens.
+#[derive(Clone, Data, Lens)]
+struct PendingBatch {
+    // Delete outright instead of moving to the trash.
+    pub permanent: bool,
+    // Move the files into this folder instead, see `move_into`.
+    #[data(same_fn = "PartialEq::eq")]
+    pub destination: Option<PathBuf>,
+    // With `destination`, the folder whose layout is kept below it ("Keep folder structure").
+    #[data(same_fn = "PartialEq::eq")]
+    pub structure_root: Option<PathBuf>,
+    pub files: Arc<Vec<ResultEntry>>,
+    // Duplicate cluster the batch resolves, removed from the duplicates view once it runs.
+    pub group: Option<Arc<Vec<ResultEntry>>>,
+}
+
+/// A new file or folder waiting for its name in the panel above the results.
+#[derive(Clone, Data, Lens)]
+struct PendingCreate {
+    #[data(same_fn = "PartialEq::eq")]
+    pub dir: PathBuf,
+    pub name: String,
+    // Create a folder instead of an empty file.
+    pub folder: bool,
+}
+
+/// A rename of the marked entries waiting for review in the panel above the results.
+#[derive(Clone, Data, Lens)]
+struct PendingRename {
+    #[data(same_fn = "PartialEq::eq")]
+    pub files: Arc<Vec<PathBuf>>,
+    // Text replaced in each name, every time it appears; empty to leave names as they are.
+    pub find: String,
+    pub replace: String,
+    // Add `_1`, `_2`, … before each extension, in the order the entries were marked.
+    pub number: bool,
+}
+
+impl PendingRename {
+    /// Each entry with the path it would be renamed to, see `rename::plan`.
+    fn plan(&self) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+        rename::plan(&self.files, &self.find, &self.replace, self.number)
+    }
+
+    /// The old → new names shown for review, and what's wrong with them if anything.
+    fn preview(&self) -> String {
+        let plan = match self.plan() {
+            Ok(plan) => plan,
+            Err(problem) => return format!("Can't rename: {}.", problem),
+        };
+        let changes: Vec<String> = plan
+            .iter()
+            .filter(|(source, target)| source != target)
+            .map(|(source, target)| {
+                let name = |path: &Path| {
+                    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+                };
+                format!("{} → {}", name(source), name(target))
+            })
+            .collect();
+        let mut text = match changes.len() {
+            0 => "No names change yet.".to_string(),
+            count => format!("{} of {} names change:\n{}", count, plan.len(), changes.join("\n")),
+        };
+        if let Some(problem) = rename::problem(&plan) {
+            text = format!("{}\n\nCan't rename: {}.", text, problem);
+        }
+        text
+    }
+}
+
+impl PendingBatch {
+    fn summary(&self) -> String {
+        let count = self.files.len();
+        if let Some(destination) = &self.destination {
+            format!("Move these {} files into {}?", count, destination.display())
+        } else if self.permanent {
+            format!("Permanently delete these {} items? This can't be undone.", count)
+        } else {
+            format!("Move these {} items to the trash?", count)
+        }
+    }
+}
+
+/// One extension present in the results, offered as a checkbox to narrow the list.
+#[derive(Clone, Data, Lens)]
+struct ExtensionFacet {
+    // Lowercase, without the dot.
+    pub extension: String,
+    pub count: usize,
+    pub selected: bool,
+}
+
+/// Totals over a result set, shown in the "Summary" section above the list.
+#[derive(Clone, Data, Default)]
+struct SearchSummary {
+    pub files: usize,
+    pub folders: usize,
+    // Sum of the files' sizes, in bytes.
+    pub total_size: u64,
+    #[data(same_fn = "PartialEq::eq")]
+    pub oldest: Option<SystemTime>,
+    #[data(same_fn = "PartialEq::eq")]
+    pub newest: Option<SystemTime>,
+    // Display path and size of the biggest file.
+    pub largest: Option<(String, u64)>,
+    // Files per extension, most common first; extensionless files are counted under "other".
+    pub by_type: Arc<Vec<(String, usize)>>,
+}
+
+impl SearchSummary {
+    /// Gathers the totals in one pass over the results. Removed entries don't count.
+    fn of(results: &[ResultEntry]) -> Self {
+        let mut summary = SearchSummary::default();
+        let mut by_type: BTreeMap<String, usize> = BTreeMap::new();
+        for result in results.iter().filter(|r| !r.is_removed()) {
+            if let Some(modified) = result.modified {
+                summary.oldest = Some(summary.oldest.map_or(modified, |t| t.min(modified)));
+                summary.newest = Some(summary.newest.map_or(modified, |t| t.max(modified)));
             }
-            // Removed file selection to force folder-only selection.
+            if result.is_dir {
+                summary.folders += 1;
+                continue;
+            }
+            summary.files += 1;
+            let size = result.size.unwrap_or(0);
+            summary.total_size += size;
+            if summary.largest.as_ref().is_none_or(|(_, largest)| size > *largest) {
+                summary.largest = Some((result.display(), size));
+            }
+            let extension = facet_extension(result).unwrap_or_else(|| "other".to_string());
+            *by_type.entry(extension).or_default() += 1;
         }
-        druid::Handled::No
+        let mut by_type: Vec<(String, usize)> = by_type.into_iter().collect();
+        // Stable, so equal counts stay in name order.
+        by_type.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        summary.by_type = Arc::new(by_type);
+        summary
+    }
+
+    /// The summary as a few lines of text.
+    fn text(&self) -> String {
+        let mut lines = vec![format!(
+            "{} files, {} folders, {} in total",
+            self.files,
+            self.folders,
+            format_size(self.total_size)
+        )];
+        if !self.by_type.is_empty() {
+            let types: Vec<String> = self
+                .by_type
+                .iter()
+                .take(SUMMARY_TYPES)
+                .map(|(extension, count)| format!("{} {}", extension, count))
+                .collect();
+            let more = self.by_type.len().saturating_sub(SUMMARY_TYPES);
+            let more = if more > 0 { format!(" and {} more", more) } else { String::new() };
+            lines.push(format!("By type: {}{}", types.join(", "), more));
+        }
+        if let (Some(oldest), Some(newest)) = (self.oldest, self.newest) {
+            lines.push(format!(
+                "Modified between {} and {} UTC",
+                format_timestamp(oldest),
+                format_timestamp(newest)
+            ));
+        }
+        if let Some((path, size)) = &self.largest {
+            lines.push(format!("Largest: {} ({})", path, format_size(*size)));
+        }
+        lines.join("\n")
     }
 }
 
-fn main() {
-    // Create the main window.
-    let main_window = WindowDesc::new(build_ui()).title("macOS File Explorer");
+/// Results sharing a file name, shown under one header that expands to their paths.
+#[derive(Clone, Data, Lens)]
+struct NameGroup {
+    pub name: String,
+    pub expanded: bool,
+    pub files: Arc<Vec<ResultEntry>>,
+}
 
-    // Initialize the state with the current directory.
-    let initial_state = AppState {
-        root_path: std::env::current_dir()
-            .unwrap_or_else(|_| PathBuf::from("."))
-            .display()
-            .to_string(),
-        search_term: "".to_string(),
-        search_results: Arc::new(Vec::new()),
-    };
+/// Groups results by file name, in the order each name first appears. Groups that were
+/// expanded in `previous` stay expanded.
+fn group_by_name(results: &[ResultEntry], previous: &[NameGroup]) -> Vec<NameGroup> {
+    let expanded: HashSet<&str> =
+        previous.iter().filter(|group| group.expanded).map(|group| group.name.as_str()).collect();
+    let mut groups: Vec<NameGroup> = Vec::new();
+    let mut index_of: HashMap<String, usize> = HashMap::new();
+    for result in results.iter().filter(|r| !r.is_removed()) {
+        let name = result.name.to_string_lossy().to_string();
+        let index = *index_of.entry(name.clone()).or_insert_with(|| {
+            groups.push(NameGroup {
+                expanded: expanded.contains(name.as_str()),
+                name,
+                files: Arc::new(Vec::new()),
+            });
+            groups.len() - 1
+        });
+        Arc::make_mut(&mut groups[index].files).push(result.clone());
+    }
+    groups
+}
 
-    // Launch the application with the delegate to handle background commands.
-    AppLauncher::with_window(main_window)
-        .configure_env(|env: &mut Env, _| {
-            env.set(druid::theme::BACKGROUND_LIGHT, Color::BLACK);
-            env.set(druid::theme::TEXT_COLOR, Color::WHITE);
-            env.set(druid::theme::PLACEHOLDER_COLOR, Color::grey(0.6));
-            // Replace WIDGET_BACKGROUND_COLOR with WINDOW_BACKGROUND_COLOR
-            env.set(druid::theme::WINDOW_BACKGROUND_COLOR, Color::rgb8(0x33, 0x33, 0x33));
-            env.set(druid::theme::BUTTON_DARK, Color::rgb8(0x44, 0x44, 0x44));
+/// How many results lie under one of the root's immediate subdirectories.
+#[derive(Clone, Data, Lens)]
+struct SubdirCount {
+    pub name: String,
+    #[data(same_fn = "PartialEq::eq")]
+    pub path: PathBuf,
+    pub count: usize,
+}
+
+/// Buckets results by their first path component under `root`, busiest first. Entries directly
+/// in the root aren't in any subdirectory and aren't counted.
+fn count_by_subdir(root: &Path, results: &[ResultEntry]) -> Vec<SubdirCount> {
+    let mut counts: BTreeMap<&OsStr, usize> = BTreeMap::new();
+    for result in results.iter().filter(|r| !r.is_removed()) {
+        // Entries directly in the root have nothing left of their folder.
+        let mut components = match result.parent.strip_prefix(root) {
+            Ok(relative) => relative.components(),
+            Err(_) => continue,
+        };
+        if let Some(first) = components.next() {
+            *counts.entry(first.as_os_str()).or_default() += 1;
+        }
+    }
+    let mut buckets: Vec<SubdirCount> = counts
+        .into_iter()
+        .map(|(name, count)| SubdirCount {
+            name: name.to_string_lossy().to_string(),
+            path: root.join(name),
+            count,
         })
-        .delegate(Delegate)
+        .collect();
+    // Stable, so equal counts stay in name order.
+    buckets.sort_by_key(|bucket| std::cmp::Reverse(bucket.count));
+    buckets
+}
+
+/// The lowercase extension a result is grouped under; None for folders and extensionless files.
+fn facet_extension(entry: &ResultEntry) -> Option<String> {
+    if entry.is_dir {
+        return None;
+    }
+    let extension = Path::new(&entry.name).extension()?;
+    Some(extension.to_string_lossy().to_lowercase())
+}
+
+/// A result paired with the settings shared by every row, as handed to each list row.
+type ResultRow = (RowSettings, ResultEntry);
+
+/// Short checksums by path, with the modification time each file was hashed at so a changed
+/// file is hashed again.
+type ChecksumCache = HashMap<PathBuf, (Option<SystemTime>, String)>;
+
+/// View settings shared by every row of the results list.
+#[derive(Clone, Data)]
+struct RowSettings {
+    // Prefix stripped from each row's displayed path; empty when collapsing is off.
+    #[data(same_fn = "PartialEq::eq")]
+    pub strip_prefix: PathBuf,
+    // Path of the row to highlight and scroll to; empty for none.
+    #[data(same_fn = "PartialEq::eq")]
+    pub highlight: PathBuf,
+    pub marked: Arc<Vec<PathBuf>>,
+    pub browse_mode: bool,
+    // Show the first matching line under each content-search result.
+    pub show_match_line: bool,
+    // Cut long paths in the middle so the file name stays visible.
+    pub shorten_paths: bool,
+    // Mark each row with its file type's color.
+    pub color_by_type: bool,
+    // Tint rows modified in the last hour brightly and in the last day faintly.
+    pub color_by_age: bool,
+    // Folders searched, to stripe each row by how deep below them it is; empty when the
+    // option is off.
+    #[data(same_fn = "PartialEq::eq")]
+    pub depth_roots: Vec<PathBuf>,
+    // Give directory rows a heavier border in the folder color, to tell them from files.
+    pub mark_folders: bool,
+    // Entry counts to show after directory rows; None when the option is off.
+    pub dir_item_counts: Option<Arc<HashMap<PathBuf, usize>>>,
+    // Git status to show on file rows in a working copy; None when the option is off.
+    pub git_statuses: Option<Arc<HashMap<PathBuf, GitStatus>>>,
+    // Short checksums of the files hashed so far; None when "Checksums" is off.
+    pub checksums: Option<Arc<ChecksumCache>>,
+    // Lay rows out as name | size | modified | folder columns.
+    pub columns: bool,
+    pub click_action: RowClickAction,
+    // Show how many typos each approximate match needed.
+    pub show_edit_distance: bool,
+    pub tags: Arc<BTreeMap<PathBuf, Vec<String>>>,
+    pub tag_name: String,
+    // Show control characters in names as escapes like `\n`, see `escape_controls`.
+    pub escape_controls: bool,
+    // Keep only the last this many parts of each path; 0 for all.
+    pub display_components: usize,
+}
+
+impl RowSettings {
+    /// A path or name as it is displayed, escaped if `escape_controls` is on.
+    fn shown(&self, text: &str) -> String {
+        if self.escape_controls {
+            escape_controls(text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Text shown for a result row, with the shared prefix removed and shortened to fit the
+    /// row if enabled. Very long paths are always cut; the hover line shows them in full.
+    fn row_text(&self, item: &ResultEntry) -> String {
+        let full_path = item.path();
+        let path = match full_path.strip_prefix(&self.strip_prefix) {
+            Ok(suffix) if !self.strip_prefix.as_os_str().is_empty() => suffix,
+            _ => &full_path,
+        };
+        let mut text = match self.path_tail(path) {
+            Some(tail) => format!("…{}{}", std::path::MAIN_SEPARATOR, self.shown(&tail.to_string_lossy())),
+            None => self.shown(&path.to_string_lossy()),
+        };
+        match &item.link {
+            Some(LinkTarget::Resolved(target)) => {
+                text = format!("{} -> {}", text, self.shown(&target.to_string_lossy()))
+            }
+            Some(LinkTarget::Broken) => text = format!("{} -> (broken link)", text),
+            None => {}
+        }
+        let max_chars = if self.shorten_paths {
+            PATH_MAX_CHARS
+        } else {
+            ROW_TEXT_MAX_CHARS
+        };
+        let mut text = shorten_middle(&text, max_chars);
+        let item_count = self.dir_item_counts.as_ref().and_then(|counts| counts.get(&item.path()));
+        if let Some(&count) = item_count {
+            text = match count {
+                1 => format!("{} (1 item)", text),
+                ITEM_COUNT_LIMIT.. => format!("{} ({}+ items)", text, ITEM_COUNT_LIMIT),
+                _ => format!("{} ({} items)", text, count),
+            };
+        }
+        let tags = self.tags_of(item);
+        if !tags.is_empty() {
+            text = format!("{} [{}]", text, tags.join(", "));
+        }
+        // Clean files go unmarked, so changes stand out.
+        if let Some(status) = self.git_status(item).filter(|&status| status != GitStatus::Clean) {
+            text = format!("{} · {}", text, status.label());
+        }
+        if let Some(mime) = item.mime {
+            text = format!("{} · {}", text, mime);
+        }
+        if let Some(hash) = self.checksum(item).filter(|hash| !hash.is_empty()) {
+            text = format!("{} · {}", text, hash);
+        }
+        // Approximate matches say how far off they were, e.g. "config.rs · 1 typo".
+        if let Some(typos) = item.edit_distance.filter(|_| self.show_edit_distance) {
+            let plural = if typos == 1 { "" } else { "s" };
+            text = format!("{} · {} typo{}", text, typos, plural);
+        }
+        // Content results say how often and where they matched, e.g. "main.rs · 3 matches (line 42)".
+        match item.match_line_number {
+            Some(line) => {
+                let plural = if item.match_count == 1 { "" } else { "es" };
+                format!("{} · {} match{} (line {})", text, item.match_count, plural, line)
+            }
+            None => text,
+        }
+    }
+
+    /// The name column: the file name, with the match summary for content results.
+    fn column_name(&self, item: &ResultEntry) -> String {
+        let name = if item.name.is_empty() {
+            self.shown(&item.display())
+        } else {
+            self.shown(&item.name.to_string_lossy())
+        };
+        match (item.match_line_number, item.edit_distance.filter(|_| self.show_edit_distance)) {
+            (Some(line), _) => format!("{} · line {}", name, line),
+            (None, Some(typos)) => format!("{} · {} typo{}", name, typos, if typos == 1 { "" } else { "s" }),
+            (None, None) => name,
+        }
+    }
+
+    /// The size column: the file size, or a folder's item count when those are shown.
+    fn column_size(&self, item: &ResultEntry) -> String {
+        if let Some(size) = item.size {
+            return format_size(size);
+        }
+        match self.dir_item_counts.as_ref().and_then(|counts| counts.get(&item.path())) {
+            Some(1) => "1 item".to_string(),
+            Some(&count) if count >= ITEM_COUNT_LIMIT => format!("{}+ items", ITEM_COUNT_LIMIT),
+            Some(count) => format!("{} items", count),
+            None => String::new(),
+        }
+    }
+
+    /// The folder column: the folder holding the entry, without the collapsed prefix.
+    fn column_folder(&self, item: &ResultEntry) -> String {
+        let parent = item.parent.as_path();
+        let folder = match parent.strip_prefix(&self.strip_prefix) {
+            Ok(suffix) if !self.strip_prefix.as_os_str().is_empty() => suffix,
+            _ => parent,
+        };
+        let max_chars = if self.shorten_paths {
+            PATH_MAX_CHARS
+        } else {
+            ROW_TEXT_MAX_CHARS
+        };
+        shorten_middle(&self.shown(&folder.to_string_lossy()), max_chars)
+    }
+
+    /// The last `display_components` parts of `path`, or None if it has no more than that.
+    fn path_tail(&self, path: &Path) -> Option<PathBuf> {
+        let count = path.components().count();
+        if self.display_components == 0 || count <= self.display_components {
+            return None;
+        }
+        Some(path.components().skip(count - self.display_components).collect())
+    }
+
+    /// How many folders down from the folder searched `item` is, 0 for its direct entries, or
+    /// None when coloring by depth is off. The deepest root holding it counts, for nested roots.
+    fn depth(&self, item: &ResultEntry) -> Option<usize> {
+        self.depth_roots
+            .iter()
+            .filter_map(|root| item.parent.strip_prefix(root).ok())
+            .map(|relative| relative.components().count())
+            .min()
+    }
+
+    fn git_status(&self, item: &ResultEntry) -> Option<GitStatus> {
+        self.git_statuses.as_ref()?.get(&item.path()).copied()
+    }
+
+    /// The cached checksum of a file row, if it was hashed since it was last modified.
+    fn checksum(&self, item: &ResultEntry) -> Option<&str> {
+        let (modified, hash) = self.checksums.as_ref()?.get(&item.path())?;
+        Some(hash.as_str()).filter(|_| *modified == item.modified)
+    }
+
+    fn tags_of(&self, item: &ResultEntry) -> &[String] {
+        self.tags.get(&item.path()).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether the Tag button would take its tag off this row rather than add it.
+    fn has_tag(&self, item: &ResultEntry) -> bool {
+        let tag = self.tag_name.trim();
+        !tag.is_empty() && self.tags_of(item).iter().any(|t| t == tag)
+    }
+
+    fn is_marked(&self, item: &ResultEntry) -> bool {
+        self.marked.contains(&item.path())
+    }
+
+    fn is_highlighted(&self, item: &ResultEntry) -> bool {
+        !self.highlight.as_os_str().is_empty() && item.is_at(&self.highlight)
+    }
+
+    fn match_line<'a>(&self, item: &'a ResultEntry) -> Option<&'a str> {
+        item.match_line.as_deref().filter(|_| self.show_match_line)
+    }
+}
+
+#[derive(Clone, Data, Lens)]
+struct AppState {
+    pub root_path: String,
+    pub search_term: String,
+    // Extra terms OR-ed with `search_term`, one per box.
+    pub or_terms: Arc<Vec<String>>,
+    // Further folders searched along with `root_path`, one per box.
+    pub other_roots: Arc<Vec<String>>,
+    // Change from im::Vector<String> to Arc<Vec<ResultEntry>> for compatibility with ListIter
+    pub search_results: Arc<Vec<ResultEntry>>,
+    // The extensions found in `search_results`, sorted; checking some narrows the list to them.
+    pub extension_facets: Arc<Vec<ExtensionFacet>>,
+    // Show results as aligned name | size | modified | folder columns.
+    pub columns: bool,
+    // "Copy into folder…" recreates the results' folders under the destination.
+    pub preserve_structure: bool,
+    // Show how many results each subdirectory of the root holds.
+    pub count_by_subdir: bool,
+    pub subdir_counts: Arc<Vec<SubdirCount>>,
+    pub sort_key: SortKey,
+    // `search_results` narrowed by the checked facets and sorted by `sort_key`; this is what
+    // the list shows and acts on.
+    pub visible_results: Arc<Vec<ResultEntry>>,
+    // Show the longest shared directory once as a header instead of on every row.
+    pub collapse_prefix: bool,
+    pub shorten_paths: bool,
+    pub color_by_type: bool,
+    pub color_by_age: bool,
+    // Stripe rows from one hue to another by their depth below the folder searched.
+    pub color_by_depth: bool,
+    pub mark_folders: bool,
+    // Show how many entries each directory result holds.
+    pub show_item_counts: bool,
+    // Under the count, say how many results each filter that is off would leave.
+    pub preview_filters: bool,
+    // Cache of those counts, cleared whenever a new search starts.
+    pub dir_item_counts: Arc<HashMap<PathBuf, usize>>,
+    // Mark file results in a git working copy with their status; runs git, so it's optional.
+    pub show_git_status: bool,
+    // Cache of those statuses, cleared like `dir_item_counts`.
+    pub git_statuses: Arc<HashMap<PathBuf, GitStatus>>,
+    // Show the start of each visible file's SHA-256, hashed in the background as rows come
+    // into view.
+    pub show_checksums: bool,
+    // Those checksums with the modification time each was read at, kept across searches.
+    pub checksums: Arc<ChecksumCache>,
+    // Files waiting to be hashed, the most recently shown last; one is hashed at a time.
+    #[data(same_fn = "PartialEq::eq")]
+    pub checksum_queue: Arc<Vec<(PathBuf, Option<SystemTime>)>>,
+    pub hashing_checksum: bool,
+    // Cancel flag for the file being hashed, set when the checksums are hidden or the results
+    // replaced.
+    #[data(ignore)]
+    pub checksum_cancel: Arc<AtomicBool>,
+    // Show the start of the highlighted file under the results.
+    pub show_preview: bool,
+    // The file `preview_text` was read from; it is only shown while that file is highlighted.
+    #[data(same_fn = "PartialEq::eq")]
+    pub preview_path: PathBuf,
+    pub preview_text: String,
+    // The picture of an image `preview_path`, drawn above `preview_text`.
+    pub preview_image: Option<ImageBuf>,
+    // Longest directory shared by every entry in `search_results`.
+    #[data(same_fn = "PartialEq::eq")]
+    pub common_prefix: PathBuf,
+    // Totals over `search_results`, and whether the "Summary" section is expanded.
+    pub summary: SearchSummary,
+    pub show_summary: bool,
+    // Timestamped lines about searches, opened and deleted files and errors, oldest first.
+    pub activity: Arc<VecDeque<String>>,
+    pub show_activity: bool,
+    // True while the list shows a plain listing of `root_path` rather than search results.
+    pub browse_mode: bool,
+    // Result to highlight in the browse view; empty for none.
+    #[data(same_fn = "PartialEq::eq")]
+    pub highlighted: PathBuf,
+    pub show_settings: bool,
+    pub theme: ThemeSettings,
+    pub monospace_results: bool,
+    pub escape_controls: bool,
+    pub display_components: usize,
+    // Sum up the active filters as removable chips above the results.
+    pub show_filter_chips: bool,
+    pub click_action: RowClickAction,
+    pub dirs_first: FolderPlacement,
+    // (opening now, total) while an "open all" batch is running.
+    pub open_progress: Option<(usize, usize)>,
+    pub search_contents: bool,
+    // Index into `content_modes()`: whether names take part in a content search.
+    pub content_mode: usize,
+    pub show_match_line: bool,
+    // Most recent error shown to the user; empty when there is none.
+    pub error_message: String,
+    // Informational message, e.g. that a search was widened; empty when there is none.
+    pub notice: String,
+    // Show the duplicate clusters in place of the results list.
+    pub show_duplicates: bool,
+    // Show the results grouped by file name in place of the list.
+    pub group_by_name: bool,
+    // `visible_results` grouped by file name, kept up to date with it.
+    pub name_groups: Arc<Vec<NameGroup>>,
+    pub duplicate_groups: Arc<Vec<DuplicateGroup>>,
+    // (files hashed, files to hash) while a duplicate scan is running.
+    pub duplicate_progress: Option<(usize, usize)>,
+    // (percent, 100) while a file's hash is being computed.
+    pub hash_progress: Option<(usize, usize)>,
+    pub command_template: String,
+    pub open_command: String,
+    pub editor_command: String,
+    pub diff_command: String,
+    // Files marked with their row's Mark button, in the order marked; "Compare" diffs two.
+    pub marked: Arc<Vec<PathBuf>>,
+    // Re-run the search automatically when entries in `root_path` change.
+    pub watch: bool,
+    // Show how many matches a running search has found so far.
+    pub live_count: bool,
+    pub found_so_far: usize,
+    // Show roughly how much of the walk is done, judged by the last full walk of the same folders.
+    pub show_coverage: bool,
+    pub dirs_scanned: usize,
+    // Directories the last finished walk with the same `coverage_key` went through, if any.
+    pub dirs_estimate: Option<usize>,
+    pub walk_sizes: Arc<HashMap<String, usize>>,
+    // Add each search's results to the ones already listed instead of replacing them.
+    pub append_results: bool,
+    pub thread_count: usize,
+    pub content_max_bytes: u64,
+    pub open_all_warn_threshold: usize,
+    // Paths per file for "Save in parts…".
+    pub segment_size: usize,
+    // Caps how often background tasks update the UI, see `update_interval`.
+    pub updates_per_second: usize,
+    pub stale_days: u64,
+    pub read_retries: usize,
+    pub match_full_path: bool,
+    pub resolve_links: bool,
+    // Let symlinks match by their target's name too.
+    pub match_link_targets: bool,
+    // List only symlinks whose target is missing, for cleaning them up.
+    pub broken_links_only: bool,
+    // List only zero-byte files and empty folders, for cleaning them up.
+    pub empty_only: bool,
+    // List only entries untouched for `stale_days`, for archiving them.
+    pub stale_only: bool,
+    pub case_sensitive: bool,
+    // Treat spaces in a term as AND: every word has to match.
+    pub all_words: bool,
+    // Match names without their extension.
+    pub stem_only: bool,
+    // Match names allowing up to `max_edit_distance` typos, closest first.
+    pub approximate: bool,
+    pub max_edit_distance: usize,
+    pub show_edit_distance: bool,
+    // Keep only the `largest_count` biggest matching files, biggest first.
+    pub largest_only: bool,
+    pub largest_count: usize,
+    pub normalize_unicode: bool,
+    // Fold `ß`, Turkish `İ`/`ı` and ligatures too, see `SearchOptions::full_case_folding`.
+    pub full_case_folding: bool,
+    pub exact_case_first: bool,
+    pub skip_binary: bool,
+    pub stay_on_filesystem: bool,
+    // Pause between directory reads to spare the disk, see `SearchOptions::low_impact`.
+    pub low_impact: bool,
+    // Read words like `size>10MB` in the term as filters, see `SearchOptions::apply_term_filters`.
+    pub term_filters: bool,
+    // Comma-separated extensions to limit the search to, e.g. "rs, toml"; empty for all files.
+    pub extensions: String,
+    // Match files by the type their contents look like, from `mime_types`; reads every file.
+    pub sniff_types: bool,
+    // Comma-separated MIME types or families, e.g. "image, application/pdf".
+    pub mime_types: String,
+    // Comma-separated names of directories not to descend into, e.g. "target, .git".
+    pub exclude_dirs: String,
+    // Comma-separated system folders never searched; starts out with the platform's defaults.
+    pub exclude_paths: String,
+    // Include dotfiles and other hidden entries; toggled with Cmd+Shift+. (Ctrl+H off macOS) as well as its checkbox.
+    pub show_hidden: bool,
+    // Search subfolders; off limits the search to the root's own entries.
+    pub recursive: bool,
+    // Levels below the root to search; 0 for no limit. Ignored when not recursive.
+    pub max_depth: usize,
+    // Only show entries modified since the app started.
+    pub modified_since_start: bool,
+    // `YYYY-MM-DD` bounds on creation time, empty for none: on or after, and before.
+    pub created_after: String,
+    pub created_before: String,
+    // Whether the root's filesystem records creation times, see `probe_creation_times`; the
+    // inputs are hidden otherwise.
+    pub creation_times: bool,
+    // Index into `permission_filters()`.
+    pub permission_filter: usize,
+    #[data(same_fn = "PartialEq::eq")]
+    pub started_at: SystemTime,
+    pub trim_terms: bool,
+    pub rerun_on_drill_down: bool,
+    pub auto_widen: bool,
+    pub listing_sizes: bool,
+    pub canonicalize_root: bool,
+    // Leave the results listed when the folder changes, e.g. to collect them with `append_results`.
+    pub keep_results_on_root_change: bool,
+    pub restore_session: bool,
+    pub list_on_start: bool,
+    // Append the results to a log every `results_log_minutes` while watching.
+    pub results_log: bool,
+    pub results_log_minutes: u64,
+    pub auto_select_first: bool,
+    pub refocus_search_box: bool,
+    // Parent folders the current search may still widen to.
+    pub widen_levels_left: usize,
+    pub presets: Arc<Vec<SearchPreset>>,
+    pub workspaces: Arc<Vec<Workspace>>,
+    pub folder_settings: Arc<Vec<FolderSettings>>,
+    // Name typed for the next "Save workspace".
+    pub workspace_name: String,
+    pub bookmarks: Arc<Vec<String>>,
+    pub recent_files: Arc<Vec<String>>,
+    // Tags by path, kept in the config so they last between sessions, see `TOGGLE_TAG`.
+    pub tags: Arc<BTreeMap<PathBuf, Vec<String>>>,
+    // The tag the rows' Tag buttons apply and "Only tagged" filters by; empty for any tag.
+    pub tag_name: String,
+    pub tagged_only: bool,
+    // Searches that "Search here" narrowed, outermost first.
+    pub drill_stack: Arc<Vec<SearchStep>>,
+    // The result sets "Search within results" replaced, oldest first, for "Undo refine".
+    pub refined_from: Arc<Vec<Arc<Vec<ResultEntry>>>>,
+    // Name typed for the next "Save preset".
+    pub preset_name: String,
+    // The preset last loaded or saved, to tell whether its fields have been changed since.
+    pub loaded_preset: Option<SearchPreset>,
+    // Searches and browsing never leave this folder; None for no limit.
+    #[data(same_fn = "PartialEq::eq")]
+    pub sandbox_root: Option<PathBuf>,
+    // Started with `--no-persist`: the config file is neither read nor written, so settings,
+    // presets, bookmarks and history last only until the app quits.
+    pub no_persist: bool,
+    // Ask before changing the folder while that preset has unsaved changes.
+    pub confirm_root_change: bool,
+    // Ask before a search that would walk a whole disk, see `whole_disk_root`.
+    pub warn_whole_disk: bool,
+    // Search term last run in each watched folder, restored by `set_root` while watching.
+    pub remember_watch_terms: bool,
+    pub watch_terms: Arc<BTreeMap<String, String>>,
+    // Set by `set_root` when it restored a watched folder's term; `WatchRoot` then re-runs it.
+    pub resume_watch_search: bool,
+    // Full path of the result under the pointer; empty for none.
+    pub hover_path: String,
+    // Batch awaiting Confirm or Cancel in the review panel.
+    pub pending_batch: Option<PendingBatch>,
+    // New file or folder being named in the panel above the results.
+    pub pending_create: Option<PendingCreate>,
+    // Rename of the marked entries awaiting Rename or Cancel, see `rename_marked`.
+    pub pending_rename: Option<PendingRename>,
+    pub palette: PaletteState,
+    // True while a search is running and can be cancelled.
+    pub searching: bool,
+    pub search_outcome: SearchOutcome,
+    // Bumped whenever a search starts or is cancelled; results tagged with an older value are stale.
+    pub search_generation: u64,
+    // Cancel flag shared with the running search's threads.
+    #[data(ignore)]
+    pub search_cancel: Arc<AtomicBool>,
+    // The folders the last search ran on and whether it went below them, which watch mode
+    // watches; the folder box may meanwhile hold a path that is still being typed.
+    pub searched_roots: Arc<Vec<PathBuf>>,
+    pub searched_recursively: bool,
+    // True while the running search is a watch-mode refresh whose results are diffed
+    // against the current ones.
+    pub diff_results: bool,
+}
+
+impl AppState {
+    /// A fresh state at `root_path` with every setting at its default.
+    fn new(root_path: String) -> Self {
+        let creation_times = creation_times_supported(Path::new(&root_path));
+        AppState {
+            root_path,
+            search_term: "".to_string(),
+            or_terms: Arc::new(Vec::new()),
+            other_roots: Arc::new(Vec::new()),
+            search_results: Arc::new(Vec::new()),
+            extension_facets: Arc::new(Vec::new()),
+            visible_results: Arc::new(Vec::new()),
+            sort_key: SortKey::Found,
+            columns: false,
+            preserve_structure: false,
+            count_by_subdir: false,
+            subdir_counts: Arc::new(Vec::new()),
+            collapse_prefix: false,
+            shorten_paths: false,
+            color_by_type: true,
+            color_by_age: false,
+            color_by_depth: false,
+            mark_folders: true,
+            show_item_counts: false,
+            preview_filters: false,
+            dir_item_counts: Arc::new(HashMap::new()),
+            show_git_status: false,
+            preview_path: PathBuf::new(),
+            preview_text: String::new(),
+            preview_image: None,
+            show_preview: false,
+            git_statuses: Arc::new(HashMap::new()),
+            show_checksums: false,
+            checksums: Arc::new(HashMap::new()),
+            checksum_queue: Arc::new(Vec::new()),
+            hashing_checksum: false,
+            checksum_cancel: Arc::new(AtomicBool::new(false)),
+            common_prefix: PathBuf::new(),
+            summary: SearchSummary::default(),
+            show_summary: false,
+            activity: Arc::new(VecDeque::new()),
+            show_activity: false,
+            browse_mode: false,
+            highlighted: PathBuf::new(),
+            show_settings: false,
+            theme: ThemeSettings::default(),
+            monospace_results: false,
+            escape_controls: true,
+            display_components: 0,
+            show_filter_chips: true,
+            click_action: RowClickAction::Open,
+            dirs_first: FolderPlacement::Mixed,
+            open_progress: None,
+            search_contents: false,
+            content_mode: 0,
+            show_match_line: true,
+            error_message: String::new(),
+            notice: String::new(),
+            show_duplicates: false,
+            group_by_name: false,
+            name_groups: Arc::new(Vec::new()),
+            duplicate_groups: Arc::new(Vec::new()),
+            duplicate_progress: None,
+            hash_progress: None,
+            command_template: String::new(),
+            open_command: config::default_open_command(),
+            diff_command: config::default_diff_command(),
+            marked: Arc::new(Vec::new()),
+            editor_command: String::new(),
+            watch: false,
+            live_count: true,
+            found_so_far: 0,
+            show_coverage: false,
+            dirs_scanned: 0,
+            dirs_estimate: None,
+            walk_sizes: Arc::new(HashMap::new()),
+            append_results: false,
+            thread_count: config::default_thread_count(),
+            content_max_bytes: search::DEFAULT_CONTENT_MAX_BYTES,
+            open_all_warn_threshold: 10,
+            segment_size: 1000,
+            updates_per_second: 30,
+            stale_days: 365,
+            read_retries: 2,
+            match_full_path: false,
+            resolve_links: false,
+            match_link_targets: false,
+            broken_links_only: false,
+            empty_only: false,
+            stale_only: false,
+            case_sensitive: false,
+            all_words: false,
+            stem_only: false,
+            approximate: false,
+            max_edit_distance: 2,
+            show_edit_distance: true,
+            largest_only: false,
+            largest_count: 50,
+            normalize_unicode: cfg!(target_os = "macos"),
+            full_case_folding: false,
+            exact_case_first: false,
+            skip_binary: true,
+            low_impact: false,
+            term_filters: false,
+            stay_on_filesystem: false,
+            extensions: String::new(),
+            sniff_types: false,
+            mime_types: String::new(),
+            exclude_dirs: String::new(),
+            exclude_paths: config::default_exclude_paths(),
+            show_hidden: true,
+            recursive: true,
+            max_depth: 0,
+            modified_since_start: false,
+            created_after: String::new(),
+            created_before: String::new(),
+            creation_times,
+            permission_filter: 0,
+            started_at: SystemTime::now(),
+            trim_terms: true,
+            rerun_on_drill_down: true,
+            auto_widen: false,
+            listing_sizes: false,
+            canonicalize_root: false,
+            keep_results_on_root_change: false,
+            restore_session: false,
+            list_on_start: false,
+            results_log: false,
+            results_log_minutes: 5,
+            auto_select_first: false,
+            refocus_search_box: false,
+            widen_levels_left: 0,
+            presets: Arc::new(Vec::new()),
+            workspaces: Arc::new(Vec::new()),
+            folder_settings: Arc::new(Vec::new()),
+            workspace_name: String::new(),
+            bookmarks: Arc::new(Vec::new()),
+            recent_files: Arc::new(Vec::new()),
+            tags: Arc::new(BTreeMap::new()),
+            tag_name: String::new(),
+            tagged_only: false,
+            drill_stack: Arc::new(Vec::new()),
+            refined_from: Arc::new(Vec::new()),
+            preset_name: String::new(),
+            loaded_preset: None,
+            sandbox_root: None,
+            no_persist: false,
+            confirm_root_change: false,
+            warn_whole_disk: true,
+            remember_watch_terms: false,
+            watch_terms: Arc::new(BTreeMap::new()),
+            resume_watch_search: false,
+            hover_path: String::new(),
+            pending_batch: None,
+            pending_create: None,
+            pending_rename: None,
+            palette: PaletteState::default(),
+            searching: false,
+            search_outcome: SearchOutcome::NotRun,
+            search_generation: 0,
+            search_cancel: Arc::new(AtomicBool::new(false)),
+            searched_roots: Arc::new(Vec::new()),
+            searched_recursively: false,
+            diff_results: false,
+        }
+    }
+
+    /// Puts every setting back to its default and leaves the session alone: the location,
+    /// search, results, saved presets and bookmarks are kept. The config file is rewritten by
+    /// `PersistConfig` as the settings change.
+    fn reset_settings(&mut self) {
+        let current = self.clone();
+        *self = AppState {
+            search_term: current.search_term,
+            or_terms: current.or_terms,
+            other_roots: current.other_roots,
+            search_results: current.search_results,
+            extension_facets: current.extension_facets,
+            visible_results: current.visible_results,
+            dir_item_counts: current.dir_item_counts,
+            git_statuses: current.git_statuses,
+            checksums: current.checksums,
+            checksum_queue: current.checksum_queue,
+            hashing_checksum: current.hashing_checksum,
+            checksum_cancel: current.checksum_cancel,
+            subdir_counts: current.subdir_counts,
+            common_prefix: current.common_prefix,
+            summary: current.summary,
+            show_summary: current.show_summary,
+            activity: current.activity,
+            show_activity: current.show_activity,
+            browse_mode: current.browse_mode,
+            highlighted: current.highlighted,
+            marked: current.marked,
+            show_settings: current.show_settings,
+            open_progress: current.open_progress,
+            error_message: current.error_message,
+            notice: current.notice,
+            show_duplicates: current.show_duplicates,
+            name_groups: current.name_groups,
+            duplicate_groups: current.duplicate_groups,
+            duplicate_progress: current.duplicate_progress,
+            hash_progress: current.hash_progress,
+            started_at: current.started_at,
+            widen_levels_left: current.widen_levels_left,
+            presets: current.presets,
+            workspaces: current.workspaces,
+            folder_settings: current.folder_settings,
+            workspace_name: current.workspace_name,
+            bookmarks: current.bookmarks,
+            recent_files: current.recent_files,
+            tags: current.tags,
+            tag_name: current.tag_name,
+            tagged_only: current.tagged_only,
+            drill_stack: current.drill_stack,
+            refined_from: current.refined_from,
+            walk_sizes: current.walk_sizes,
+            preset_name: current.preset_name,
+            loaded_preset: current.loaded_preset,
+            // Only the config file can lift the sandbox.
+            sandbox_root: current.sandbox_root,
+            no_persist: current.no_persist,
+            watch_terms: current.watch_terms,
+            resume_watch_search: current.resume_watch_search,
+            hover_path: current.hover_path,
+            pending_batch: current.pending_batch,
+            pending_create: current.pending_create,
+            pending_rename: current.pending_rename,
+            palette: current.palette,
+            searching: current.searching,
+            search_outcome: current.search_outcome,
+            search_generation: current.search_generation,
+            search_cancel: current.search_cancel,
+            searched_roots: current.searched_roots,
+            searched_recursively: current.searched_recursively,
+            diff_results: current.diff_results,
+            ..AppState::new(current.root_path)
+        };
+        // The list may have been sorted by a setting that was just reset.
+        self.update_visible_results();
+    }
+    fn row_settings(&self) -> RowSettings {
+        let strip_prefix = if self.collapse_prefix {
+            self.common_prefix.clone()
+        } else {
+            PathBuf::new()
+        };
+        let depth_roots = if self.color_by_depth {
+            std::iter::once(&self.root_path)
+                .chain(self.other_roots.iter())
+                .map(PathBuf::from)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        RowSettings {
+            strip_prefix,
+            highlight: self.highlighted.clone(),
+            marked: self.marked.clone(),
+            browse_mode: self.browse_mode,
+            show_match_line: self.show_match_line,
+            shorten_paths: self.shorten_paths,
+            color_by_type: self.color_by_type,
+            color_by_age: self.color_by_age,
+            depth_roots,
+            mark_folders: self.mark_folders,
+            dir_item_counts: Some(self.dir_item_counts.clone()).filter(|_| self.show_item_counts),
+            git_statuses: Some(self.git_statuses.clone()).filter(|_| self.show_git_status),
+            checksums: Some(self.checksums.clone()).filter(|_| self.show_checksums),
+            columns: self.columns,
+            escape_controls: self.escape_controls,
+            display_components: self.display_components,
+            click_action: self.click_action,
+            show_edit_distance: self.show_edit_distance,
+            tags: self.tags.clone(),
+            tag_name: self.tag_name.clone(),
+        }
+    }
+
+    fn search_options(&self) -> SearchOptions {
+        let mode = if self.approximate {
+            SearchMode::Approximate
+        } else if self.search_contents {
+            content_modes().get(self.content_mode).map_or(SearchMode::Contents, |&(_, mode)| mode)
+        } else if self.match_full_path {
+            SearchMode::FullPath
+        } else {
+            SearchMode::Name
+        };
+        let mut options = SearchOptions {
+            or_terms: self.or_terms.to_vec(),
+            other_roots: self.other_roots.iter().map(PathBuf::from).collect(),
+            case_sensitive: self.case_sensitive,
+            all_words: self.all_words,
+            stem_only: self.stem_only,
+            trim_terms: self.trim_terms,
+            max_edit_distance: self.max_edit_distance,
+            normalize_unicode: self.normalize_unicode,
+            full_case_folding: self.full_case_folding,
+            exact_case_first: self.exact_case_first,
+            mode,
+            extensions: split_list(&self.extensions),
+            mime_types: if self.sniff_types { split_list(&self.mime_types) } else { Vec::new() },
+            exclude_dirs: split_list(&self.exclude_dirs),
+            show_hidden: self.show_hidden,
+            exclude_paths: split_list(&self.exclude_paths).into_iter().map(PathBuf::from).collect(),
+            max_depth: if self.recursive {
+                Some(self.max_depth).filter(|&depth| depth > 0)
+            } else {
+                Some(1)
+            },
+            modified_after: Some(self.started_at).filter(|_| self.modified_since_start),
+            modified_before: Some(stale_cutoff(self.stale_days)).filter(|_| self.stale_only),
+            created_after: parse_date(&self.created_after).filter(|_| self.creation_times),
+            created_before: parse_date(&self.created_before).filter(|_| self.creation_times),
+            permissions: permission_filters()
+                .get(self.permission_filter)
+                .map_or(PermissionFilter::Any, |&(_, filter)| filter),
+            resolve_links: self.resolve_links,
+            match_link_targets: self.match_link_targets,
+            broken_links_only: self.broken_links_only,
+            empty_only: self.empty_only,
+            skip_binary: self.skip_binary,
+            low_impact: self.low_impact,
+            read_retries: self.read_retries,
+            stay_on_filesystem: self.stay_on_filesystem,
+            content_max_bytes: Some(self.content_max_bytes).filter(|&bytes| bytes > 0),
+            thread_count: self.thread_count.max(1),
+            largest_files: Some(self.largest_count).filter(|_| self.largest_only),
+            ..SearchOptions::new(&self.root_path, self.search_term.clone())
+        };
+        if self.term_filters {
+            options.apply_term_filters();
+        }
+        options
+    }
+
+    /// The persisted subset of the state.
+    fn config(&self) -> Config {
+        Config {
+            theme: self.theme.clone(),
+            monospace_results: self.monospace_results,
+            escape_controls: self.escape_controls,
+            display_components: self.display_components,
+            show_filter_chips: self.show_filter_chips,
+            click_action: self.click_action,
+            dirs_first: self.dirs_first,
+            command_template: self.command_template.clone(),
+            open_command: self.open_command.clone(),
+            diff_command: self.diff_command.clone(),
+            editor_command: self.editor_command.clone(),
+            thread_count: self.thread_count,
+            exclude_paths: self.exclude_paths.clone(),
+            content_max_bytes: self.content_max_bytes,
+            open_all_warn_threshold: self.open_all_warn_threshold,
+            segment_size: self.segment_size,
+            updates_per_second: self.updates_per_second,
+            stale_days: self.stale_days,
+            read_retries: self.read_retries,
+            trim_terms: self.trim_terms,
+            rerun_on_drill_down: self.rerun_on_drill_down,
+            auto_widen: self.auto_widen,
+            listing_sizes: self.listing_sizes,
+            canonicalize_root: self.canonicalize_root,
+            keep_results_on_root_change: self.keep_results_on_root_change,
+            sandbox_root: self.sandbox_root.clone(),
+            confirm_root_change: self.confirm_root_change,
+            warn_whole_disk: self.warn_whole_disk,
+            presets: self.presets.clone(),
+            workspaces: self.workspaces.clone(),
+            folder_settings: self.folder_settings.clone(),
+            bookmarks: self.bookmarks.clone(),
+            recent_files: self.recent_files.clone(),
+            tags: self.tags.clone(),
+            restore_session: self.restore_session,
+            session: Some(self.session()).filter(|_| self.restore_session),
+            list_on_start: self.list_on_start,
+            results_log: self.results_log,
+            results_log_minutes: self.results_log_minutes,
+            remember_watch_terms: self.remember_watch_terms,
+            watch_terms: self.watch_terms.clone(),
+            auto_select_first: self.auto_select_first,
+            refocus_search_box: self.refocus_search_box,
+        }
+    }
+
+    fn apply_config(&mut self, config: Config) {
+        self.theme = config.theme;
+        self.monospace_results = config.monospace_results;
+        self.escape_controls = config.escape_controls;
+        self.display_components = config.display_components;
+        self.show_filter_chips = config.show_filter_chips;
+        self.click_action = config.click_action;
+        self.dirs_first = config.dirs_first;
+        self.command_template = config.command_template;
+        self.open_command = config.open_command;
+        self.diff_command = config.diff_command;
+        self.editor_command = config.editor_command;
+        self.thread_count = config.thread_count;
+        self.exclude_paths = config.exclude_paths;
+        self.content_max_bytes = config.content_max_bytes;
+        self.open_all_warn_threshold = config.open_all_warn_threshold;
+        self.segment_size = config.segment_size;
+        self.updates_per_second = config.updates_per_second;
+        self.stale_days = config.stale_days;
+        self.read_retries = config.read_retries;
+        self.trim_terms = config.trim_terms;
+        self.rerun_on_drill_down = config.rerun_on_drill_down;
+        self.auto_widen = config.auto_widen;
+        self.listing_sizes = config.listing_sizes;
+        self.canonicalize_root = config.canonicalize_root;
+        self.keep_results_on_root_change = config.keep_results_on_root_change;
+        self.sandbox_root = config.sandbox_root;
+        self.confirm_root_change = config.confirm_root_change;
+        self.warn_whole_disk = config.warn_whole_disk;
+        self.remember_watch_terms = config.remember_watch_terms;
+        self.watch_terms = config.watch_terms;
+        self.presets = config.presets;
+        self.workspaces = config.workspaces;
+        // Entries saved before folders were resolved would otherwise never be found.
+        self.folder_settings = Arc::new(
+            config
+                .folder_settings
+                .iter()
+                .map(|settings| FolderSettings { root: folder_key(&settings.root), ..settings.clone() })
+                .collect(),
+        );
+        self.bookmarks = config.bookmarks;
+        self.recent_files = config.recent_files;
+        self.tags = config.tags;
+        self.restore_session = config.restore_session;
+        self.list_on_start = config.list_on_start;
+        self.results_log = config.results_log;
+        self.results_log_minutes = config.results_log_minutes;
+        self.auto_select_first = config.auto_select_first;
+        self.refocus_search_box = config.refocus_search_box;
+        if let Some(session) = config.session.filter(|_| config.restore_session) {
+            let root = PathBuf::from(&session.root_path);
+            self.apply_session(session);
+            // Like any other folder, the restored one is tidied, kept inside the sandbox and
+            // given the options remembered for it.
+            self.set_root(&root);
+        }
+    }
+
+    /// What a window opened with "New window" starts with: these settings, as a fresh start
+    /// would load them, with `dir` as the folder.
+    fn new_window_state(&self, dir: &Path) -> AppState {
+        let mut state = AppState::new(dir.display().to_string());
+        state.no_persist = self.no_persist;
+        state.apply_config(self.config());
+        state.root_path = dir.display().to_string();
+        state.clamp_to_sandbox();
+        state.probe_creation_times();
+        state
+    }
+
+    /// The search fields and view toggles to restore on the next start.
+    fn session(&self) -> SessionState {
+        SessionState {
+            root_path: self.root_path.clone(),
+            search_term: self.search_term.clone(),
+            or_terms: self.or_terms.clone(),
+            other_roots: self.other_roots.clone(),
+            search_contents: self.search_contents,
+            content_mode: self.content_mode,
+            match_full_path: self.match_full_path,
+            case_sensitive: self.case_sensitive,
+            all_words: self.all_words,
+            stem_only: self.stem_only,
+            approximate: self.approximate,
+            max_edit_distance: self.max_edit_distance,
+            largest_only: self.largest_only,
+            largest_count: self.largest_count,
+            exact_case_first: self.exact_case_first,
+            normalize_unicode: self.normalize_unicode,
+            full_case_folding: self.full_case_folding,
+            resolve_links: self.resolve_links,
+            match_link_targets: self.match_link_targets,
+            broken_links_only: self.broken_links_only,
+            empty_only: self.empty_only,
+            stale_only: self.stale_only,
+            skip_binary: self.skip_binary,
+            low_impact: self.low_impact,
+            term_filters: self.term_filters,
+            stay_on_filesystem: self.stay_on_filesystem,
+            show_match_line: self.show_match_line,
+            extensions: self.extensions.clone(),
+            sniff_types: self.sniff_types,
+            mime_types: self.mime_types.clone(),
+            exclude_dirs: self.exclude_dirs.clone(),
+            show_hidden: self.show_hidden,
+            recursive: self.recursive,
+            max_depth: self.max_depth,
+            permission_filter: self.permission_filter,
+            created_after: self.created_after.clone(),
+            created_before: self.created_before.clone(),
+            sort_key: self.sort_key,
+            collapse_prefix: self.collapse_prefix,
+            shorten_paths: self.shorten_paths,
+            color_by_type: self.color_by_type,
+            color_by_age: self.color_by_age,
+            color_by_depth: self.color_by_depth,
+            mark_folders: self.mark_folders,
+            columns: self.columns,
+            group_by_name: self.group_by_name,
+        }
+    }
+
+    fn apply_session(&mut self, session: SessionState) {
+        self.root_path = session.root_path;
+        self.search_term = session.search_term;
+        self.or_terms = session.or_terms;
+        self.other_roots = session.other_roots;
+        self.search_contents = session.search_contents;
+        self.content_mode = session.content_mode;
+        self.match_full_path = session.match_full_path;
+        self.case_sensitive = session.case_sensitive;
+        self.all_words = session.all_words;
+        self.stem_only = session.stem_only;
+        self.approximate = session.approximate;
+        self.max_edit_distance = session.max_edit_distance;
+        self.largest_only = session.largest_only;
+        self.largest_count = session.largest_count;
+        self.exact_case_first = session.exact_case_first;
+        self.normalize_unicode = session.normalize_unicode;
+        self.full_case_folding = session.full_case_folding;
+        self.resolve_links = session.resolve_links;
+        self.match_link_targets = session.match_link_targets;
+        self.broken_links_only = session.broken_links_only;
+        self.empty_only = session.empty_only;
+        self.stale_only = session.stale_only;
+        self.skip_binary = session.skip_binary;
+        self.low_impact = session.low_impact;
+        self.term_filters = session.term_filters;
+        self.stay_on_filesystem = session.stay_on_filesystem;
+        self.show_match_line = session.show_match_line;
+        self.extensions = session.extensions;
+        self.sniff_types = session.sniff_types;
+        self.mime_types = session.mime_types;
+        self.exclude_dirs = session.exclude_dirs;
+        self.show_hidden = session.show_hidden;
+        self.recursive = session.recursive;
+        self.max_depth = session.max_depth;
+        self.permission_filter = session.permission_filter;
+        self.created_after = session.created_after;
+        self.created_before = session.created_before;
+        self.sort_key = session.sort_key;
+        self.collapse_prefix = session.collapse_prefix;
+        self.shorten_paths = session.shorten_paths;
+        self.color_by_type = session.color_by_type;
+        self.color_by_age = session.color_by_age;
+        self.color_by_depth = session.color_by_depth;
+        self.mark_folders = session.mark_folders;
+        self.columns = session.columns;
+        self.group_by_name = session.group_by_name;
+    }
+
+    /// The current search fields as a preset called `name`.
+    fn preset_fields(&self, name: String) -> SearchPreset {
+        SearchPreset {
+            name,
+            term: self.search_term.clone(),
+            search_contents: self.search_contents,
+            content_mode: self.content_mode,
+            match_full_path: self.match_full_path,
+            filters: Some(self.search_filters()),
+        }
+    }
+
+    /// The filters and flags saved with a search preset.
+    fn search_filters(&self) -> SearchFilters {
+        SearchFilters {
+            or_terms: self.or_terms.clone(),
+            case_sensitive: self.case_sensitive,
+            all_words: self.all_words,
+            stem_only: self.stem_only,
+            approximate: self.approximate,
+            max_edit_distance: self.max_edit_distance,
+            largest_only: self.largest_only,
+            largest_count: self.largest_count,
+            exact_case_first: self.exact_case_first,
+            normalize_unicode: self.normalize_unicode,
+            full_case_folding: self.full_case_folding,
+            resolve_links: self.resolve_links,
+            match_link_targets: self.match_link_targets,
+            broken_links_only: self.broken_links_only,
+            empty_only: self.empty_only,
+            stale_only: self.stale_only,
+            skip_binary: self.skip_binary,
+            stay_on_filesystem: self.stay_on_filesystem,
+            term_filters: self.term_filters,
+            extensions: self.extensions.clone(),
+            sniff_types: self.sniff_types,
+            mime_types: self.mime_types.clone(),
+            exclude_dirs: self.exclude_dirs.clone(),
+            exclude_paths: self.exclude_paths.clone(),
+            show_hidden: self.show_hidden,
+            recursive: self.recursive,
+            max_depth: self.max_depth,
+            permission_filter: self.permission_filter,
+            created_after: self.created_after.clone(),
+            created_before: self.created_before.clone(),
+        }
+    }
+
+    /// Whether the search differs from `preset`, as far as the preset records it.
+    fn differs_from_preset(&self, preset: &SearchPreset) -> bool {
+        let mut current = self.preset_fields(preset.name.clone());
+        if preset.filters.is_none() {
+            current.filters = None;
+        }
+        current != *preset
+    }
+
+    /// Saves the current search fields under `preset_name`, replacing a preset of the same name.
+    fn save_preset(&mut self) {
+        let name = self.preset_name.trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+        let preset = self.preset_fields(name.clone());
+        let mut presets: Vec<SearchPreset> =
+            self.presets.iter().filter(|p| p.name != name).cloned().collect();
+        presets.push(preset.clone());
+        self.presets = Arc::new(presets);
+        self.loaded_preset = Some(preset);
+    }
+
+    fn load_preset(&mut self, preset: &SearchPreset) {
+        self.search_term = preset.term.clone();
+        self.search_contents = preset.search_contents;
+        self.content_mode = preset.content_mode;
+        self.match_full_path = preset.match_full_path;
+        if let Some(filters) = &preset.filters {
+            self.apply_search_filters(filters);
+        }
+        self.preset_name = preset.name.clone();
+        self.loaded_preset = Some(preset.clone());
+    }
+
+    /// Takes on the filters and flags saved with a preset, see `search_filters`.
+    fn apply_search_filters(&mut self, filters: &SearchFilters) {
+        self.or_terms = filters.or_terms.clone();
+        self.case_sensitive = filters.case_sensitive;
+        self.all_words = filters.all_words;
+        self.stem_only = filters.stem_only;
+        self.approximate = filters.approximate;
+        self.max_edit_distance = filters.max_edit_distance;
+        self.largest_only = filters.largest_only;
+        self.largest_count = filters.largest_count;
+        self.exact_case_first = filters.exact_case_first;
+        self.normalize_unicode = filters.normalize_unicode;
+        self.full_case_folding = filters.full_case_folding;
+        self.resolve_links = filters.resolve_links;
+        self.match_link_targets = filters.match_link_targets;
+        self.broken_links_only = filters.broken_links_only;
+        self.empty_only = filters.empty_only;
+        self.stale_only = filters.stale_only;
+        self.skip_binary = filters.skip_binary;
+        self.stay_on_filesystem = filters.stay_on_filesystem;
+        self.term_filters = filters.term_filters;
+        self.extensions = filters.extensions.clone();
+        self.sniff_types = filters.sniff_types;
+        self.mime_types = filters.mime_types.clone();
+        self.exclude_dirs = filters.exclude_dirs.clone();
+        self.exclude_paths = filters.exclude_paths.clone();
+        self.show_hidden = filters.show_hidden;
+        self.recursive = filters.recursive;
+        self.max_depth = filters.max_depth;
+        self.permission_filter = filters.permission_filter;
+        self.created_after = filters.created_after.clone();
+        self.created_before = filters.created_before.clone();
+    }
+
+    /// Saves the theme and view options under `workspace_name`, replacing one of the same name.
+    fn save_workspace(&mut self) {
+        let name = self.workspace_name.trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+        let workspace = Workspace {
+            name: name.clone(),
+            theme: self.theme.clone(),
+            view: self.session(),
+        };
+        let mut workspaces: Vec<Workspace> =
+            self.workspaces.iter().filter(|w| w.name != name).cloned().collect();
+        workspaces.push(workspace);
+        self.workspaces = Arc::new(workspaces);
+    }
+
+    /// Takes on a workspace's theme and options, keeping the folders and terms searched.
+    fn load_workspace(&mut self, workspace: &Workspace) {
+        self.apply_view(workspace.view.clone());
+        self.theme = workspace.theme.clone();
+        self.workspace_name = workspace.name.clone();
+    }
+
+    /// Takes on the options in `view` but not its folders and terms.
+    fn apply_view(&mut self, view: SessionState) {
+        let root_path = self.root_path.clone();
+        let search_term = self.search_term.clone();
+        let or_terms = self.or_terms.clone();
+        let other_roots = self.other_roots.clone();
+        let sort_key = self.sort_key;
+        self.apply_session(view);
+        self.root_path = root_path;
+        self.search_term = search_term;
+        self.or_terms = or_terms;
+        self.other_roots = other_roots;
+        // Re-sort what's listed if the view sorts differently.
+        let wanted = std::mem::replace(&mut self.sort_key, sort_key);
+        self.set_sort_key(wanted);
+    }
+
+    /// The options remembered for the current folder, if any.
+    fn current_folder_settings(&self) -> Option<&FolderSettings> {
+        let key = folder_key(&self.root_path);
+        self.folder_settings.iter().find(|settings| settings.root == key)
+    }
+
+    /// Remembers the current options for the current folder, replacing what was there.
+    fn remember_folder_settings(&mut self) {
+        self.tidy_root();
+        let mut folder_settings = self.forgotten_folder_settings();
+        folder_settings.push(FolderSettings {
+            root: folder_key(&self.root_path),
+            view: self.session(),
+        });
+        self.folder_settings = Arc::new(folder_settings);
+        self.notice = format!("These options will be used whenever {} is chosen.", self.root_path);
+    }
+
+    /// Every remembered folder's options except the current folder's.
+    fn forgotten_folder_settings(&self) -> Vec<FolderSettings> {
+        let key = folder_key(&self.root_path);
+        self.folder_settings.iter().filter(|s| s.root != key).cloned().collect()
+    }
+
+    /// True unless `confirm_root_change` is on, the loaded preset has unsaved changes and the
+    /// user declines to change the folder anyway. Once they agree the preset stops being
+    /// tracked, so they aren't asked again on every step.
+    fn root_change_confirmed(&mut self) -> bool {
+        let Some(preset) = self.loaded_preset.as_ref().filter(|_| self.confirm_root_change) else {
+            return true;
+        };
+        if !self.differs_from_preset(preset) {
+            return true;
+        }
+        let question = format!(
+            "The search loaded from preset \"{}\" has changed and isn't saved. \
+             Change the folder anyway?",
+            preset.name
+        );
+        if !confirm("Change folder", &question) {
+            return false;
+        }
+        self.loaded_preset = None;
+        true
+    }
+
+    /// Cancels any running search and returns the generation and cancel flag for a new one.
+    fn begin_search(&mut self) -> (u64, Arc<AtomicBool>) {
+        self.cancel_search();
+        self.searching = true;
+        self.found_so_far = 0;
+        self.dirs_scanned = 0;
+        self.search_cancel = Arc::new(AtomicBool::new(false));
+        self.dir_item_counts = Arc::new(HashMap::new());
+        self.git_statuses = Arc::new(HashMap::new());
+        // Rows of the old results that were never hashed won't be shown again.
+        self.checksum_queue = Arc::new(Vec::new());
+        self.checksum_cancel.store(true, Ordering::Relaxed);
+        (self.search_generation, self.search_cancel.clone())
+    }
+
+    /// Adds a file's checksum to `checksums`. Once `CHECKSUM_CACHE_LIMIT` is reached only
+    /// those of the current results are kept, and if that isn't enough the cache starts over.
+    fn remember_checksum(&mut self, path: PathBuf, modified: Option<SystemTime>, hash: String) {
+        let checksums = Arc::make_mut(&mut self.checksums);
+        if checksums.len() >= CHECKSUM_CACHE_LIMIT && !checksums.contains_key(&path) {
+            let current: HashSet<PathBuf> = self.search_results.iter().map(ResultEntry::path).collect();
+            checksums.retain(|path, _| current.contains(path));
+            if checksums.len() >= CHECKSUM_CACHE_LIMIT {
+                checksums.clear();
+            }
+        }
+        checksums.insert(path, (modified, hash));
+    }
+
+    /// The shortest gap between two progress updates from a background task. Submitting every
+    /// step instead would queue more commands than the event loop can handle, making it lag.
+    fn update_interval(&self) -> Duration {
+        Duration::from_secs(1) / self.updates_per_second.clamp(1, 1000) as u32
+    }
+
+    /// Whether a search has run or is running, as opposed to browsing or nothing yet.
+    fn has_searched(&self) -> bool {
+        !self.browse_mode && (self.searching || self.search_outcome != SearchOutcome::NotRun)
+    }
+
+    /// A chip for every filter that narrows the search, in the order the controls appear.
+    fn filter_chips(&self) -> Vec<FilterChip> {
+        let chip = |kind, label: String| FilterChip { kind, label };
+        let mut chips = Vec::new();
+        if !self.search_term.is_empty() {
+            chips.push(chip(FilterKind::Term, format!("term: {}", self.search_term)));
+        }
+        if !self.or_terms.is_empty() {
+            chips.push(chip(FilterKind::OrTerms, format!("or: {}", self.or_terms.join(" | "))));
+        }
+        let toggles = [
+            (self.case_sensitive, FilterKind::CaseSensitive, "case-sensitive"),
+            (self.all_words, FilterKind::AllWords, "all words"),
+            (self.term_filters, FilterKind::TermFilters, "filters in term"),
+            (self.stem_only, FilterKind::StemOnly, "ignore extensions"),
+            (self.approximate, FilterKind::Approximate, "approximate"),
+            (self.match_full_path, FilterKind::FullPath, "full path"),
+            (self.search_contents, FilterKind::Contents, "contents"),
+            (!self.show_hidden, FilterKind::HideHidden, "no hidden files"),
+            (!self.recursive, FilterKind::NotRecursive, "top level only"),
+            (self.modified_since_start, FilterKind::ModifiedSinceStart, "changed since start"),
+            (self.match_link_targets, FilterKind::MatchLinkTargets, "link targets"),
+            (self.broken_links_only, FilterKind::BrokenLinksOnly, "broken links"),
+            (self.empty_only, FilterKind::EmptyOnly, "empty only"),
+            (self.stale_only, FilterKind::StaleOnly, "stale"),
+            (self.stay_on_filesystem, FilterKind::StayOnFilesystem, "one filesystem"),
+        ];
+        for (on, kind, label) in toggles {
+            if on {
+                chips.push(chip(kind, label.to_string()));
+            }
+        }
+        let texts = [
+            (&self.extensions, FilterKind::Extensions, "ext"),
+            (&self.exclude_dirs, FilterKind::ExcludeDirs, "skip"),
+            (&self.created_after, FilterKind::CreatedAfter, "created after"),
+            (&self.created_before, FilterKind::CreatedBefore, "created before"),
+        ];
+        for (text, kind, name) in texts {
+            if !text.trim().is_empty() {
+                chips.push(chip(kind, format!("{}: {}", name, text.trim())));
+            }
+        }
+        if self.sniff_types && !self.mime_types.trim().is_empty() {
+            chips.push(chip(FilterKind::MimeTypes, format!("type: {}", self.mime_types.trim())));
+        }
+        if self.recursive && self.max_depth > 0 {
+            chips.push(chip(FilterKind::MaxDepth, format!("depth: {}", self.max_depth)));
+        }
+        if self.permission_filter != 0 {
+            if let Some((name, _)) = permission_filters().get(self.permission_filter) {
+                chips.push(chip(FilterKind::Permissions, name.to_lowercase()));
+            }
+        }
+        if self.largest_only {
+            chips.push(chip(FilterKind::LargestOnly, format!("largest {}", self.largest_count)));
+        }
+        chips
+    }
+
+    /// How many of the results each common filter would keep if it were turned on, e.g.
+    /// "Would leave: stale 12 of 200 · tagged 5 of 200". Only filters that can be judged from the
+    /// results themselves are counted; hidden files are told by a leading dot, so Windows' hidden
+    /// attribute isn't seen here.
+    fn filter_previews(&self) -> String {
+        let results: Vec<&ResultEntry> =
+            self.search_results.iter().filter(|r| !r.is_removed()).collect();
+        let stale_cutoff = Some(stale_cutoff(self.stale_days));
+        let is_hidden = |r: &ResultEntry| r.name.to_string_lossy().starts_with('.');
+        let mut counts = Vec::new();
+        let mut preview = |offered: bool, name: &str, keeps: &dyn Fn(&ResultEntry) -> bool| {
+            if offered {
+                let kept = results.iter().filter(|r| keeps(r)).count();
+                counts.push(format!("{} {} of {}", name, kept, results.len()));
+            }
+        };
+        preview(self.show_hidden, "no hidden", &|r| !is_hidden(r));
+        preview(!self.empty_only, "empty", &|r| r.empty);
+        preview(!self.stale_only, "stale", &|r| {
+            stale_cutoff.is_some_and(|cutoff| r.modified.is_some_and(|m| m < cutoff))
+        });
+        // As "Only tagged" filters: by the tag typed, or any tag when none is.
+        let tag = self.tag_name.trim();
+        preview(!self.tagged_only, "tagged", &|r| {
+            self.tags
+                .get(&r.path())
+                .is_some_and(|tags| tag.is_empty() || tags.iter().any(|t| t == tag))
+        });
+        if counts.is_empty() {
+            return "Every previewed filter is already on.".to_string();
+        }
+        format!("Would leave: {}", counts.join(" · "))
+    }
+
+    /// The line above the results: how many there are, or so far while searching.
+    fn count_text(&self) -> String {
+        if self.searching {
+            let searching = if self.live_count {
+                format!("Searching… {} found so far", self.found_so_far)
+            } else {
+                "Searching…".to_string()
+            };
+            return if !self.show_coverage {
+                searching
+            } else if let Some(estimate) = self.dirs_estimate.filter(|&total| total > 0) {
+                // Folders may have been added since, so never claim to be done.
+                let percent = (self.dirs_scanned * 100 / estimate).min(99);
+                format!("{} (about {}% of folders)", searching, percent)
+            } else {
+                format!("{} ({} folders scanned)", searching, self.dirs_scanned)
+            };
+        }
+        match (self.visible_results.len(), self.browse_mode) {
+            (1, true) => "1 entry".to_string(),
+            (count, true) => format!("{} entries", count),
+            (1, false) => "1 result".to_string(),
+            (count, false) => format!("{} results", count),
+        }
+    }
+
+    /// Adds a line to the activity log, stamped with the current time, dropping the oldest
+    /// past `ACTIVITY_LIMIT`.
+    fn log(&mut self, text: impl AsRef<str>) {
+        let activity = Arc::make_mut(&mut self.activity);
+        activity.push_back(format!("{}  {}", format_timestamp(SystemTime::now()), text.as_ref()));
+        if activity.len() > ACTIVITY_LIMIT {
+            activity.pop_front();
+        }
+    }
+
+    /// Tells the running search's threads to stop and makes any results still in flight stale.
+    fn cancel_search(&mut self) {
+        self.search_cancel.store(true, Ordering::Relaxed);
+        self.search_generation += 1;
+        self.searching = false;
+        self.diff_results = false;
+    }
+
+    /// Points the app at a new root directory, dropping results that belonged to the old one
+    /// unless `keep_results_on_root_change` is on. Returns false, leaving everything as it
+    /// was, if the user declines to lose changes to the loaded preset.
+    fn set_root(&mut self, dir: &Path) -> bool {
+        if !self.root_change_confirmed() {
+            return false;
+        }
+        self.cancel_search();
+        self.root_path = dir.to_string_lossy().to_string();
+        self.tidy_root();
+        self.clamp_to_sandbox();
+        self.probe_creation_times();
+        if !self.keep_results_on_root_change {
+            self.search_outcome = SearchOutcome::NotRun;
+            self.set_results(Arc::new(Vec::new()));
+        }
+        if let Some(settings) = self.current_folder_settings().cloned() {
+            self.apply_view(settings.view);
+            self.notice = format!("Using the options remembered for {}.", self.root_path);
+        }
+        if self.watch && self.remember_watch_terms {
+            if let Some(term) = self.watch_terms.get(&folder_key(&self.root_path)) {
+                self.search_term = term.clone();
+                self.resume_watch_search = true;
+            }
+        }
+        true
+    }
+
+    /// Checks whether the root's filesystem records creation times, as some Linux ones don't.
+    /// A root that can't be read yet, e.g. one being typed, leaves the last answer.
+    fn probe_creation_times(&mut self) {
+        let root = Path::new(&self.root_path);
+        if root.is_dir() {
+            self.creation_times = creation_times_supported(root);
+        }
+    }
+
+    /// Whether `dir` lies inside `sandbox_root`, see `search::sandbox_allows`. Always true
+    /// without a sandbox.
+    fn sandbox_allows(&self, dir: &Path) -> bool {
+        self.sandbox_root.as_ref().is_none_or(|sandbox| search::sandbox_allows(sandbox, dir))
+    }
+
+    /// Moves `root_path` back to the sandbox if it points outside, saying why. Returns false
+    /// if it had to.
+    fn clamp_to_sandbox(&mut self) -> bool {
+        let Some(sandbox) = self.sandbox_root.clone() else {
+            return true;
+        };
+        if self.sandbox_allows(Path::new(&self.root_path)) {
+            return true;
+        }
+        self.error_message = format!(
+            "{} is outside {}, which this app is limited to.",
+            self.root_path,
+            sandbox.display()
+        );
+        self.root_path = sandbox.to_string_lossy().to_string();
+        false
+    }
+
+    /// Adds the results of a search to those already listed, skipping paths listed before.
+    fn add_results(&mut self, results: &[ResultEntry]) {
+        let listed: HashSet<PathBuf> = self.search_results.iter().map(ResultEntry::path).collect();
+        let added: Vec<ResultEntry> =
+            results.iter().filter(|r| !listed.contains(&r.path())).cloned().collect();
+        let mut all = self.search_results.to_vec();
+        all.extend(added);
+        self.set_results(Arc::new(all));
+    }
+
+    /// With `canonicalize_root` on, replaces `root_path` with its canonical absolute form. A
+    /// path that can't be resolved, e.g. because it doesn't exist yet, is left as typed.
+    fn tidy_root(&mut self) {
+        if !self.canonicalize_root || self.root_path.is_empty() {
+            return;
+        }
+        match fs::canonicalize(&self.root_path) {
+            Ok(path) => {
+                self.root_path = without_verbatim_prefix(&path).to_string_lossy().to_string()
+            }
+            Err(_) => {
+                self.notice = format!("Couldn't resolve {}, so it is used as typed.", self.root_path)
+            }
+        }
+    }
+
+    /// Puts a file that was just opened at the top of the recent files, dropping the oldest
+    /// past `RECENT_FILES_LIMIT`. Folders aren't recorded.
+    fn record_opened(&mut self, path: &Path) {
+        if path.is_dir() {
+            return;
+        }
+        let opened = path.to_string_lossy().to_string();
+        let recent = Arc::make_mut(&mut self.recent_files);
+        recent.retain(|file| *file != opened);
+        recent.insert(0, opened);
+        recent.truncate(RECENT_FILES_LIMIT);
+    }
+
+    /// Drops a single entry, e.g. after it was deleted.
+    fn remove_result(&mut self, path: &Path) {
+        let results = self
+            .search_results
+            .iter()
+            .filter(|r| !r.is_at(path))
+            .cloned()
+            .collect();
+        self.set_results(Arc::new(results));
+    }
+
+    /// Replaces the results with a refreshed set, marking entries that appeared or disappeared.
+    /// Removed entries are kept, at their old position, until their highlight expires.
+    fn merge_refreshed_results(&mut self, results: &[ResultEntry]) {
+        let now = Instant::now();
+        let old_changes: HashMap<PathBuf, Option<(RowChange, Instant)>> =
+            self.search_results.iter().map(|r| (r.path(), r.change)).collect();
+        let mut merged: Vec<ResultEntry> = results
+            .iter()
+            .map(|r| {
+                let mut r = r.clone();
+                r.change = match old_changes.get(&r.path()) {
+                    None | Some(Some((RowChange::Removed, _))) => Some((RowChange::Added, now)),
+                    Some(change) => *change,
+                };
+                r
+            })
+            .collect();
+        let new_paths: HashSet<PathBuf> = results.iter().map(ResultEntry::path).collect();
+        for (i, old) in self.search_results.iter().enumerate() {
+            if !new_paths.contains(&old.path()) {
+                let mut removed = old.clone();
+                if !removed.is_removed() {
+                    removed.change = Some((RowChange::Removed, now));
+                }
+                merged.insert(i.min(merged.len()), removed);
+            }
+        }
+        self.set_results(Arc::new(merged));
+    }
+
+    /// Drops removed entries and clears added marks once their highlight has run its course.
+    fn expire_changes(&mut self) {
+        let expired = |r: &ResultEntry| r.change.is_some_and(|(_, at)| at.elapsed() >= CHANGE_HIGHLIGHT);
+        if !self.search_results.iter().any(expired) {
+            return;
+        }
+        let results = self
+            .search_results
+            .iter()
+            .filter(|r| !(r.is_removed() && expired(r)))
+            .map(|r| {
+                let mut r = r.clone();
+                if expired(&r) {
+                    r.change = None;
+                }
+                r
+            })
+            .collect();
+        self.set_results(Arc::new(results));
+    }
+
+    fn has_changes(&self) -> bool {
+        self.search_results.iter().any(|r| r.change.is_some())
+    }
+
+    /// Replaces the result set and recomputes anything derived from it.
+    /// Checked facets stay checked as long as their extension is still present.
+    fn set_results(&mut self, results: Arc<Vec<ResultEntry>>) {
+        self.common_prefix = common_dir_prefix(&results);
+        self.summary = SearchSummary::of(&results);
+        self.subdir_counts = Arc::new(count_by_subdir(Path::new(&self.root_path), &results));
+        let selected: HashSet<&str> = self
+            .extension_facets
+            .iter()
+            .filter(|facet| facet.selected)
+            .map(|facet| facet.extension.as_str())
+            .collect();
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for extension in results.iter().filter_map(facet_extension) {
+            *counts.entry(extension).or_default() += 1;
+        }
+        let facets = counts
+            .into_iter()
+            .map(|(extension, count)| ExtensionFacet {
+                selected: selected.contains(extension.as_str()),
+                extension,
+                count,
+            })
+            .collect();
+        self.search_results = results;
+        self.set_facets(Arc::new(facets));
+    }
+
+    /// Applies a change to the checked facets by re-filtering the results in memory.
+    fn set_facets(&mut self, facets: Arc<Vec<ExtensionFacet>>) {
+        self.extension_facets = facets;
+        self.update_visible_results();
+    }
+
+    fn set_sort_key(&mut self, key: SortKey) {
+        self.sort_key = key;
+        self.update_visible_results();
+        if key == SortKey::Accessed && access_times_look_stale(&self.search_results) {
+            self.notice = "No file here was read after it was last changed, so access times may \
+                not be recorded (e.g. a disk mounted noatime); sorting by modification time."
+                .to_string();
+        }
+    }
+
+    fn update_visible_results(&mut self) {
+        let selected: HashSet<&str> = self
+            .extension_facets
+            .iter()
+            .filter(|facet| facet.selected)
+            .map(|facet| facet.extension.as_str())
+            .collect();
+        let tag = self.tag_name.trim();
+        let tag_allows = |r: &ResultEntry| {
+            !self.tagged_only
+                || self
+                    .tags
+                    .get(&r.path())
+                    .is_some_and(|tags| tag.is_empty() || tags.iter().any(|t| t == tag))
+        };
+        let unsorted = self.sort_key == SortKey::Found && self.dirs_first == FolderPlacement::Mixed;
+        if selected.is_empty() && unsorted && !self.tagged_only {
+            self.visible_results = self.search_results.clone();
+        } else {
+            let mut visible: Vec<ResultEntry> = self
+                .search_results
+                .iter()
+                .filter(|r| {
+                    selected.is_empty()
+                        || facet_extension(r).is_some_and(|ext| selected.contains(ext.as_str()))
+                })
+                .filter(|r| tag_allows(r))
+                .cloned()
+                .collect();
+            sort_results(&mut visible, self.sort_key, self.dirs_first);
+            self.visible_results = Arc::new(visible);
+        }
+        self.name_groups = Arc::new(group_by_name(&self.visible_results, &self.name_groups));
+    }
+}
+
+fn build_ui() -> impl Widget<AppState> {
+    // Use string literals for the buttons instead of Label::new(...)
+    let choose_dir_btn = toolbar_button("Choose Directory", choose_directory);
+
+    let search_btn = BusyRing::new(toolbar_button("Search", search));
+
+    let refresh_btn = toolbar_button("Refresh", search);
+
+    // Applies the term to what is listed instead of searching the disk again.
+    let search_within_btn = Either::new(
+        |data: &AppState, _env| !data.search_results.is_empty() || !data.refined_from.is_empty(),
+        Flex::row()
+            .with_child(toolbar_button("Search within results", search_within_results))
+            .with_child(
+                toolbar_button("Undo refine", undo_refine)
+                    .disabled_if(|data: &AppState, _env| data.refined_from.is_empty()),
+            ),
+        SizedBox::empty(),
+    );
+
+    let cancel_btn = Either::new(
+        |data: &AppState, _env| data.searching,
+        toolbar_button("Cancel", |_ctx, data| data.cancel_search()),
+        SizedBox::empty(),
+    );
+
+    let browse_btn = toolbar_button("Browse", browse_root);
+
+    // TextBox: dark background and white text; uses lens for state binding
+    let directory_box = TextBox::new()
+        .with_placeholder("Enter directory path")
+        .with_text_size(14.0)
+        .with_text_color(theme::TEXT)
+        .padding(8.0)
+        .background(theme::SURFACE)
+        .lens(AppState::root_path);
+
+    let search_box = TextBox::new()
+        .with_placeholder("Enter search term")
+        .with_text_size(14.0)
+        .with_text_color(theme::TEXT)
+        .controller(SearchBoxKeys)
+        .padding(8.0)
+        .background(theme::SURFACE)
+        .lens(AppState::search_term);
+
+    // Alternatives to the main term, each in its own box, so OR needs no special syntax.
+    let or_terms_row = Flex::row()
+        .with_child(
+            List::new(|| {
+                Flex::row()
+                    .with_child(Label::new("or").padding(4.0))
+                    .with_child(
+                        TextBox::new()
+                            .with_placeholder("term")
+                            .with_text_color(theme::TEXT)
+                            .fix_width(120.0),
+                    )
+            })
+            .horizontal()
+            .lens(AppState::or_terms),
+        )
+        .with_child(
+            Button::new("+ Or")
+                .on_click(|_ctx, data: &mut AppState, _env| {
+                    Arc::make_mut(&mut data.or_terms).push(String::new())
+                })
+                .padding(4.0),
+        )
+        .padding(4.0);
+
+    // More folders to search with the same options, typed in or imported from a list.
+    let other_roots_row = Flex::row()
+        .with_child(
+            List::new(|| {
+                Flex::row()
+                    .with_child(Label::new("and in").padding(4.0))
+                    .with_child(
+                        TextBox::new()
+                            .with_placeholder("folder")
+                            .with_text_color(theme::TEXT)
+                            .fix_width(200.0),
+                    )
+            })
+            .horizontal()
+            .lens(AppState::other_roots),
+        )
+        .with_child(
+            Button::new("+ Folder")
+                .on_click(|_ctx, data: &mut AppState, _env| {
+                    Arc::make_mut(&mut data.other_roots).push(String::new())
+                })
+                .padding(4.0),
+        )
+        .with_child(toolbar_button("Import roots…", import_roots))
+        .with_child(Either::new(
+            |data: &AppState, _env| !data.other_roots.is_empty(),
+            toolbar_button("Clear folders", |_ctx, data| data.other_roots = Arc::new(Vec::new())),
+            SizedBox::empty(),
+        ))
+        .padding(4.0);
+
+    let open_all_btn = toolbar_button("Open all", open_all);
+
+    let open_progress = build_progress_row(|current, total| {
+        format!("Opening {} of {}", current, total)
+    })
+    .lens(AppState::open_progress);
+
+    let copy_args_btn = toolbar_button("Copy as args", copy_as_args);
+
+    let copy_table_btn = toolbar_button("Copy as table", copy_as_table);
+
+    let save_segments_btn = toolbar_button("Save in parts…", save_segments);
+
+    let link_into_btn = toolbar_button("Link into folder…", link_into_folder);
+
+    let copy_into_btn = toolbar_button("Copy into folder…", copy_into_folder);
+
+    let preserve_structure_box = Checkbox::new("Keep folder structure")
+        .padding(8.0)
+        .lens(AppState::preserve_structure);
+
+    let find_duplicates_btn = toolbar_button("Find duplicates", find_duplicates);
+
+    // Clearing out what an empty-entry or stale-file search found, after a review.
+    let trash_empty_btn = Either::new(
+        |data: &AppState, _env| {
+            (data.empty_only || data.stale_only) && !data.visible_results.is_empty()
+        },
+        toolbar_button("Trash all…", trash_all_results),
+        SizedBox::empty(),
+    );
+
+    // Archiving stale files somewhere else, keeping their folders with "Keep folder structure".
+    let move_stale_btn = Either::new(
+        |data: &AppState, _env| data.stale_only && !data.visible_results.is_empty(),
+        toolbar_button("Move into folder…", move_into_folder),
+        SizedBox::empty(),
+    );
+
+    // Compares exactly two marked files; greyed out otherwise.
+    let compare_btn = Flex::row()
+        .with_child(
+            toolbar_button("Compare", compare_marked)
+                .disabled_if(|data: &AppState, _env| data.marked.len() != 2),
+        )
+        .with_child(Either::new(
+            |data: &AppState, _env| !data.marked.is_empty(),
+            Flex::row()
+                .with_child(toolbar_button("Rename…", rename_marked))
+                .with_child(toolbar_button("Clear marks", |_ctx, data| {
+                    data.marked = Arc::new(Vec::new())
+                })),
+            SizedBox::empty(),
+        ));
+
+    let duplicate_progress = build_progress_row(|hashed, total| {
+        format!("Comparing files: {} of {}", hashed, total)
+    })
+    .lens(AppState::duplicate_progress);
+
+    let hash_progress = build_progress_row(|percent, _| format!("Computing SHA-256: {}%", percent))
+        .lens(AppState::hash_progress);
+
+    // The active filters at a glance, e.g. "ext: rs ✕"; clicking one clears it.
+    let filter_chips_bar = Either::new(
+        |data: &AppState, _env| data.show_filter_chips && !data.filter_chips().is_empty(),
+        Scroll::new(
+            List::new(|| {
+                Button::new(|chip: &FilterChip, _env: &_| format!("{} ✕", chip.label))
+                    .on_click(|ctx, chip: &mut FilterChip, _env| {
+                        ctx.submit_command(CLEAR_FILTER.with(chip.kind));
+                    })
+                    .padding((4.0, 0.0))
+            })
+            .horizontal()
+            .lens(lens::Map::new(
+                |data: &AppState| FilterChips(data.filter_chips()),
+                |_data: &mut AppState, _: FilterChips| {},
+            )),
+        )
+        .horizontal()
+        .expand_width()
+        .padding(4.0),
+        SizedBox::empty(),
+    );
+
+    let error_bar = Either::new(
+        |data: &AppState, _env| !data.error_message.is_empty(),
+        Flex::row()
+            .with_flex_child(
+                Label::new(|data: &AppState, _env: &_| data.error_message.clone())
+                    .with_text_color(Color::rgb8(0xff, 0x6b, 0x6b))
+                    .with_line_break_mode(druid::widget::LineBreaking::WordWrap)
+                    .expand_width(),
+                1.0,
+            )
+            .with_child(
+                Button::new("Dismiss")
+                    .on_click(|_ctx, data: &mut AppState, _env| data.error_message.clear()),
+            )
+            .padding(8.0),
+        SizedBox::empty(),
+    );
+
+    let notice_bar = Either::new(
+        |data: &AppState, _env| !data.notice.is_empty(),
+        Flex::row()
+            .with_flex_child(
+                Label::new(|data: &AppState, _env: &_| data.notice.clone())
+                    .with_text_color(theme::TEXT)
+                    .with_line_break_mode(druid::widget::LineBreaking::WordWrap)
+                    .expand_width(),
+                1.0,
+            )
+            .with_child(
+                Button::new("Dismiss").on_click(|_ctx, data: &mut AppState, _env| data.notice.clear()),
+            )
+            .padding(8.0),
+        SizedBox::empty(),
+    );
+
+    let batch_panel = Either::new(
+        |data: &AppState, _env| data.pending_batch.is_some(),
+        build_batch_panel(),
+        SizedBox::empty(),
+    );
+
+    let create_panel = Either::new(
+        |data: &AppState, _env| data.pending_create.is_some(),
+        build_create_panel(),
+        SizedBox::empty(),
+    );
+
+    let rename_panel = Either::new(
+        |data: &AppState, _env| data.pending_rename.is_some(),
+        build_rename_panel(),
+        SizedBox::empty(),
+    );
+
+    let collapse_prefix_box = Checkbox::new("Collapse common prefix")
+        .padding(8.0)
+        .lens(AppState::collapse_prefix);
+
+    let shorten_paths_box = Checkbox::new("Shorten long paths")
+        .padding(8.0)
+        .lens(AppState::shorten_paths);
+
+    let color_by_type_box = Checkbox::new("Color by file type")
+        .padding(8.0)
+        .lens(AppState::color_by_type);
+
+    let mark_folders_box = Checkbox::new("Outline folders")
+        .padding(8.0)
+        .lens(AppState::mark_folders);
+
+    let show_item_counts_box = Checkbox::new("Show folder item counts")
+        .padding(8.0)
+        .lens(AppState::show_item_counts);
+
+    let show_preview_box = Checkbox::new("Preview")
+        .padding(8.0)
+        .lens(AppState::show_preview);
+
+    let show_git_status_box = Checkbox::new("Git status")
+        .padding(8.0)
+        .lens(AppState::show_git_status);
+
+    let show_checksums_box = Checkbox::new("Checksums")
+        .padding(8.0)
+        .lens(AppState::show_checksums)
+        .controller(CancelHiddenChecksums);
+
+    let preview_filters_box = Checkbox::new("Preview filters")
+        .padding(8.0)
+        .lens(AppState::preview_filters);
+
+    let count_by_subdir_box = Checkbox::new("Count by folder")
+        .padding(8.0)
+        .lens(AppState::count_by_subdir);
+
+    let group_by_name_box = Checkbox::new("Group by name")
+        .padding(8.0)
+        .lens(AppState::group_by_name);
+
+    let columns_box = Checkbox::new("Columns")
+        .padding(8.0)
+        .lens(AppState::columns);
+
+    let color_by_age_box = Checkbox::new("Highlight recent changes")
+        .padding(8.0)
+        .lens(AppState::color_by_age);
+
+    let color_by_depth_box = Checkbox::new("Color by depth")
+        .padding(8.0)
+        .lens(AppState::color_by_depth);
+
+    let workspaces_bar = Flex::row()
+        .with_child(
+            TextBox::new()
+                .with_placeholder("Workspace name")
+                .fix_width(140.0)
+                .lens(AppState::workspace_name),
+        )
+        .with_child(
+            Button::new("Save workspace")
+                .on_click(|_ctx, data: &mut AppState, _env| data.save_workspace()),
+        )
+        .with_child(
+            Button::new("Remember for this folder")
+                .on_click(|_ctx, data: &mut AppState, _env| data.remember_folder_settings())
+                .padding((4.0, 0.0)),
+        )
+        .with_child(Either::new(
+            |data: &AppState, _env| data.current_folder_settings().is_some(),
+            Button::new("Forget for this folder").on_click(|_ctx, data: &mut AppState, _env| {
+                data.folder_settings = Arc::new(data.forgotten_folder_settings());
+            }),
+            SizedBox::empty(),
+        ))
+        .with_flex_child(
+            Scroll::new(
+                List::new(|| {
+                    Button::new(|workspace: &Workspace, _env: &_| workspace.name.clone())
+                        .on_click(|ctx, workspace: &mut Workspace, _env| {
+                            ctx.submit_command(LOAD_WORKSPACE.with(workspace.clone()));
+                        })
+                        .padding((4.0, 0.0))
+                })
+                .horizontal()
+                .lens(AppState::workspaces),
+            )
+            .horizontal()
+            .expand_width(),
+            1.0,
+        )
+        .padding(4.0);
+
+    let presets_bar = Flex::row()
+        .with_child(
+            TextBox::new()
+                .with_placeholder("Preset name")
+                .fix_width(140.0)
+                .lens(AppState::preset_name),
+        )
+        .with_child(
+            Button::new("Save preset")
+                .on_click(|_ctx, data: &mut AppState, _env| data.save_preset()),
+        )
+        .with_child(
+            Button::new(|data: &AppState, _env: &_| {
+                format!("Load preset ({}) ▾", data.presets.len())
+            })
+            .on_click(|ctx, data: &mut AppState, _env| {
+                let menu = data.presets.iter().fold(Menu::<AppState>::empty(), |menu, preset| {
+                    menu.entry(
+                        MenuItem::new(preset.name.clone()).command(LOAD_PRESET.with(preset.clone())),
+                    )
+                });
+                // Dropped down from the button's bottom edge.
+                let below = ctx.window_origin() + Vec2::new(0.0, ctx.size().height);
+                ctx.show_context_menu(menu, below);
+            })
+            .disabled_if(|data: &AppState, _env| data.presets.is_empty())
+            .padding((4.0, 0.0)),
+        )
+        .padding(4.0);
+
+    // Pinned locations, labelled with their last path component (the full path for a drive root).
+    let bookmarks_bar = Flex::row()
+        .with_child(toolbar_button("Bookmark folder", |ctx, data| {
+            ctx.submit_command(ADD_BOOKMARK.with(PathBuf::from(&data.root_path)));
+        }))
+        .with_flex_child(
+            Scroll::new(
+                List::new(|| {
+                    Flex::row()
+                        .with_child(
+                            Button::new(|bookmark: &String, _env: &_| {
+                                let path = Path::new(bookmark);
+                                match path.file_name() {
+                                    Some(name) => name.to_string_lossy().to_string(),
+                                    None => bookmark.clone(),
+                                }
+                            })
+                            .on_click(|ctx, bookmark: &mut String, _env| {
+                                ctx.submit_command(OPEN_BOOKMARK.with(bookmark.clone()));
+                            }),
+                        )
+                        .with_child(Button::new("×").on_click(
+                            |ctx, bookmark: &mut String, _env| {
+                                ctx.submit_command(REMOVE_BOOKMARK.with(bookmark.clone()));
+                            },
+                        ))
+                        .padding((4.0, 0.0))
+                })
+                .horizontal()
+                .lens(AppState::bookmarks),
+            )
+            .horizontal()
+            .expand_width(),
+            1.0,
+        )
+        .padding(4.0);
+
+    // Each narrowing step, e.g. "Back  projects “todo” › src “todo” › parser"; clicking one
+    // returns to that search.
+    let breadcrumbs = Either::new(
+        |data: &AppState, _env| !data.drill_stack.is_empty(),
+        Flex::row()
+            .with_child(toolbar_button("Back", |ctx, data| {
+                ctx.submit_command(POP_SEARCH_STEP.with(data.drill_stack.len() - 1));
+            }))
+            .with_flex_child(
+                Scroll::new(
+                    Flex::row()
+                        .with_child(
+                            List::new(|| {
+                                Flex::row()
+                                    .with_child(
+                                        Button::new(|(_, label): &(usize, String), _env: &_| {
+                                            label.clone()
+                                        })
+                                        .on_click(|ctx, (index, _): &mut (usize, String), _env| {
+                                            ctx.submit_command(POP_SEARCH_STEP.with(*index));
+                                        }),
+                                    )
+                                    .with_child(Label::new("›").padding((4.0, 0.0)))
+                            })
+                            .horizontal()
+                            .lens(lens::Map::new(
+                                |data: &AppState| {
+                                    let labels = data.drill_stack.iter().map(SearchStep::label);
+                                    let steps: Vec<(usize, String)> = labels.enumerate().collect();
+                                    Arc::new(steps)
+                                },
+                                |_data: &mut AppState, _steps: Arc<Vec<(usize, String)>>| {},
+                            )),
+                        )
+                        .with_child(Label::new(|data: &AppState, _env: &_| {
+                            SearchStep {
+                                root: data.root_path.clone(),
+                                term: data.search_term.clone(),
+                            }
+                            .label()
+                        })),
+                )
+                .horizontal()
+                .expand_width(),
+                1.0,
+            )
+            .padding(4.0),
+        SizedBox::empty(),
+    );
+
+    // Files opened from the results, newest first; clicking one opens it again.
+    let recent_files_bar = Either::new(
+        |data: &AppState, _env| !data.recent_files.is_empty(),
+        Flex::row()
+            .with_child(Label::new("Recent:").padding(4.0))
+            .with_flex_child(
+                Scroll::new(
+                    List::new(|| {
+                        Button::new(|file: &String, _env: &_| {
+                            let path = Path::new(file);
+                            path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().to_string()
+                        })
+                        .on_click(|ctx, file: &mut String, _env| {
+                            ctx.submit_command(OPEN_PATH.with(PathBuf::from(&file)));
+                        })
+                        .padding((4.0, 0.0))
+                    })
+                    .horizontal()
+                    .lens(AppState::recent_files),
+                )
+                .horizontal()
+                .expand_width(),
+                1.0,
+            )
+            .with_child(toolbar_button("Clear", |_ctx, data| {
+                data.recent_files = Arc::new(Vec::new())
+            }))
+            .padding(4.0),
+        SizedBox::empty(),
+    );
+
+    let append_results_box = Checkbox::new("Add to results")
+        .padding(8.0)
+        .lens(AppState::append_results);
+
+    let live_count_box = Checkbox::new("Count while searching")
+        .padding(8.0)
+        .lens(AppState::live_count);
+
+    let show_coverage_box = Checkbox::new("Show progress")
+        .padding(8.0)
+        .lens(AppState::show_coverage);
+
+    let watch_box = Checkbox::new("Watch for changes")
+        .padding(8.0)
+        .lens(AppState::watch);
+
+    let match_full_path_box = Checkbox::new("Match full path")
+        .padding(8.0)
+        .lens(AppState::match_full_path);
+
+    let modified_since_start_box = Checkbox::new("Changed since launch")
+        .padding(8.0)
+        .lens(AppState::modified_since_start);
+
+    let all_words_box = Checkbox::new("All words")
+        .padding(8.0)
+        .lens(AppState::all_words);
+
+    let term_filters_box = Checkbox::new("Filters in the term")
+        .padding(8.0)
+        .lens(AppState::term_filters);
+
+    let stem_only_box = Checkbox::new("Ignore extensions")
+        .padding(8.0)
+        .lens(AppState::stem_only);
+
+    // Typos only make sense for plain names, so this takes over from the other match modes.
+    let approximate_box = Flex::row()
+        .with_child(Checkbox::new("Allow typos").lens(AppState::approximate))
+        .with_child(Either::new(
+            |data: &AppState, _env| data.approximate,
+            Flex::row()
+                .with_child(Label::new("up to").padding(4.0))
+                .with_child(
+                    TextBox::new()
+                        .with_formatter(ParseFormatter::new())
+                        .update_data_while_editing(true)
+                        .fix_width(40.0)
+                        .lens(AppState::max_edit_distance),
+                )
+                .with_child(Label::new("edits").padding(4.0))
+                .with_child(
+                    Checkbox::new("Show typo counts")
+                        .padding(4.0)
+                        .lens(AppState::show_edit_distance),
+                ),
+            SizedBox::empty(),
+        ))
+        .padding(8.0);
+
+    let case_sensitive_box = Checkbox::new("Case sensitive")
+        .padding(8.0)
+        .lens(AppState::case_sensitive);
+
+    let normalize_unicode_box = Checkbox::new("Normalize Unicode")
+        .padding(8.0)
+        .lens(AppState::normalize_unicode);
+
+    // Both only matter when the search itself ignores case.
+    let full_case_folding_box = Either::new(
+        |data: &AppState, _env| !data.case_sensitive,
+        Checkbox::new("Fold ß, İ and ı")
+            .padding(8.0)
+            .lens(AppState::full_case_folding),
+        SizedBox::empty(),
+    );
+
+    let exact_case_first_box = Either::new(
+        |data: &AppState, _env| !data.case_sensitive,
+        Checkbox::new("Exact case first")
+            .padding(8.0)
+            .lens(AppState::exact_case_first),
+        SizedBox::empty(),
+    );
+
+    // Changing either refilters what's listed, like the extension checkboxes.
+    let tags_row = Flex::row()
+        .with_child(Label::new("Tag:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("e.g. important")
+                .fix_width(120.0)
+                .lens(lens::Map::new(
+                    |data: &AppState| data.tag_name.clone(),
+                    |data: &mut AppState, tag: String| {
+                        if data.tag_name != tag {
+                            data.tag_name = tag;
+                            data.update_visible_results();
+                        }
+                    },
+                )),
+        )
+        .with_child(
+            Checkbox::new("Only tagged")
+                .padding(4.0)
+                .lens(lens::Map::new(
+                    |data: &AppState| data.tagged_only,
+                    |data: &mut AppState, tagged_only: bool| {
+                        if data.tagged_only != tagged_only {
+                            data.tagged_only = tagged_only;
+                            data.update_visible_results();
+                        }
+                    },
+                )),
+        )
+        .padding(4.0);
+
+    let filters_row = Flex::row()
+        .with_child(Label::new("Extensions:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("e.g. rs, toml")
+                .fix_width(120.0)
+                .lens(AppState::extensions),
+        )
+        .with_child(
+            Checkbox::new("Type by contents:")
+                .padding(4.0)
+                .lens(AppState::sniff_types),
+        )
+        .with_child(
+            TextBox::new()
+                .with_placeholder("e.g. image, application/pdf")
+                .fix_width(180.0)
+                .lens(AppState::mime_types),
+        )
+        .with_child(Label::new("Skip folders:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("e.g. target, .git")
+                .fix_width(140.0)
+                .lens(AppState::exclude_dirs),
+        )
+        .with_child(
+            Checkbox::new("Show hidden files")
+                .padding(4.0)
+                .lens(AppState::show_hidden),
+        )
+        .with_child(
+            Checkbox::new("Include subfolders")
+                .padding(4.0)
+                .lens(AppState::recursive),
+        )
+        .with_child(Label::new("Max depth:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("0 = any")
+                .with_formatter(ParseFormatter::new())
+                .update_data_while_editing(true)
+                .fix_width(60.0)
+                .lens(AppState::max_depth),
+        )
+        .with_child(
+            Checkbox::new("Only the largest")
+                .padding(4.0)
+                .lens(AppState::largest_only),
+        )
+        .with_child(Either::new(
+            |data: &AppState, _env| data.largest_only,
+            Flex::row()
+                .with_child(
+                    TextBox::new()
+                        .with_formatter(ParseFormatter::new())
+                        .update_data_while_editing(true)
+                        .fix_width(60.0)
+                        .lens(AppState::largest_count),
+                )
+                .with_child(Label::new("files").padding(4.0)),
+            SizedBox::empty(),
+        ))
+        .padding(4.0);
+
+    let created_row = Either::new(
+        |data: &AppState, _env| data.creation_times,
+        Flex::row()
+            .with_child(Label::new("Created on or after:").padding(4.0))
+            .with_child(
+                TextBox::new()
+                    .with_placeholder("YYYY-MM-DD")
+                    .fix_width(110.0)
+                    .lens(AppState::created_after),
+            )
+            .with_child(Label::new("and before:").padding(4.0))
+            .with_child(
+                TextBox::new()
+                    .with_placeholder("YYYY-MM-DD")
+                    .fix_width(110.0)
+                    .lens(AppState::created_before),
+            ),
+        Label::new("Creation times aren't recorded on this system, so they can't be filtered on.")
+            .with_text_size(12.0)
+            .with_text_color(Color::grey(0.7))
+            .padding(4.0),
+    )
+    .padding(4.0);
+
+    let sort_row = Flex::row()
+        .with_child(Label::new("Sort:").padding(4.0))
+        .with_child(
+            RadioGroup::row(vec![
+                ("As found", SortKey::Found),
+                ("Name", SortKey::Name),
+                ("Size", SortKey::Size),
+                ("Modified", SortKey::Modified),
+                ("Oldest", SortKey::Oldest),
+                ("Recently used", SortKey::Accessed),
+                ("Path", SortKey::Path),
+            ])
+            .lens(lens::Map::new(
+                |data: &AppState| data.sort_key,
+                |data: &mut AppState, key: SortKey| {
+                    if key != data.sort_key {
+                        data.set_sort_key(key);
+                    }
+                },
+            )),
+        )
+        .padding(4.0);
+
+    let permissions_row = Flex::row()
+        .with_child(Label::new("Permissions:").padding(4.0))
+        .with_child(
+            RadioGroup::row(
+                permission_filters()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (label, _))| (label, index)),
+            )
+            .lens(AppState::permission_filter),
+        )
+        .padding(4.0);
+
+    let resolve_links_box = Checkbox::new("Resolve symlinks")
+        .padding(8.0)
+        .lens(AppState::resolve_links);
+
+    let match_link_targets_box = Checkbox::new("Match link targets")
+        .padding(8.0)
+        .lens(AppState::match_link_targets);
+
+    let broken_links_only_box = Checkbox::new("Broken symlinks only")
+        .padding(8.0)
+        .lens(AppState::broken_links_only);
+
+    let empty_only_box = Checkbox::new("Empty files and folders only")
+        .padding(8.0)
+        .lens(AppState::empty_only);
+
+    // Turning it on also lists the oldest first, the order they'd be archived in.
+    let stale_only_box = Checkbox::new("Stale files only")
+        .padding(8.0)
+        .lens(lens::Map::new(
+            |data: &AppState| data.stale_only,
+            |data: &mut AppState, stale_only: bool| {
+                if data.stale_only != stale_only {
+                    data.stale_only = stale_only;
+                    if stale_only {
+                        data.set_sort_key(SortKey::Oldest);
+                    }
+                }
+            },
+        ));
+
+    let search_contents_box = Checkbox::new("Search file contents")
+        .padding(8.0)
+        .lens(AppState::search_contents);
+
+    let content_mode_row = Either::new(
+        |data: &AppState, _env| data.search_contents,
+        Flex::row()
+            .with_child(Label::new("Match:").padding(4.0))
+            .with_child(
+                RadioGroup::row(
+                    content_modes()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, (label, _))| (label, index)),
+                )
+                .lens(AppState::content_mode),
+            )
+            .padding(4.0),
+        SizedBox::empty(),
+    );
+
+    // Only meaningful for content search, so it's hidden otherwise.
+    let show_match_line_box = Either::new(
+        |data: &AppState, _env| data.search_contents,
+        Checkbox::new("Show first matching line")
+            .padding(8.0)
+            .lens(AppState::show_match_line),
+        SizedBox::empty(),
+    );
+
+    let skip_binary_box = Either::new(
+        |data: &AppState, _env| data.search_contents,
+        Checkbox::new("Skip binary files")
+            .padding(8.0)
+            .lens(AppState::skip_binary),
+        SizedBox::empty(),
+    );
+
+    // Collapsible log of what the app did, newest last.
+    let activity_section = Either::new(
+        |data: &AppState, _env| !data.activity.is_empty(),
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(
+                Flex::row()
+                    .with_child(
+                        Label::new(|data: &AppState, _env: &_| {
+                            let arrow = if data.show_activity { "▾" } else { "▸" };
+                            format!("{} Activity ({})", arrow, data.activity.len())
+                        })
+                        .with_text_color(theme::TEXT)
+                        .on_click(|_ctx, data: &mut AppState, _env| {
+                            data.show_activity = !data.show_activity
+                        }),
+                    )
+                    .with_child(toolbar_button("Clear", |_ctx, data| {
+                        data.activity = Arc::new(VecDeque::new())
+                    }))
+                    .with_child(toolbar_button("Save report…", save_report)),
+            )
+            .with_child(Either::new(
+                |data: &AppState, _env| data.show_activity,
+                Label::new(|data: &AppState, _env: &_| {
+                    data.activity.iter().map(String::as_str).collect::<Vec<_>>().join("\n")
+                })
+                    .with_text_size(12.0)
+                    .with_text_color(Color::grey(0.8))
+                    .with_line_break_mode(druid::widget::LineBreaking::WordWrap)
+                    .padding((12.0, 4.0, 0.0, 0.0)),
+                SizedBox::empty(),
+            ))
+            .padding(8.0),
+        SizedBox::empty(),
+    );
+
+    // Collapsible totals for the results; clicking the header shows or hides them.
+    let summary_section = Either::new(
+        |data: &AppState, _env| !data.browse_mode && !data.search_results.is_empty(),
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(
+                Label::new(|data: &AppState, _env: &_| {
+                    if data.show_summary { "▾ Summary" } else { "▸ Summary" }.to_string()
+                })
+                .with_text_color(theme::TEXT)
+                .on_click(|_ctx, data: &mut AppState, _env| data.show_summary = !data.show_summary),
+            )
+            .with_child(Either::new(
+                |data: &AppState, _env| data.show_summary,
+                Label::new(|data: &AppState, _env: &_| data.summary.text())
+                    .with_text_size(13.0)
+                    .with_text_color(Color::grey(0.8))
+                    .with_line_break_mode(druid::widget::LineBreaking::WordWrap)
+                    .padding((12.0, 4.0, 0.0, 0.0)),
+                SizedBox::empty(),
+            ))
+            .padding(8.0),
+        SizedBox::empty(),
+    );
+
+    // Header showing the prefix stripped from every row, only while collapsing.
+    let prefix_header = Either::new(
+        |data: &AppState, _env| data.collapse_prefix && !data.common_prefix.as_os_str().is_empty(),
+        Label::new(|data: &AppState, _env: &_| format!("In {}", data.common_prefix.display()))
+            .with_text_size(14.0)
+            .with_text_color(Color::grey(0.8))
+            .padding(8.0),
+        SizedBox::empty(),
+    );
+
+    // Each row also receives the shared RowSettings alongside its ResultEntry. Only the rows in
+    // view are built, so even huge result sets scroll smoothly.
+    let results_list = LazyList::new(build_result_row)
+        .with_spacing(4.0)
+        .scroll_to_marked(RowSettings::is_highlighted)
+        .lens(lens::Map::new(
+            |data: &AppState| (data.row_settings(), data.visible_results.clone()),
+            // Rows never edit their entry, so there is nothing to write back.
+            |_data: &mut AppState, _: (RowSettings, Arc<Vec<ResultEntry>>)| {},
+        ));
+
+    // How many results each subfolder of the root holds, e.g. "src: 120  tests: 14"; clicking a
+    // folder searches just that folder.
+    let subdir_counts_row = Either::new(
+        |data: &AppState, _env| {
+            data.count_by_subdir && !data.browse_mode && !data.subdir_counts.is_empty()
+        },
+        Flex::row()
+            .with_child(Label::new("By folder:").padding(4.0))
+            .with_flex_child(
+                Scroll::new(
+                    List::new(|| {
+                        Button::new(|bucket: &SubdirCount, _env: &_| {
+                            format!("{}: {}", bucket.name, bucket.count)
+                        })
+                        .on_click(|ctx, bucket: &mut SubdirCount, _env| {
+                            ctx.submit_command(SEARCH_IN.with(bucket.path.clone()));
+                        })
+                        .padding((4.0, 0.0))
+                    })
+                    .horizontal()
+                    .lens(AppState::subdir_counts),
+                )
+                .horizontal(),
+                1.0,
+            )
+            .padding(4.0),
+        SizedBox::empty(),
+    );
+
+    // Checkboxes for the extensions in the results, e.g. "rs (12)"; none checked shows all.
+    let facets_row = Either::new(
+        |data: &AppState, _env| !data.extension_facets.is_empty(),
+        Flex::row()
+            .with_child(Label::new("Types:").padding(4.0))
+            .with_flex_child(
+                Scroll::new(
+                    List::new(|| {
+                        Flex::row()
+                            .with_child(Checkbox::new("").lens(ExtensionFacet::selected))
+                            .with_child(
+                                Label::new(|facet: &ExtensionFacet, _env: &_| {
+                                    format!("{} ({})", facet.extension, facet.count)
+                                })
+                                .on_click(|_ctx, facet: &mut ExtensionFacet, _env| {
+                                    facet.selected = !facet.selected
+                                }),
+                            )
+                            .padding((4.0, 0.0))
+                    })
+                    .horizontal(),
+                )
+                .horizontal()
+                .lens(lens::Map::new(
+                    |data: &AppState| data.extension_facets.clone(),
+                    |data: &mut AppState, facets: Arc<Vec<ExtensionFacet>>| {
+                        if !Arc::ptr_eq(&data.extension_facets, &facets) {
+                            data.set_facets(facets);
+                        }
+                    },
+                )),
+                1.0,
+            )
+            .padding(4.0),
+        SizedBox::empty(),
+    );
+
+    let scroll = Scroll::new(results_list)
+        .background(theme::BACKGROUND)
+        .expand();
+
+    // In place of an empty list, say whether the search found nothing or couldn't run.
+    let empty_message = Either::new(
+        |data: &AppState, _env| matches!(data.search_outcome, SearchOutcome::Failed(_)),
+        Label::new(|data: &AppState, _env: &_| match &data.search_outcome {
+            SearchOutcome::Failed(message) => message.clone(),
+            _ => String::new(),
+        })
+        .with_text_color(Color::rgb8(0xff, 0x6b, 0x6b))
+        .with_line_break_mode(druid::widget::LineBreaking::WordWrap),
+        Label::new(|data: &AppState, _env: &_| {
+            if data.browse_mode { "This folder is empty" } else { "No results" }.to_string()
+        })
+        .with_text_color(Color::grey(0.7)),
+    )
+    .padding(12.0)
+    .expand()
+    .background(theme::BACKGROUND);
+
+    let results_view = Either::new(
+        |data: &AppState, _env| {
+            data.visible_results.is_empty()
+                && !data.searching
+                && data.search_outcome != SearchOutcome::NotRun
+        },
+        empty_message,
+        Either::new(
+            |data: &AppState, _env| data.searching && data.visible_results.is_empty(),
+            SkeletonRows { phase: 0.0 }.expand().background(theme::BACKGROUND),
+            scroll,
+        ),
+    );
+
+    // The full path of the hovered row, since row text may be shortened.
+    let hover_line = Either::new(
+        |data: &AppState, _env| !data.hover_path.is_empty(),
+        Label::new(|data: &AppState, _env: &_| data.hover_path.clone())
+            .with_text_size(12.0)
+            .with_text_color(Color::grey(0.7))
+            .with_line_break_mode(druid::widget::LineBreaking::WordWrap)
+            .expand_width()
+            .padding(4.0),
+        SizedBox::empty(),
+    );
+
+    let preview_pane = Either::new(
+        |data: &AppState, _env| {
+            data.show_preview
+                && !data.highlighted.as_os_str().is_empty()
+                && data.preview_path == data.highlighted
+        },
+        Either::new(
+            |data: &AppState, _env| data.preview_image.is_some(),
+            Flex::column()
+                .with_flex_child(
+                    Image::new(ImageBuf::empty())
+                        .fill_mode(FillStrat::Contain)
+                        .controller(ShowPreviewImage)
+                        .expand_width(),
+                    1.0,
+                )
+                .with_child(
+                    Label::new(|data: &AppState, _env: &_| data.preview_text.clone())
+                        .with_text_size(12.0)
+                        .with_text_color(theme::TEXT),
+                )
+                .padding(8.0),
+            Scroll::new(
+                Label::new(|data: &AppState, _env: &_| data.preview_text.clone())
+                    .with_font(theme::RESULT_FONT)
+                    .with_text_size(12.0)
+                    .with_text_color(theme::TEXT)
+                    .padding(8.0),
+            ),
+        )
+        .fix_height(PREVIEW_HEIGHT)
+        .expand_width()
+        .background(theme::SURFACE)
+        .border(theme::ACCENT, 1.0)
+        .rounded(4.0),
+        SizedBox::empty(),
+    );
+
+    let results_area = Either::new(
+        |data: &AppState, _env| data.show_duplicates,
+        build_duplicates_view(),
+        Either::new(
+            |data: &AppState, _env| data.group_by_name && !data.visible_results.is_empty(),
+            build_name_groups_view(),
+            results_view,
+        ),
+    );
+    #[cfg(target_os = "macos")]
+    let results_area = results_area.controller(QuickLook);
+
+    let settings_btn = Button::new(|data: &AppState, _env: &_| {
+        if data.show_settings { "Hide settings" } else { "Settings" }.to_string()
+    })
+    .on_click(|_ctx, data: &mut AppState, _env| data.show_settings = !data.show_settings)
+    .padding(8.0)
+    .background(keyboard::focus_ring_background())
+    .controller(KeyboardActivate::new(|_ctx, data: &mut AppState| {
+        data.show_settings = !data.show_settings
+    }));
+
+    let settings_panel = Either::new(
+        |data: &AppState, _env| data.show_settings,
+        build_settings_panel(),
+        SizedBox::empty(),
+    );
+
+    // Main layout with black background. The folder and search controls stay pinned at the top,
+    // the options below them scroll once they'd take more than a third of the window, and the
+    // results get whatever is left.
+    let controls = Flex::column()
+        .with_child(palette::build_palette(palette_actions()))
+        .with_child(choose_dir_btn)
+        .with_child(directory_box)
+        .with_child(other_roots_row)
+        .with_child(search_box)
+        .with_child(or_terms_row)
+        // Buttons sit right after the fields they act on so Tab moves through them in reading order.
+        .with_child(
+            Flex::row()
+                .with_child(search_btn)
+                .with_child(refresh_btn)
+                .with_child(search_within_btn)
+                .with_child(cancel_btn)
+                .with_child(browse_btn)
+                .with_child(open_all_btn)
+                .with_child(find_duplicates_btn)
+                .with_child(trash_empty_btn)
+                .with_child(move_stale_btn)
+                .with_child(compare_btn)
+                .with_child(copy_args_btn)
+                .with_child(copy_table_btn)
+                .with_child(save_segments_btn)
+                .with_child(link_into_btn)
+                .with_child(copy_into_btn)
+                .with_child(preserve_structure_box),
+        )
+        .with_child(filter_chips_bar)
+        .with_child(open_progress)
+        .with_child(duplicate_progress)
+        .with_child(hash_progress)
+        .with_child(error_bar)
+        .with_child(notice_bar)
+        .with_child(batch_panel)
+        .with_child(create_panel)
+        .with_child(rename_panel);
+
+    let options = Flex::column()
+        .with_child(workspaces_bar)
+        .with_child(presets_bar)
+        .with_child(bookmarks_bar)
+        .with_child(recent_files_bar)
+        .with_child(tags_row)
+        .with_child(settings_btn)
+        .with_child(settings_panel)
+        .with_child(activity_section)
+        .with_child(
+            Flex::row()
+                .with_child(case_sensitive_box)
+                .with_child(all_words_box)
+                .with_child(term_filters_box)
+                .with_child(stem_only_box)
+                .with_child(approximate_box)
+                .with_child(exact_case_first_box)
+                .with_child(full_case_folding_box)
+                .with_child(normalize_unicode_box)
+                .with_child(match_full_path_box)
+                .with_child(search_contents_box)
+                .with_child(show_match_line_box)
+                .with_child(skip_binary_box)
+                .with_child(resolve_links_box)
+                .with_child(match_link_targets_box)
+                .with_child(broken_links_only_box)
+                .with_child(empty_only_box)
+                .with_child(stale_only_box)
+                .with_child(build_stay_on_filesystem_box())
+                .with_child(Checkbox::new("Low impact").padding(8.0).lens(AppState::low_impact)),
+        )
+        .with_child(content_mode_row)
+        .with_child(filters_row)
+        .with_child(permissions_row)
+        .with_child(created_row)
+        .with_child(
+            Flex::row()
+                .with_child(columns_box)
+                .with_child(group_by_name_box)
+                .with_child(collapse_prefix_box)
+                .with_child(shorten_paths_box)
+                .with_child(color_by_type_box)
+                .with_child(color_by_age_box)
+                .with_child(color_by_depth_box)
+                .with_child(mark_folders_box)
+                .with_child(count_by_subdir_box)
+                .with_child(show_item_counts_box)
+                .with_child(show_git_status_box)
+                .with_child(show_checksums_box)
+                .with_child(show_preview_box)
+                .with_child(preview_filters_box)
+                .with_child(append_results_box)
+                .with_child(live_count_box)
+                .with_child(show_coverage_box)
+                .with_child(watch_box)
+                .with_child(modified_since_start_box),
+        )
+        .with_child(sort_row);
+
+    let count_line = Either::new(
+        |data: &AppState, _env| data.searching || data.search_outcome == SearchOutcome::Completed,
+        Label::new(|data: &AppState, _env: &_| data.count_text())
+            .with_text_size(12.0)
+            .with_text_color(Color::grey(0.7))
+            .padding((8.0, 2.0)),
+        SizedBox::empty(),
+    );
+
+    // Only worked out while there are results to count.
+    let filter_previews_line = Either::new(
+        |data: &AppState, _env| {
+            data.preview_filters && !data.searching && !data.search_results.is_empty()
+        },
+        Label::new(|data: &AppState, _env: &_| data.filter_previews())
+            .with_text_size(12.0)
+            .with_text_color(Color::grey(0.7))
+            .padding((8.0, 2.0)),
+        SizedBox::empty(),
+    );
+
+    let results = Flex::column()
+        .with_child(count_line)
+        .with_child(filter_previews_line)
+        .with_child(breadcrumbs)
+        .with_child(subdir_counts_row)
+        .with_child(facets_row)
+        .with_child(summary_section)
+        .with_child(prefix_header)
+        .with_child(Either::new(
+            |data: &AppState, _env| data.columns && !data.visible_results.is_empty(),
+            build_column_header(),
+            SizedBox::empty(),
+        ))
+        .with_flex_child(results_area, 1.0)
+        .with_child(preview_pane)
+        .with_child(hover_line);
+
+    let root = Pinned::new(controls, Scroll::new(options), results, 1.0 / 3.0)
+        .padding(12.0)
+        .background(theme::BACKGROUND)
+        .controller(PersistConfig)
+        .controller(WatchRoot::default())
+        .controller(FadeChanges { timer: None })
+        .controller(LogResults { timer: None })
+        .controller(Shortcuts)
+        .controller(CountDirItems)
+        .controller(LookUpGitStatus)
+        .controller(LoadPreview);
+
+    // Re-apply the theme whenever it changes so the colors and fonts update live.
+    EnvScope::new(
+        |env, data: &AppState| {
+            data.theme.apply(env);
+            theme::set_result_font(env, data.monospace_results);
+        },
+        root,
+    )
+}
+
+/// Actions shared by the toolbar buttons and the command palette.
+fn search(ctx: &mut EventCtx, data: &mut AppState) {
+    start_search(ctx.window_sink(), data);
+}
+
+/// Narrows the results to those whose name, or path with "Match full path", matches the search
+/// term as a regex, keeping the set it replaced for `undo_refine`. With "Filters in the term",
+/// words like `size>10MB` narrow by size and date instead of being matched.
+fn search_within_results(_ctx: &mut EventCtx, data: &mut AppState) {
+    let options = data.search_options();
+    let term = if data.trim_terms { options.term.trim() } else { &options.term };
+    let pattern = match regex::RegexBuilder::new(term).case_insensitive(!data.case_sensitive).build()
+    {
+        Ok(pattern) => pattern,
+        Err(err) => {
+            data.error_message = SearchError::InvalidPattern(err).to_string();
+            return;
+        }
+    };
+    let full_path = data.match_full_path;
+    let narrowed: Vec<ResultEntry> = data
+        .search_results
+        .iter()
+        .filter(|r| {
+            let text = if full_path || r.name.is_empty() {
+                r.display()
+            } else {
+                r.name.to_string_lossy().into_owned()
+            };
+            pattern.is_match(&text)
+                && options.size_within(r.size.filter(|_| !r.is_dir))
+                && options.modified_within(r.modified)
+        })
+        .cloned()
+        .collect();
+    let before = data.search_results.len();
+    data.notice = format!("Kept {} of {} results matching \"{}\".", narrowed.len(), before, term);
+    Arc::make_mut(&mut data.refined_from).push(data.search_results.clone());
+    data.set_results(Arc::new(narrowed));
+}
+
+/// Brings back the results listed before the last "Search within results".
+fn undo_refine(_ctx: &mut EventCtx, data: &mut AppState) {
+    if let Some(results) = Arc::make_mut(&mut data.refined_from).pop() {
+        data.notice.clear();
+        data.set_results(results);
+    }
+}
+
+fn choose_directory(ctx: &mut EventCtx, _data: &mut AppState) {
+    ctx.submit_command(Command::new(commands::SHOW_OPEN_PANEL, FileDialogOptions::default(), Target::Auto));
+}
+
+fn browse_root(ctx: &mut EventCtx, data: &mut AppState) {
+    ctx.submit_command(BROWSE_TO.with(PathBuf::from(&data.root_path)));
+}
+
+/// Starts "Open all" on the visible results, asking first if there are many of them.
+fn open_all(ctx: &mut EventCtx, data: &mut AppState) {
+    if data.open_progress.is_some() || data.visible_results.is_empty() {
+        return;
+    }
+    let paths: Vec<PathBuf> = data
+        .visible_results
+        .iter()
+        .filter(|r| !r.is_removed())
+        .map(|r| r.path())
+        .collect();
+    if paths.len() > data.open_all_warn_threshold {
+        let question = format!("Open all {} results?", paths.len());
+        if !confirm("Open all", &question) {
+            return;
+        }
+    }
+    data.open_progress = Some((0, paths.len()));
+    spawn_open_all(ctx.window_sink(), data.open_command.clone(), paths);
+}
+
+/// Copies the visible results to the clipboard as shell-quoted arguments.
+fn copy_as_args(_ctx: &mut EventCtx, data: &mut AppState) {
+    let args: Vec<String> = data
+        .visible_results
+        .iter()
+        .filter(|r| !r.is_removed())
+        .map(|r| shell_quote(&r.display()))
+        .collect();
+    druid::Application::global().clipboard().put_string(args.join(" "));
+}
+
+/// The names of `dir`'s immediate entries, sorted, with a `/` after folders and, if asked,
+/// a tab and the size after files.
+fn dir_listing(dir: &Path, with_sizes: bool) -> std::io::Result<Vec<String>> {
+    let mut entries: Vec<(OsString, Option<fs::Metadata>)> = fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| (entry.file_name(), entry.metadata().ok()))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let lines = entries
+        .into_iter()
+        .map(|(name, metadata)| {
+            let name = name.to_string_lossy();
+            match metadata {
+                Some(metadata) if metadata.is_dir() => format!("{}/", name),
+                Some(metadata) if with_sizes => format!("{}\t{}", name, format_size(metadata.len())),
+                _ => name.to_string(),
+            }
+        })
+        .collect();
+    Ok(lines)
+}
+
+/// Copies the visible results to the clipboard as a tab-separated table.
+fn copy_as_table(_ctx: &mut EventCtx, data: &mut AppState) {
+    let rows: Vec<String> = data
+        .visible_results
+        .iter()
+        .filter(|r| !r.is_removed())
+        .map(table_row)
+        .collect();
+    let table = format!("path\tsize\tmodified\n{}\n", rows.join("\n"));
+    druid::Application::global().clipboard().put_string(table);
+}
+
+/// Writes the visible results' paths, one per line, into a folder the user picks, split across
+/// `results-001.txt`, `results-002.txt` and so on with `segment_size` paths in each, for tools
+/// that take only so many arguments. Nothing is written if any of those files already exists.
+fn save_segments(_ctx: &mut EventCtx, data: &mut AppState) {
+    let paths: Vec<String> = data
+        .visible_results
+        .iter()
+        .filter(|r| !r.is_removed())
+        .map(ResultEntry::display)
+        .collect();
+    if paths.is_empty() {
+        return;
+    }
+    let Some(folder) = rfd::FileDialog::new().pick_folder() else {
+        return;
+    };
+    let size = data.segment_size.max(1);
+    let count = paths.len().div_ceil(size);
+    let width = count.to_string().len().max(3);
+    let files: Vec<PathBuf> = (1..=count)
+        .map(|n| folder.join(format!("results-{:0width$}.txt", n, width = width)))
+        .collect();
+    if let Some(taken) = files.iter().find(|file| file.exists()) {
+        data.error_message = format!("{} already exists; pick an empty folder.", taken.display());
+        return;
+    }
+    for (file, chunk) in files.iter().zip(paths.chunks(size)) {
+        if let Err(err) = fs::write(file, chunk.join("\n") + "\n") {
+            data.error_message = format!("Couldn't write {}: {}", file.display(), err);
+            return;
+        }
+    }
+    let plural = if count == 1 { "" } else { "s" };
+    data.notice = format!(
+        "Saved {} paths to {} file{} in {}.",
+        paths.len(),
+        count,
+        plural,
+        folder.display()
+    );
+    data.log(format!("Saved {} results in {} parts to {}", paths.len(), count, folder.display()));
+}
+
+/// Adds the folders listed in a text file the user picks to the other roots. Lines that aren't
+/// folders are reported but don't stop the rest from being added.
+fn import_roots(_ctx: &mut EventCtx, data: &mut AppState) {
+    let Some(file) = rfd::FileDialog::new().pick_file() else {
+        return;
+    };
+    let text = match fs::read_to_string(&file) {
+        Ok(text) => text,
+        Err(err) => {
+            data.error_message = format!("Couldn't read {}: {}", file.display(), err);
+            return;
+        }
+    };
+    let (folders, rejected) = search::parse_root_list(&text);
+    let count = folders.len();
+    let roots = Arc::make_mut(&mut data.other_roots);
+    roots.retain(|root| !root.trim().is_empty());
+    for folder in folders {
+        if !roots.contains(&folder) {
+            roots.push(folder);
+        }
+    }
+    data.notice = if rejected.is_empty() {
+        format!("Imported {} folders.", count)
+    } else {
+        format!("Imported {} folders; skipped {} that aren't folders.", count, rejected.join(", "))
+    };
+}
+
+/// Symlinks every visible result into a folder the user picks.
+fn link_into_folder(_ctx: &mut EventCtx, data: &mut AppState) {
+    let Some(folder) = rfd::FileDialog::new().pick_folder() else {
+        return;
+    };
+    let mut failures = Vec::new();
+    for result in data.visible_results.iter().filter(|r| !r.is_removed()) {
+        if let Err(err) = link_into(&folder, &result.path()) {
+            failures.push(format!("{}: {}", result.display(), err));
+        }
+    }
+    if !failures.is_empty() {
+        data.error_message = format!("Couldn't link {}", failures.join("; "));
+    }
+}
+
+/// Copies every visible file into a folder the user picks, flat or keeping the folders they are
+/// in under the search root. Folders among the results are skipped; the files in them are only
+/// copied if they are results themselves.
+fn copy_into_folder(ctx: &mut EventCtx, data: &mut AppState) {
+    let files: Vec<PathBuf> = data
+        .visible_results
+        .iter()
+        .filter(|r| !r.is_removed() && !r.is_dir)
+        .map(ResultEntry::path)
+        .collect();
+    if files.is_empty() {
+        return;
+    }
+    let Some(folder) = rfd::FileDialog::new().pick_folder() else {
+        return;
+    };
+    data.notice = format!("Copying {} files into {}…", files.len(), folder.display());
+    let root = Some(PathBuf::from(&data.root_path)).filter(|_| data.preserve_structure);
+    spawn_copy_into(ctx.window_sink(), folder, root, files);
+}
+
+/// Copies `files` into `folder` on a background thread, so large files don't hold up the
+/// window, reporting how many were copied and what went wrong with the rest.
+fn spawn_copy_into(sink: WindowSink, folder: PathBuf, root: Option<PathBuf>, files: Vec<PathBuf>) {
+    thread::spawn(move || {
+        let mut copied = 0;
+        let mut failures = Vec::new();
+        for file in files {
+            match copy_into(&folder, &file, root.as_deref()) {
+                Ok(()) => copied += 1,
+                Err(err) => failures.push(format!("{}: {}", file.display(), err)),
+            }
+        }
+        let _ = sink.submit_command(FILES_COPIED, (folder, copied, failures));
+    });
+}
+
+/// Lists every visible file for review before moving them into a folder the user picks, laid
+/// out as `copy_into_folder` does. Folders among the results stay where they are.
+fn move_into_folder(_ctx: &mut EventCtx, data: &mut AppState) {
+    let files: Vec<ResultEntry> =
+        data.visible_results.iter().filter(|r| !r.is_removed() && !r.is_dir).cloned().collect();
+    if files.is_empty() {
+        return;
+    }
+    let Some(folder) = rfd::FileDialog::new().pick_folder() else {
+        return;
+    };
+    data.pending_batch = Some(PendingBatch {
+        permanent: false,
+        destination: Some(folder),
+        structure_root: Some(PathBuf::from(&data.root_path)).filter(|_| data.preserve_structure),
+        files: Arc::new(files),
+        group: None,
+    });
+}
+
+/// Moves `files` into `folder` on a background thread, reporting which ones moved and what
+/// went wrong with the rest.
+fn spawn_move_into(sink: WindowSink, folder: PathBuf, root: Option<PathBuf>, files: Vec<PathBuf>) {
+    thread::spawn(move || {
+        let mut moved = Vec::new();
+        let mut failures = Vec::new();
+        for file in files {
+            match move_into(&folder, &file, root.as_deref()) {
+                Ok(()) => moved.push(file),
+                Err(err) => failures.push(format!("{}: {}", file.display(), err)),
+            }
+        }
+        let _ = sink.submit_command(FILES_MOVED, (folder, moved, failures));
+    });
+}
+
+/// Writes a session report for record-keeping to a file the user picks: the machine, the
+/// current search options, the "Search here" trail, the activity log and the visible results.
+/// A `.json` file name gets JSON; anything else gets Markdown.
+fn save_report(_ctx: &mut EventCtx, data: &mut AppState) {
+    let Some(file) = rfd::FileDialog::new()
+        .set_file_name("session-report.md")
+        .add_filter("Markdown", &["md"])
+        .add_filter("JSON", &["json"])
+        .save_file()
+    else {
+        return;
+    };
+    let json = file.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let report = session_report(data, &hostname(), SystemTime::now(), json);
+    match fs::write(&file, report) {
+        Ok(()) => data.notice = format!("Saved the session report to {}.", file.display()),
+        Err(err) => data.error_message = format!("Couldn't write {}: {}", file.display(), err),
+    }
+}
+
+fn session_report(data: &AppState, host: &str, now: SystemTime, json: bool) -> String {
+    let results: Vec<String> = data
+        .visible_results
+        .iter()
+        .filter(|r| !r.is_removed())
+        .map(ResultEntry::display)
+        .collect();
+    // Log lines are "<timestamp>  <event>".
+    let activity = data.activity.iter().map(|line| line.split_once("  ").unwrap_or(("", line)));
+    if json {
+        let report = serde_json::json!({
+            "hostname": host,
+            "generated": format_timestamp(now),
+            "options": data.session(),
+            "search_trail": data
+                .drill_stack
+                .iter()
+                .map(|step| serde_json::json!({ "root": step.root, "term": step.term }))
+                .collect::<Vec<_>>(),
+            "activity": activity
+                .map(|(time, event)| serde_json::json!({ "time": time, "event": event }))
+                .collect::<Vec<_>>(),
+            "results": results,
+        });
+        return serde_json::to_string_pretty(&report).unwrap_or_default();
+    }
+    let mut report = format!(
+        "# Session report\n\n- Host: {}\n- Generated: {}\n\n\
+         ## Search options\n\n```json\n{}\n```\n",
+        host,
+        format_timestamp(now),
+        serde_json::to_string_pretty(&data.session()).unwrap_or_default()
+    );
+    if !data.drill_stack.is_empty() {
+        report.push_str("\n## Search trail\n\n");
+        for step in data.drill_stack.iter() {
+            report.push_str(&format!("- {} \"{}\"\n", step.root, step.term));
+        }
+    }
+    report.push_str("\n## Activity\n\n");
+    for (time, event) in activity {
+        report.push_str(&format!("- {}: {}\n", time, event));
+    }
+    report.push_str(&format!("\n## Results ({})\n\n", results.len()));
+    for path in &results {
+        report.push_str(&format!("- `{}`\n", path));
+    }
+    report
+}
+
+/// This machine's name, for telling reports from different machines apart.
+fn hostname() -> String {
+    let from_env = std::env::var("HOSTNAME").or_else(|_| std::env::var("COMPUTERNAME")).ok();
+    from_env
+        .or_else(|| {
+            let output = std::process::Command::new("hostname").output().ok()?;
+            Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Opens the two marked files in the compare command.
+fn compare_marked(_ctx: &mut EventCtx, data: &mut AppState) {
+    let [a, b] = data.marked.as_slice() else {
+        data.error_message = "Mark exactly two files to compare them.".to_string();
+        return;
+    };
+    let template = if data.diff_command.trim().is_empty() {
+        config::default_diff_command()
+    } else {
+        data.diff_command.clone()
+    };
+    let values = [("{a}", a.as_os_str()), ("{b}", b.as_os_str())];
+    let (program, args) = match template::expand(&template, &values, &["{a}", "{b}"]) {
+        Ok(command) => command,
+        Err(err) => {
+            data.error_message = format!("Couldn't use the compare command: {}", err);
+            return;
+        }
+    };
+    // Diff tools exit with a failure status when the files differ, so only launching counts.
+    match std::process::Command::new(&program).args(&args).spawn() {
+        Ok(_) => data.log(format!("Compared {} with {}", a.display(), b.display())),
+        Err(err) => {
+            data.error_message = format!("Couldn't run `{}`: {}", program.to_string_lossy(), err)
+        }
+    }
+}
+
+/// Opens the rename panel for the marked entries.
+fn rename_marked(_ctx: &mut EventCtx, data: &mut AppState) {
+    if data.marked.is_empty() {
+        data.error_message = "Mark the entries to rename first.".to_string();
+        return;
+    }
+    data.pending_rename = Some(PendingRename {
+        files: data.marked.clone(),
+        find: String::new(),
+        replace: String::new(),
+        number: false,
+    });
+}
+
+/// Lists the visible results for review before moving them all to the trash. With "Stale
+/// files only" that is the files alone: a folder is judged stale by its own modification time,
+/// which says nothing about the files inside it.
+fn trash_all_results(_ctx: &mut EventCtx, data: &mut AppState) {
+    let files: Vec<ResultEntry> = data
+        .visible_results
+        .iter()
+        .filter(|r| !r.is_removed() && (!data.stale_only || !r.is_dir))
+        .cloned()
+        .collect();
+    if !files.is_empty() {
+        data.pending_batch = Some(PendingBatch {
+            permanent: false,
+            destination: None,
+            structure_root: None,
+            files: Arc::new(files),
+            group: None,
+        });
+    }
+}
+
+/// Looks for identical files among the visible results.
+fn find_duplicates(ctx: &mut EventCtx, data: &mut AppState) {
+    if data.duplicate_progress.is_some() {
+        return;
+    }
+    let paths: Vec<PathBuf> = data
+        .visible_results
+        .iter()
+        .filter(|r| !r.is_removed())
+        .map(|r| r.path())
+        .collect();
+    data.duplicate_progress = Some((0, 0));
+    spawn_find_duplicates(ctx.window_sink(), paths, data.update_interval());
+}
+
+/// Puts the settings back to their defaults once the user confirms.
+fn confirm_reset_settings(_ctx: &mut EventCtx, data: &mut AppState) {
+    let question = "Reset the theme, filters and all other settings to their defaults?\n\n\
+        Your current folder, presets and bookmarks are kept.";
+    if confirm("Reset settings", question) {
+        data.reset_settings();
+    }
+}
+
+/// Forgets what the app has remembered about past use once the user confirms: the recently
+/// opened files, the "Search here" trail, the options and watch terms remembered per folder,
+/// the walk sizes behind the coverage estimate and the results log. `PersistConfig` then drops
+/// them from the config file; other settings are untouched.
+fn confirm_clear_history(_ctx: &mut EventCtx, data: &mut AppState) {
+    let question = "Forget the recently opened files, the folders searched with Search here, the \
+        options and watch terms remembered for folders, and how large past searches were, and \
+        delete the results log?\n\n\
+        Settings, presets and bookmarks are kept, and so are the current folder and search, \
+        which are saved as usual if the app reopens with them.";
+    if !confirm("Clear history", question) {
+        return;
+    }
+    data.recent_files = Arc::new(Vec::new());
+    data.drill_stack = Arc::new(Vec::new());
+    data.folder_settings = Arc::new(Vec::new());
+    data.watch_terms = Arc::new(BTreeMap::new());
+    data.walk_sizes = Arc::new(HashMap::new());
+    data.dirs_estimate = None;
+    // With --no-persist the app doesn't touch its files, the log included.
+    if let Some(path) = config::results_log_path().filter(|_| !data.no_persist) {
+        for path in [path.with_extension("log.1"), path] {
+            match fs::remove_file(&path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    data.error_message = format!("Couldn't delete {}: {}", path.display(), err);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Shows the config file in the file manager for editing by hand, writing it out first if
+/// nothing has been saved yet. Edits take effect on the next start.
+fn show_config_file(ctx: &mut EventCtx, data: &mut AppState) {
+    if data.no_persist {
+        data.notice = "Started with --no-persist, so no config file is used.".to_string();
+        return;
+    }
+    // The sandbox is set in the config file, so it mustn't be editable from inside it.
+    if data.sandbox_root.is_some() {
+        data.error_message =
+            "The config file can't be opened while searches are limited to a folder.".to_string();
+        return;
+    }
+    let Some(path) = config::config_path() else {
+        data.error_message = "There is no config folder on this system.".to_string();
+        return;
+    };
+    if !path.exists() {
+        if let Err(err) = data.config().save() {
+            data.error_message = format!("Couldn't write {}: {}", path.display(), err);
+            return;
+        }
+    }
+    data.notice = format!(
+        "The config file is {}. Quit the app before editing it, or changes here may overwrite yours.",
+        path.display()
+    );
+    spawn_reveal(ctx.window_sink(), path);
+}
+
+/// Everything the command palette offers, in the order shown for an empty query.
+fn palette_actions() -> Vec<PaletteAction> {
+    let action = |name, run| PaletteAction { name, run };
+    vec![
+        action("Search", search),
+        action("Cancel search", |_ctx, data| data.cancel_search()),
+        action("Clear results", |_ctx, data| {
+            data.cancel_search();
+            data.search_outcome = SearchOutcome::NotRun;
+            data.set_results(Arc::new(Vec::new()));
+        }),
+        action("Choose directory", choose_directory),
+        action("Browse folder", browse_root),
+        action("Import roots", import_roots),
+        action("Bookmark folder", |ctx, data| {
+            ctx.submit_command(ADD_BOOKMARK.with(PathBuf::from(&data.root_path)));
+        }),
+        action("Open all results", open_all),
+        action("Copy results as args", copy_as_args),
+        action("Copy results as table", copy_as_table),
+        action("Save results in parts", save_segments),
+        action("Link results into folder", link_into_folder),
+        action("Copy results into folder", copy_into_folder),
+        action("Find duplicates", find_duplicates),
+        action("Search within results", search_within_results),
+        action("Undo refine", undo_refine),
+        action("Find stale files", |ctx, data| {
+            data.stale_only = true;
+            data.set_sort_key(SortKey::Oldest);
+            search(ctx, data);
+        }),
+        action("Compare marked files", compare_marked),
+        action("Rename marked files", rename_marked),
+        action("Save session report", save_report),
+        action("Toggle content search", |_ctx, data| data.search_contents = !data.search_contents),
+        action("Toggle full path matching", |_ctx, data| {
+            data.match_full_path = !data.match_full_path
+        }),
+        action("Toggle case sensitivity", |_ctx, data| data.case_sensitive = !data.case_sensitive),
+        action("Toggle all words", |_ctx, data| data.all_words = !data.all_words),
+        action("Toggle watch mode", |_ctx, data| data.watch = !data.watch),
+        action("Next theme", |_ctx, data| {
+            let presets = theme::presets();
+            let current = presets.iter().position(|(_, preset)| *preset == data.theme);
+            let next = current.map_or(0, |i| (i + 1) % presets.len());
+            data.theme = presets[next].1.clone();
+        }),
+        action("Show or hide settings", |_ctx, data| data.show_settings = !data.show_settings),
+        action("Reset settings to defaults", confirm_reset_settings),
+        action("Clear history", confirm_clear_history),
+        action("Show config file", show_config_file),
+    ]
+}
+
+/// A toolbar button that can also be reached with Tab and pressed with Enter or Space.
+fn toolbar_button(label: &str, action: fn(&mut EventCtx, &mut AppState)) -> impl Widget<AppState> {
+    Button::new(label)
+        .on_click(move |ctx, data: &mut AppState, _env| action(ctx, data))
+        .padding(8.0)
+        .background(keyboard::focus_ring_background())
+        .controller(KeyboardActivate::new(action))
+}
+
+/// A label and progress bar for a background task, hidden while no task is running.
+/// `describe` turns (done, total) into the label text.
+fn build_progress_row(describe: fn(usize, usize) -> String) -> impl Widget<Option<(usize, usize)>> {
+    Either::new(
+        |progress: &Option<(usize, usize)>, _env| progress.is_some(),
+        Flex::row()
+            .with_child(
+                Label::new(move |progress: &Option<(usize, usize)>, _env: &_| {
+                    let (done, total) = progress.unwrap_or_default();
+                    describe(done, total)
+                })
+                .padding(8.0),
+            )
+            .with_flex_child(
+                ProgressBar::new()
+                    .lens(lens::Map::new(
+                        |progress: &Option<(usize, usize)>| match *progress {
+                            Some((done, total)) if total > 0 => done as f64 / total as f64,
+                            _ => 0.0,
+                        },
+                        |_progress: &mut Option<(usize, usize)>, _fraction: f64| {},
+                    ))
+                    .expand_width(),
+                1.0,
+            ),
+        SizedBox::empty(),
+    )
+}
+
+/// Clusters of identical files, each with a helper to keep one copy.
+fn build_duplicates_view() -> impl Widget<AppState> {
+    let header = Flex::row()
+        .with_flex_child(
+            Label::new(|data: &AppState, _env: &_| match data.duplicate_groups.len() {
+                0 => "No duplicate files found".to_string(),
+                n => format!("{} groups of identical files", n),
+            })
+            .padding(8.0)
+            .expand_width(),
+            1.0,
+        )
+        .with_child(
+            Button::new("Back to results")
+                .on_click(|_ctx, data: &mut AppState, _env| data.show_duplicates = false),
+        );
+
+    let groups = List::new(|| {
+        let files = List::new(|| {
+            Label::new(|item: &ResultEntry, _env: &_| item.display())
+                .with_text_color(theme::TEXT)
+                .padding(4.0)
+                .on_click(|ctx, item: &mut ResultEntry, _env| {
+                    ctx.submit_command(OPEN_PATH.with(item.path()));
+                })
+        })
+        .lens(DuplicateGroup::files);
+
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(
+                Flex::row()
+                    .with_flex_child(
+                        Label::new(|group: &DuplicateGroup, _env: &_| {
+                            let count = group.files.len();
+                            format!("{} identical files, {} bytes each", count, group.size)
+                        })
+                        .expand_width(),
+                        1.0,
+                    )
+                    .with_child(Button::new("Keep first, trash the rest").on_click(
+                        |ctx, group: &mut DuplicateGroup, _env| {
+                            ctx.submit_command(TRASH_DUPLICATES.with(group.files.clone()));
+                        },
+                    )),
+            )
+            .with_child(files)
+            .padding(8.0)
+            .background(theme::SURFACE)
+            .border(theme::ACCENT, 1.0)
+            .rounded(4.0)
+    })
+    .with_spacing(4.0)
+    .lens(AppState::duplicate_groups);
+
+    Flex::column()
+        .with_child(header)
+        .with_flex_child(Scroll::new(groups).vertical().expand(), 1.0)
+}
+
+/// The results grouped by file name, e.g. "config.toml (7 locations)". Clicking a header shows
+/// or hides its paths, and clicking a path opens it.
+fn build_name_groups_view() -> impl Widget<AppState> {
+    let groups = List::new(|| {
+        let files = Either::new(
+            |group: &NameGroup, _env| group.expanded,
+            List::new(|| {
+                Label::new(|item: &ResultEntry, _env: &_| item.display())
+                    .with_text_color(theme::TEXT)
+                    .padding((24.0, 4.0, 4.0, 4.0))
+                    .on_click(|ctx, item: &mut ResultEntry, _env| {
+                        ctx.submit_command(OPEN_PATH.with(item.path()));
+                    })
+            })
+            .lens(NameGroup::files),
+            SizedBox::empty(),
+        );
+
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(
+                Label::new(|group: &NameGroup, _env: &_| {
+                    let marker = if group.expanded { "▾" } else { "▸" };
+                    match group.files.len() {
+                        1 => format!("{} {} (1 location)", marker, group.name),
+                        n => format!("{} {} ({} locations)", marker, group.name, n),
+                    }
+                })
+                .with_text_color(theme::TEXT)
+                .expand_width()
+                .on_click(|_ctx, group: &mut NameGroup, _env| group.expanded = !group.expanded),
+            )
+            .with_child(files)
+            .padding(8.0)
+            .background(theme::SURFACE)
+            .border(theme::ACCENT, 1.0)
+            .rounded(4.0)
+    })
+    .with_spacing(4.0)
+    .lens(AppState::name_groups);
+
+    Scroll::new(groups).vertical().expand().background(theme::BACKGROUND)
+}
+
+/// Asks for the name of the new file or folder; nothing is created until Create.
+fn build_create_panel() -> impl Widget<AppState> {
+    let fields = Maybe::new(
+        || {
+            Flex::row()
+                .with_child(
+                    Label::new(|pending: &PendingCreate, _env: &_| {
+                        format!("New in {}:", pending.dir.display())
+                    })
+                    .padding(4.0),
+                )
+                .with_child(
+                    TextBox::new()
+                        .with_placeholder("Name")
+                        .fix_width(240.0)
+                        .lens(PendingCreate::name),
+                )
+                .with_child(Checkbox::new("Folder").padding(4.0).lens(PendingCreate::folder))
+        },
+        SizedBox::empty,
+    )
+    .lens(AppState::pending_create);
+
+    let buttons = Flex::row()
+        .with_child(Button::new("Create").on_click(|ctx, data: &mut AppState, _env| {
+            if let Some(pending) = data.pending_create.take() {
+                create_item(ctx.window_sink(), data, pending);
+            }
+        }))
+        .with_spacer(8.0)
+        .with_child(
+            Button::new("Cancel")
+                .on_click(|_ctx, data: &mut AppState, _env| data.pending_create = None),
+        );
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(fields)
+        .with_child(buttons)
+        .padding(8.0)
+        .background(theme::SURFACE)
+        .border(theme::ACCENT, 1.0)
+        .rounded(4.0)
+}
+
+/// Creates the empty file or folder named in `pending`, then lists its folder with it
+/// highlighted. A bad name or one that is already taken leaves the panel open to fix it.
+fn create_item(sink: WindowSink, data: &mut AppState, pending: PendingCreate) {
+    let name = pending.name.trim();
+    let invalid = name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.chars().any(std::path::is_separator);
+    if invalid {
+        data.error_message = format!("\"{}\" isn't a valid name", name);
+        data.pending_create = Some(pending);
+        return;
+    }
+    let path = pending.dir.join(name);
+    let created = if pending.folder {
+        fs::create_dir(&path)
+    } else {
+        // Never truncate a file that is already there.
+        fs::OpenOptions::new().write(true).create_new(true).open(&path).map(|_| ())
+    };
+    match created {
+        Ok(()) => {
+            data.log(format!("Created {}", path.display()));
+            let dir = pending.dir.clone();
+            browse_to(sink, data, &dir, path);
+        }
+        Err(err) => {
+            data.error_message = if err.kind() == std::io::ErrorKind::AlreadyExists {
+                format!("{} already exists", path.display())
+            } else {
+                format!("Couldn't create {}: {}", path.display(), err)
+            };
+            data.pending_create = Some(pending);
+        }
+    }
+}
+
+/// Asks how to rename the marked entries and previews the new names; nothing is renamed until
+/// Rename.
+fn build_rename_panel() -> impl Widget<AppState> {
+    let fields = Maybe::new(
+        || {
+            let options = Flex::row()
+                .with_child(Label::new("Replace").padding(4.0))
+                .with_child(
+                    TextBox::new()
+                        .with_placeholder("Text")
+                        .fix_width(160.0)
+                        .lens(PendingRename::find),
+                )
+                .with_child(Label::new("with").padding(4.0))
+                .with_child(TextBox::new().fix_width(160.0).lens(PendingRename::replace))
+                .with_child(Checkbox::new("Add a number").padding(4.0).lens(PendingRename::number));
+            let preview = Label::new(|pending: &PendingRename, _env: &_| pending.preview())
+                .with_text_color(theme::TEXT)
+                .padding(4.0);
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(options)
+                .with_child(Scroll::new(preview).vertical().fix_height(160.0).expand_width())
+        },
+        SizedBox::empty,
+    )
+    .lens(AppState::pending_rename);
+
+    let buttons = Flex::row()
+        .with_child(Button::new("Rename").on_click(|ctx, data: &mut AppState, _env| {
+            if let Some(pending) = data.pending_rename.take() {
+                rename_entries(ctx, data, pending);
+            }
+        }))
+        .with_spacer(8.0)
+        .with_child(
+            Button::new("Cancel")
+                .on_click(|_ctx, data: &mut AppState, _env| data.pending_rename = None),
+        );
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(fields)
+        .with_child(buttons)
+        .padding(8.0)
+        .background(theme::SURFACE)
+        .border(theme::ACCENT, 1.0)
+        .rounded(4.0)
+}
+
+/// Renames each entry as `pending` plans, after checking the whole plan. If a rename fails
+/// part way, or a new name was taken in the meantime, the ones already done are undone so the
+/// entries aren't left half renamed. A plan that can't run leaves the panel open to fix it.
+fn rename_entries(ctx: &mut EventCtx, data: &mut AppState, pending: PendingRename) {
+    let plan: Vec<(PathBuf, PathBuf)> = match pending.plan() {
+        Ok(plan) => plan.into_iter().filter(|(source, target)| source != target).collect(),
+        Err(problem) => {
+            data.error_message = format!("Couldn't rename: {}", problem);
+            data.pending_rename = Some(pending);
+            return;
+        }
+    };
+    if let Some(problem) = rename::problem(&plan) {
+        data.error_message = format!("Couldn't rename: {}", problem);
+        data.pending_rename = Some(pending);
+        return;
+    }
+    let mut done: Vec<&(PathBuf, PathBuf)> = Vec::new();
+    for step in &plan {
+        let (source, target) = step;
+        // `fs::rename` replaces what's there on Unix, so check again right before.
+        let renamed = if rename::is_taken(source, target) {
+            Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists))
+        } else {
+            fs::rename(source, target)
+        };
+        if let Err(err) = renamed {
+            let undone = done
+                .iter()
+                .rev()
+                .filter(|(source, target)| fs::rename(target, source).is_ok())
+                .count();
+            data.error_message = format!(
+                "Couldn't rename {}: {}. Undid {} of the {} renames before it.",
+                source.display(),
+                err,
+                undone,
+                done.len()
+            );
+            data.pending_rename = Some(pending);
+            return;
+        }
+        done.push(step);
+    }
+    for (source, target) in &plan {
+        data.log(format!("Renamed {} to {}", source.display(), target.display()));
+    }
+    data.notice = format!("Renamed {} entries.", plan.len());
+    data.marked = Arc::new(Vec::new());
+    ctx.submit_command(REFRESH_SEARCH);
+}
+
+/// Lists every path a pending batch will affect; nothing happens until Confirm.
+fn build_batch_panel() -> impl Widget<AppState> {
+    let details = Maybe::new(
+        || {
+            let files = List::new(|| {
+                Label::new(|item: &ResultEntry, _env: &_| item.display())
+                    .with_text_color(theme::TEXT)
+                    .padding(2.0)
+            })
+            .lens(PendingBatch::files);
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(Label::new(|batch: &PendingBatch, _env: &_| batch.summary()).padding(4.0))
+                .with_child(Scroll::new(files).vertical().fix_height(160.0).expand_width())
+        },
+        SizedBox::empty,
+    )
+    .lens(AppState::pending_batch);
+
+    let buttons = Flex::row()
+        .with_child(Button::new("Confirm").on_click(|ctx, data: &mut AppState, _env| {
+            if let Some(batch) = data.pending_batch.take() {
+                run_batch(ctx.window_sink(), data, &batch);
+            }
+        }))
+        .with_spacer(8.0)
+        .with_child(
+            Button::new("Cancel")
+                .on_click(|_ctx, data: &mut AppState, _env| data.pending_batch = None),
+        );
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(details)
+        .with_child(buttons)
+        .padding(8.0)
+        .background(theme::SURFACE)
+        .border(Color::rgb8(0xff, 0x6b, 0x6b), 1.0)
+        .rounded(4.0)
+}
+
+/// Settings that aren't needed for every search, shown on demand.
+fn build_settings_panel() -> impl Widget<AppState> {
+    let mut presets = Flex::row().with_child(Label::new("Theme:").padding(4.0));
+    for (name, preset) in theme::presets() {
+        presets.add_child(
+            Button::new(name)
+                .on_click(move |_ctx, data: &mut AppState, _env| data.theme = preset.clone())
+                .padding(4.0),
+        );
+    }
+
+    let accent_box = Flex::row()
+        .with_child(Label::new("Accent:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("#rrggbb")
+                .lens(AppState::theme.then(ThemeSettings::accent)),
+        );
+
+    let command_box = Flex::row()
+        .with_child(Label::new("Run command:").padding(4.0))
+        .with_flex_child(
+            TextBox::new()
+                .with_placeholder("e.g. code {}  ({} is replaced with the path)")
+                .expand_width()
+                .lens(AppState::command_template),
+            1.0,
+        );
+
+    let open_command_box = Flex::row()
+        .with_child(Label::new("Open command:").padding(4.0))
+        .with_flex_child(
+            TextBox::new()
+                .with_placeholder(config::default_open_command())
+                .expand_width()
+                .lens(AppState::open_command),
+            1.0,
+        );
+
+    let diff_command_box = Flex::row()
+        .with_child(Label::new("Compare command:").padding(4.0))
+        .with_flex_child(
+            TextBox::new()
+                .with_placeholder(config::default_diff_command())
+                .expand_width()
+                .lens(AppState::diff_command),
+            1.0,
+        );
+
+    let editor_command_box = Flex::row()
+        .with_child(Label::new("Editor command:").padding(4.0))
+        .with_flex_child(
+            TextBox::new()
+                .with_placeholder("e.g. code -g {path}:{line}  (opens content matches at their line)")
+                .expand_width()
+                .lens(AppState::editor_command),
+            1.0,
+        );
+
+    let threads_box = Flex::row()
+        .with_child(Label::new("Content search threads:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_formatter(ParseFormatter::new())
+                .update_data_while_editing(true)
+                .fix_width(60.0)
+                .lens(AppState::thread_count),
+        );
+
+    let exclude_paths_box = Flex::row()
+        .with_child(Label::new("Never search in:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("Search system folders too")
+                .fix_width(360.0)
+                .lens(AppState::exclude_paths),
+        );
+
+    let content_max_bytes_box = Flex::row()
+        .with_child(Label::new("Content search reads at most:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_formatter(ParseFormatter::new())
+                .update_data_while_editing(true)
+                .fix_width(100.0)
+                .lens(AppState::content_max_bytes),
+        )
+        .with_child(Label::new("bytes per file (0 = no limit)").padding(4.0));
+
+    let open_all_threshold_box = Flex::row()
+        .with_child(Label::new("Ask before opening more than:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_formatter(ParseFormatter::new())
+                .update_data_while_editing(true)
+                .fix_width(60.0)
+                .lens(AppState::open_all_warn_threshold),
+        )
+        .with_child(Label::new("results").padding(4.0));
+
+    let segment_size_box = Flex::row()
+        .with_child(Label::new("Save in parts with:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_formatter(ParseFormatter::new())
+                .update_data_while_editing(true)
+                .fix_width(60.0)
+                .lens(AppState::segment_size),
+        )
+        .with_child(Label::new("paths per file").padding(4.0));
+
+    let updates_per_second_box = Flex::row()
+        .with_child(Label::new("Update progress at most:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_formatter(ParseFormatter::new())
+                .update_data_while_editing(true)
+                .fix_width(60.0)
+                .lens(AppState::updates_per_second),
+        )
+        .with_child(Label::new("times a second").padding(4.0));
+
+    let results_log_row = Flex::row()
+        .with_child(
+            Checkbox::new("While watching, log the results every")
+                .padding(4.0)
+                .lens(AppState::results_log),
+        )
+        .with_child(
+            TextBox::new()
+                .with_formatter(ParseFormatter::new())
+                .update_data_while_editing(true)
+                .fix_width(60.0)
+                .lens(AppState::results_log_minutes),
+        )
+        .with_child(Label::new("minutes to results.log").padding(4.0));
+
+    let stale_days_box = Flex::row()
+        .with_child(Label::new("Stale files are untouched for:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_formatter(ParseFormatter::new())
+                .update_data_while_editing(true)
+                .fix_width(60.0)
+                .lens(AppState::stale_days),
+        )
+        .with_child(Label::new("days").padding(4.0));
+
+    let read_retries_box = Flex::row()
+        .with_child(Label::new("Retry folders that fail to read:").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_formatter(ParseFormatter::new())
+                .update_data_while_editing(true)
+                .fix_width(60.0)
+                .lens(AppState::read_retries),
+        )
+        .with_child(Label::new("times (for network drives)").padding(4.0));
+
+    let trim_terms_box = Checkbox::new("Ignore spaces around search terms")
+        .padding(4.0)
+        .lens(AppState::trim_terms);
+
+    let rerun_on_drill_down_box = Checkbox::new("Re-run the search after Search here")
+        .padding(4.0)
+        .lens(AppState::rerun_on_drill_down);
+
+    let auto_widen_box = Checkbox::new(format!(
+        "If nothing is found, search the parent folder instead (up to {} levels)",
+        AUTO_WIDEN_LEVELS
+    ))
+    .padding(4.0)
+    .lens(AppState::auto_widen);
+
+    let monospace_results_box = Checkbox::new("Show results in a monospaced font")
+        .padding(4.0)
+        .lens(AppState::monospace_results);
+
+    let escape_controls_box = Checkbox::new("Show control characters in names as escapes, e.g. \\n")
+        .padding(4.0)
+        .lens(AppState::escape_controls);
+
+    let display_components_box = Flex::row()
+        .with_child(Label::new("Show the last").padding(4.0))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("0 = all")
+                .with_formatter(ParseFormatter::new())
+                .update_data_while_editing(true)
+                .fix_width(60.0)
+                .lens(AppState::display_components),
+        )
+        .with_child(Label::new("parts of each path").padding(4.0));
+
+    let dirs_first_row = Flex::row()
+        .with_child(Label::new("Folders in the results:").padding(4.0))
+        .with_child(
+            RadioGroup::row(vec![
+                ("At the top", FolderPlacement::Top),
+                ("At the bottom", FolderPlacement::Bottom),
+                ("Mixed with files", FolderPlacement::Mixed),
+            ])
+            .lens(lens::Map::new(
+                |data: &AppState| data.dirs_first,
+                |data: &mut AppState, placement: FolderPlacement| {
+                    if placement != data.dirs_first {
+                        data.dirs_first = placement;
+                        data.update_visible_results();
+                    }
+                },
+            )),
+        )
+        .padding(4.0);
+
+    let click_action_row = Flex::row()
+        .with_child(Label::new("Clicking a result:").padding(4.0))
+        .with_child(
+            RadioGroup::row(vec![
+                ("Opens it", RowClickAction::Open),
+                ("Selects it", RowClickAction::Select),
+                ("Shows it in its folder", RowClickAction::Reveal),
+            ])
+            .lens(AppState::click_action),
+        )
+        .padding(4.0);
+
+    let listing_sizes_box = Checkbox::new("Include file sizes in Copy listing")
+        .padding(4.0)
+        .lens(AppState::listing_sizes);
+
+    let canonicalize_root_box = Checkbox::new("Resolve .. and symlinks in the folder path")
+        .padding(4.0)
+        .lens(AppState::canonicalize_root);
+
+    let keep_results_box = Checkbox::new("Keep the results listed when changing folders")
+        .padding(4.0)
+        .lens(AppState::keep_results_on_root_change);
+
+    let confirm_root_change_box =
+        Checkbox::new("Ask before changing the folder when a loaded preset has unsaved changes")
+            .padding(4.0)
+            .lens(AppState::confirm_root_change);
+
+    let restore_session_box = Checkbox::new("Reopen with the last folder, search and view options")
+        .padding(4.0)
+        .lens(AppState::restore_session);
+
+    let reset_btn = toolbar_button("Reset to defaults", confirm_reset_settings);
+    let clear_history_btn = toolbar_button("Clear history", confirm_clear_history);
+
+    let config_file_btn = toolbar_button("Show config file", show_config_file);
+
+    let no_persist_line = Either::new(
+        |data: &AppState, _env| data.no_persist,
+        Label::new("Started with --no-persist: changes here are forgotten on quitting.")
+            .with_text_color(Color::grey(0.7))
+            .padding(4.0),
+        SizedBox::empty(),
+    );
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(no_persist_line)
+        .with_child(presets)
+        .with_child(accent_box)
+        .with_child(monospace_results_box)
+        .with_child(escape_controls_box)
+        .with_child(display_components_box)
+        .with_child(
+            Checkbox::new("Show the active filters above the results")
+                .padding(4.0)
+                .lens(AppState::show_filter_chips),
+        )
+        .with_child(click_action_row)
+        .with_child(dirs_first_row)
+        .with_child(command_box)
+        .with_child(open_command_box)
+        .with_child(editor_command_box)
+        .with_child(diff_command_box)
+        .with_child(threads_box)
+        .with_child(exclude_paths_box)
+        .with_child(content_max_bytes_box)
+        .with_child(open_all_threshold_box)
+        .with_child(segment_size_box)
+        .with_child(updates_per_second_box)
+        .with_child(stale_days_box)
+        .with_child(read_retries_box)
+        .with_child(trim_terms_box)
+        .with_child(rerun_on_drill_down_box)
+        .with_child(auto_widen_box)
+        .with_child(listing_sizes_box)
+        .with_child(canonicalize_root_box)
+        .with_child(keep_results_box)
+        .with_child(confirm_root_change_box)
+        .with_child(
+            Checkbox::new("Warn before searching a whole disk")
+                .padding(4.0)
+                .lens(AppState::warn_whole_disk),
+        )
+        .with_child(
+            Checkbox::new("Remember each folder's search while watching")
+                .padding(4.0)
+                .lens(AppState::remember_watch_terms),
+        )
+        .with_child(restore_session_box)
+        .with_child(
+            Checkbox::new("List the folder's contents on start")
+                .padding(4.0)
+                .lens(AppState::list_on_start),
+        )
+        .with_child(results_log_row)
+        .with_child(
+            Checkbox::new("Select the first result when a search finishes")
+                .padding(4.0)
+                .lens(AppState::auto_select_first),
+        )
+        .with_child(
+            Checkbox::new("Return to the search box when a search finishes")
+                .padding(4.0)
+                .lens(AppState::refocus_search_box),
+        )
+        .with_child(
+            Flex::row()
+                .with_child(reset_btn)
+                .with_child(clear_history_btn)
+                .with_child(config_file_btn),
+        )
+        .padding(8.0)
+        .background(theme::SURFACE)
+        .rounded(4.0)
+}
+
+/// Writes the config file whenever a persisted setting changes, unless started with
+/// `--no-persist`.
+struct PersistConfig;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for PersistConfig {
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        let config = data.config();
+        if !data.no_persist && config != old_data.config() {
+            if let Err(err) = config.save() {
+                eprintln!("failed to save config: {}", err);
+            }
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/// Keeps a filesystem watcher on the folders the last search ran on while watch mode is on,
+/// covering the folders below them too if that search did. Setting up a watch walks the whole
+/// tree on some platforms, so it is done on its own thread and arrives as `WATCHER_READY`.
+#[derive(Default)]
+struct WatchRoot {
+    // The folders and recursion being watched, or being set up to be.
+    watched: Option<(Arc<Vec<PathBuf>>, bool)>,
+    watcher: Option<DirWatcher>,
+    // Bumped on every change, so a setup that finishes after a newer one is dropped.
+    generation: u64,
+}
+
+impl WatchRoot {
+    fn sync(&mut self, ctx: &mut UpdateCtx, data: &AppState) {
+        let wanted = Some((data.searched_roots.clone(), data.searched_recursively))
+            .filter(|(roots, _)| data.watch && !roots.is_empty());
+        if wanted == self.watched {
+            return;
+        }
+        // Stop watching the old folders before anything else.
+        self.watched = wanted.clone();
+        self.watcher = None;
+        self.generation += 1;
+        let Some((roots, recursive)) = wanted else {
+            return;
+        };
+        let generation = self.generation;
+        let sink = ctx.window_sink();
+        thread::spawn(move || {
+            let on_change = {
+                let sink = sink.clone();
+                move || {
+                    let _ = sink.submit_command(REFRESH_SEARCH, ());
+                }
+            };
+            let watcher = DirWatcher::new(&roots, recursive, on_change)
+                .map_err(|err| format!("Couldn't watch the folders searched: {}", err));
+            let _ = sink.submit_command(WATCHER_READY, SingleUse::new((generation, watcher)));
+        });
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for WatchRoot {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        if let Event::Command(cmd) = event {
+            if let Some(ready) = cmd.get(WATCHER_READY) {
+                match ready.take() {
+                    Some((generation, Ok(watcher))) if generation == self.generation => {
+                        self.watcher = Some(watcher)
+                    }
+                    Some((generation, Err(message))) if generation == self.generation => {
+                        data.error_message = message
+                    }
+                    _ => {}
+                }
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if data.watch != old_data.watch
+            || !data.searched_roots.same(&old_data.searched_roots)
+            || data.searched_recursively != old_data.searched_recursively
+        {
+            self.sync(ctx, data);
+        }
+        if data.resume_watch_search && !old_data.resume_watch_search {
+            ctx.submit_command(RUN_SEARCH);
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/// Counts the entries of directory results in the background while item counts are shown,
+/// skipping directories already counted.
+struct CountDirItems;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for CountDirItems {
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        let needed = data.show_item_counts
+            && (!old_data.show_item_counts || !old_data.search_results.same(&data.search_results));
+        if needed {
+            let dirs: Vec<PathBuf> = data
+                .search_results
+                .iter()
+                .filter(|r| r.is_dir && !data.dir_item_counts.contains_key(&r.path()))
+                .map(|r| r.path())
+                .collect();
+            if !dirs.is_empty() {
+                spawn_count_dir_items(ctx.window_sink(), dirs);
+            }
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/// Looks up the git status of file results in the background while "Git status" is on,
+/// skipping files already looked up.
+struct LookUpGitStatus;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for LookUpGitStatus {
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        let needed = data.show_git_status
+            && (!old_data.show_git_status || !old_data.search_results.same(&data.search_results));
+        if needed {
+            let files: Vec<PathBuf> = data
+                .search_results
+                .iter()
+                .filter(|r| !r.is_dir && !data.git_statuses.contains_key(&r.path()))
+                .map(|r| r.path())
+                .collect();
+            if !files.is_empty() {
+                let sink = ctx.window_sink();
+                // Tagged like a search, so statuses for results since replaced are dropped.
+                let generation = data.search_generation;
+                thread::spawn(move || {
+                    let statuses = git_status::statuses(&files);
+                    let _ = sink.submit_command(GIT_STATUSES, (generation, statuses));
+                });
+            }
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/// Reads the start of the highlighted result in the background while "Preview" is on.
+struct LoadPreview;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for LoadPreview {
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        let needed = data.show_preview
+            && !data.highlighted.as_os_str().is_empty()
+            && (!old_data.show_preview || old_data.highlighted != data.highlighted);
+        if needed {
+            let path = data.highlighted.clone();
+            let sink = ctx.window_sink();
+            thread::spawn(move || {
+                let (text, image) = file_preview(&path);
+                let _ = sink.submit_command(FILE_PREVIEW, (path, text, image));
+            });
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/// Hands a newly loaded preview picture to the image widget, which holds its own copy.
+struct ShowPreviewImage;
+
+impl Controller<AppState, Image> for ShowPreviewImage {
+    fn update(
+        &mut self,
+        child: &mut Image,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if !old_data.preview_image.same(&data.preview_image) {
+            if let Some(image) = &data.preview_image {
+                child.set_image_data(image.clone());
+                ctx.request_layout();
+            }
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/// Space previews the selected result with Quick Look, as in Finder, once the results have
+/// been clicked. Taking focus on that click keeps the space bar typing into text boxes
+/// otherwise.
+#[cfg(target_os = "macos")]
+struct QuickLook;
+
+#[cfg(target_os = "macos")]
+impl<W: Widget<AppState>> Controller<AppState, W> for QuickLook {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        match event {
+            Event::MouseDown(_) => ctx.request_focus(),
+            Event::KeyDown(key)
+                if ctx.is_focused()
+                    && HotKey::new(None, " ").matches(key)
+                    && !data.highlighted.as_os_str().is_empty() =>
+            {
+                let previewed = std::process::Command::new("qlmanage")
+                    .arg("-p")
+                    .arg(&data.highlighted)
+                    .stdout(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::null())
+                    .spawn();
+                if let Err(err) = previewed {
+                    data.error_message = format!("Couldn't preview: {}", err);
+                }
+                ctx.set_handled();
+                return;
+            }
+            _ => {}
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            ctx.register_for_focus();
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
+
+/// Window-wide shortcuts (Ctrl instead of Cmd off macOS): Cmd+R re-runs the current search,
+/// exactly like clicking Search, Cmd+O opens the directory picker like "Choose Directory",
+/// Cmd+Shift+. (Ctrl+H off macOS, where Cmd+H hides the app) flips "Show hidden files",
+/// re-running the search if there is one, and Cmd+Shift+P opens the command palette.
+struct Shortcuts;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for Shortcuts {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        if let Event::KeyDown(key) = event {
+            if HotKey::new(SysMods::Cmd, "r").matches(key) {
+                start_search(ctx.window_sink(), data);
+                ctx.set_handled();
+                return;
+            }
+            if HotKey::new(SysMods::Cmd, "o").matches(key) {
+                choose_directory(ctx, data);
+                ctx.set_handled();
+                return;
+            }
+            if hidden_files_keys().iter().any(|hotkey| hotkey.matches(key)) {
+                data.show_hidden = !data.show_hidden;
+                if data.has_searched() {
+                    start_search(ctx.window_sink(), data);
+                }
+                ctx.set_handled();
+                return;
+            }
+            // Shift usually turns the key into an uppercase "P", but not on every platform.
+            let palette_keys = [HotKey::new(SysMods::CmdShift, "P"), HotKey::new(SysMods::CmdShift, "p")];
+            if palette_keys.iter().any(|hotkey| hotkey.matches(key)) {
+                palette::open(ctx, data);
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+/// The shortcuts for "Show hidden files": Cmd+Shift+. like Finder's, which Shift may turn
+/// into ">", on macOS, and Ctrl+H elsewhere.
+#[cfg(target_os = "macos")]
+fn hidden_files_keys() -> [HotKey; 2] {
+    [HotKey::new(SysMods::CmdShift, "."), HotKey::new(SysMods::CmdShift, ">")]
+}
+
+#[cfg(not(target_os = "macos"))]
+fn hidden_files_keys() -> [HotKey; 1] {
+    [HotKey::new(SysMods::Cmd, "h")]
+}
+
+/// Enter in the search box runs the search, and `FOCUS_SEARCH_BOX` puts the cursor back in it.
+struct SearchBoxKeys;
+
+impl<W: Widget<String>> Controller<String, W> for SearchBoxKeys {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut String, env: &Env) {
+        match event {
+            Event::Command(cmd) if cmd.is(FOCUS_SEARCH_BOX) => ctx.request_focus(),
+            Event::KeyDown(key) if ctx.is_focused() && key.key == KbKey::Enter => {
+                ctx.submit_command(RUN_SEARCH);
+                ctx.set_handled();
+                return;
+            }
+            _ => {}
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+/// Greyed-out placeholder rows that pulse while a search is running and nothing has arrived yet.
+struct SkeletonRows {
+    // Seconds of animation so far, driving the pulse.
+    phase: f64,
+}
+
+const SKELETON_ROWS: usize = 6;
+const SKELETON_ROW_HEIGHT: f64 = 40.0;
+
+impl Widget<AppState> for SkeletonRows {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, _env: &Env) {
+        if let Event::AnimFrame(interval) = event {
+            self.phase += *interval as f64 / 1e9;
+            ctx.request_paint();
+            if data.searching {
+                ctx.request_anim_frame();
+            }
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, _env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            if data.searching {
+                ctx.request_anim_frame();
+            }
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &AppState, data: &AppState, _env: &Env) {
+        // Hidden widgets get no updates, so the stored old data can't tell when a search began.
+        if data.searching {
+            ctx.request_anim_frame();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &AppState,
+        _env: &Env,
+    ) -> Size {
+        let height = SKELETON_ROWS as f64 * (SKELETON_ROW_HEIGHT + 4.0);
+        bc.constrain(Size::new(bc.max().width, height))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &AppState, env: &Env) {
+        let width = ctx.size().width;
+        let surface = env.get(theme::SURFACE);
+        for row in 0..SKELETON_ROWS {
+            // Each row lags the one above, so the pulse ripples down the list.
+            let wave = (self.phase * 4.0 - row as f64 * 0.6).sin();
+            let y = row as f64 * (SKELETON_ROW_HEIGHT + 4.0);
+            let rect = druid::Rect::new(0.0, y, width, y + SKELETON_ROW_HEIGHT).to_rounded_rect(4.0);
+            ctx.fill(rect, &surface.with_alpha(0.55 + 0.25 * wave));
+            // A stand-in for the path text.
+            let bar = druid::Rect::new(12.0, y + 14.0, (width * 0.5).max(12.0), y + 26.0);
+            ctx.fill(bar.to_rounded_rect(3.0), &Color::grey(0.5).with_alpha(0.3 + 0.15 * wave));
+        }
+    }
+}
+
+/// Wraps a button and, while a search is running, runs a highlight around its edge.
+struct BusyRing<W> {
+    child: W,
+    // Seconds of animation so far; one lap of the ring takes a second.
+    phase: f64,
+}
+
+impl<W> BusyRing<W> {
+    fn new(child: W) -> Self {
+        BusyRing { child, phase: 0.0 }
+    }
+}
+
+impl<W: Widget<AppState>> Widget<AppState> for BusyRing<W> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        if let Event::AnimFrame(interval) = event {
+            self.phase = (self.phase + *interval as f64 / 1e9).fract();
+            ctx.request_paint();
+            if data.searching {
+                ctx.request_anim_frame();
+            }
+        }
+        self.child.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, env: &Env) {
+        self.child.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
+        if data.searching && !old_data.searching {
+            self.phase = 0.0;
+            ctx.request_anim_frame();
+        } else if old_data.searching && !data.searching {
+            ctx.request_paint();
+        }
+        self.child.update(ctx, old_data, data, env)
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &AppState, env: &Env) -> Size {
+        self.child.layout(ctx, bc, data, env)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AppState, env: &Env) {
+        self.child.paint(ctx, data, env);
+        if !data.searching {
+            return;
+        }
+        // Drawn in the padding around the button: a faint track with a bright quarter moving
+        // round it.
+        let rect = ctx.size().to_rect().inset(-3.0).to_rounded_rect(6.0);
+        let accent = env.get(theme::ACCENT);
+        ctx.stroke(rect, &accent.with_alpha(0.3), 2.0);
+        let perimeter = 2.0 * (rect.width() + rect.height());
+        let mut style = druid::piet::StrokeStyle::new();
+        style.set_dash_pattern(vec![perimeter / 4.0, perimeter * 3.0 / 4.0]);
+        style.set_dash_offset(-self.phase * perimeter);
+        ctx.stroke_styled(rect, &Color::rgb8(0x6c, 0xa6, 0xff), 2.0, &style);
+    }
+}
+
+/// Repaints while watch-mode change highlights fade and clears them once they expire.
+struct FadeChanges {
+    timer: Option<TimerToken>,
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for FadeChanges {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        if let Event::Timer(token) = event {
+            if Some(*token) == self.timer {
+                self.timer = None;
+                data.expire_changes();
+                if data.has_changes() {
+                    self.timer = Some(ctx.request_timer(CHANGE_FADE_TICK));
+                }
+                ctx.request_paint();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if self.timer.is_none() && data.has_changes() {
+            self.timer = Some(ctx.request_timer(CHANGE_FADE_TICK));
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/// Appends the results to the results log every `results_log_minutes` while watching, so an
+/// unattended session leaves a record even if the app dies. Nothing is written with
+/// `--no-persist`.
+struct LogResults {
+    timer: Option<TimerToken>,
+}
+
+impl LogResults {
+    fn interval(data: &AppState) -> Duration {
+        Duration::from_secs(data.results_log_minutes.max(1).saturating_mul(60))
+    }
+
+    fn enabled(data: &AppState) -> bool {
+        data.watch && data.results_log && !data.no_persist
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for LogResults {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        if let Event::Timer(token) = event {
+            if Some(*token) == self.timer {
+                self.timer = None;
+                if LogResults::enabled(data) {
+                    let results: Vec<ResultEntry> =
+                        data.visible_results.iter().filter(|r| !r.is_removed()).cloned().collect();
+                    spawn_log_results(ctx.window_sink(), data.root_path.clone(), results);
+                    self.timer = Some(ctx.request_timer(LogResults::interval(data)));
+                }
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        // A changed interval takes effect after the tick already waiting.
+        if self.timer.is_none() && LogResults::enabled(data) {
+            self.timer = Some(ctx.request_timer(LogResults::interval(data)));
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/// Appends the results to the results log on a background thread, first moving the log aside
+/// to `results.log.1` once it has grown past RESULTS_LOG_MAX_BYTES.
+fn spawn_log_results(sink: WindowSink, root: String, results: Vec<ResultEntry>) {
+    thread::spawn(move || {
+        let Some(path) = config::results_log_path() else {
+            return;
+        };
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > RESULTS_LOG_MAX_BYTES) {
+                fs::rename(&path, path.with_extension("log.1"))?;
+            }
+            let mut text = format!(
+                "== {}  {}  ({} results)\n",
+                format_timestamp(SystemTime::now()),
+                root,
+                results.len()
+            );
+            for entry in &results {
+                text.push_str(&table_row(entry));
+                text.push('\n');
+            }
+            let mut file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+            std::io::Write::write_all(&mut file, text.as_bytes())
+        };
+        if let Err(err) = write() {
+            let message = format!("Couldn't write the results log {}: {}", path.display(), err);
+            let _ = sink.submit_command(REPORT_ERROR, message);
+        }
+    });
+}
+
+/// Headers for the column layout, lined up with the rows' columns. Clicking one sorts by it;
+/// clicking the current one goes back to the order the results were found in.
+fn build_column_header() -> impl Widget<AppState> {
+    let header = |title: &'static str, key: SortKey| {
+        Label::new(move |data: &AppState, _env: &_| {
+            if data.sort_key == key {
+                format!("{} ▾", title)
+            } else {
+                title.to_string()
+            }
+        })
+        .with_text_size(13.0)
+        .with_text_color(Color::grey(0.8))
+        .on_click(move |_ctx, data: &mut AppState, _env| {
+            let key = if data.sort_key == key { SortKey::Found } else { key };
+            data.set_sort_key(key);
+        })
+    };
+    // Rows are inset by their padding and border.
+    Flex::row()
+        .with_child(header("Name", SortKey::Name).fix_width(NAME_COLUMN_WIDTH))
+        .with_child(header("Size", SortKey::Size).fix_width(SIZE_COLUMN_WIDTH))
+        .with_child(header("Modified", SortKey::Modified).fix_width(MODIFIED_COLUMN_WIDTH))
+        .with_child(Either::new(
+            |data: &AppState, _env| data.show_checksums,
+            Label::new("SHA-256")
+                .with_text_size(13.0)
+                .with_text_color(Color::grey(0.8))
+                .fix_width(CHECKSUM_COLUMN_WIDTH),
+            SizedBox::empty(),
+        ))
+        .with_flex_child(header("Folder", SortKey::Path).expand_width(), 1.0)
+        .padding((9.0, 4.0))
+}
+
+/// The name | size | modified | folder cells of a row in the column layout.
+fn build_result_columns() -> impl Widget<ResultRow> {
+    let cell = |text: fn(&RowSettings, &ResultEntry) -> String| {
+        Label::new(move |(settings, item): &ResultRow, _env: &_| text(settings, item))
+            .with_font(theme::RESULT_FONT)
+            .with_text_size(14.0)
+            .with_text_color(theme::TEXT)
+            .with_line_break_mode(druid::widget::LineBreaking::Clip)
+    };
+    Flex::row()
+        .with_child(cell(RowSettings::column_name).fix_width(NAME_COLUMN_WIDTH))
+        .with_child(cell(RowSettings::column_size).fix_width(SIZE_COLUMN_WIDTH))
+        .with_child(
+            cell(|_, item| item.modified.map(format_timestamp).unwrap_or_default())
+                .fix_width(MODIFIED_COLUMN_WIDTH),
+        )
+        .with_child(Either::new(
+            |(settings, _): &ResultRow, _env| settings.checksums.is_some(),
+            cell(|settings, item| settings.checksum(item).unwrap_or_default().to_string())
+                .fix_width(CHECKSUM_COLUMN_WIDTH),
+            SizedBox::empty(),
+        ))
+        .with_flex_child(cell(RowSettings::column_folder).expand_width(), 1.0)
+}
+
+/// Opens a result with the system, or at its matching line for content results.
+fn open_result(ctx: &mut EventCtx, item: &ResultEntry) {
+    if let Some(line) = item.match_line_number {
+        ctx.submit_command(OPEN_AT_LINE.with((item.open_target(), line)));
+    } else {
+        ctx.submit_command(OPEN_PATH.with(item.open_target()));
+    }
+}
+
+/// One row of the results list: white text, padding, dark background, border, and rounded corners.
+fn build_result_row() -> impl Widget<ResultRow> {
+    let path_label = Either::new(
+        |(settings, _): &ResultRow, _env| settings.columns,
+        build_result_columns(),
+        Label::new(|(settings, item): &ResultRow, _env: &_| settings.row_text(item))
+            .with_font(theme::RESULT_FONT)
+            .with_text_size(14.0)
+            .with_text_color(theme::TEXT),
+    );
+
+    // Secondary text with the first matching line, grep-style.
+    let match_line = Either::new(
+        |(settings, item): &ResultRow, _env| settings.match_line(item).is_some(),
+        Label::new(|(settings, item): &ResultRow, _env: &_| {
+            settings.match_line(item).unwrap_or_default().to_string()
+        })
+        .with_font(theme::RESULT_FONT)
+        .with_text_size(12.0)
+        .with_text_color(Color::grey(0.7)),
+        SizedBox::empty(),
+    );
+
+    let label = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(path_label)
+        .with_child(match_line)
+        .expand_width()
+        .on_click(|ctx, (settings, item): &mut ResultRow, _env| {
+            // Inside the browse view, directories open in the app rather than the OS.
+            if settings.browse_mode && item.is_dir {
+                ctx.submit_command(BROWSE_TO.with(item.path()));
+            } else if settings.click_action == RowClickAction::Select {
+                ctx.submit_command(SELECT_PATH.with(item.path()));
+            } else if settings.click_action == RowClickAction::Reveal {
+                ctx.submit_command(REVEAL_IN_APP.with(item.path()));
+            } else {
+                open_result(ctx, item);
+            }
+        });
+
+    // When a click doesn't open the result, this does.
+    let open_btn = Either::new(
+        |(settings, _): &ResultRow, _env| settings.click_action != RowClickAction::Open,
+        Button::new("Open").on_click(|ctx, (_, item): &mut ResultRow, _env| open_result(ctx, item)),
+        SizedBox::empty(),
+    );
+
+    let show_in_app_btn = Button::new("Show in app")
+        .on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(REVEAL_IN_APP.with(item.path()));
+        });
+
+    let reveal_btn = Button::new("Reveal")
+        .on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(REVEAL_IN_FILE_MANAGER.with(item.path()));
+        });
+
+    // Drilling down only makes sense for directories.
+    let search_here_btn = Either::new(
+        |(_, item): &ResultRow, _env| item.is_dir,
+        Button::new("Search here").on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(SEARCH_IN.with(item.path()));
+        }),
+        SizedBox::empty(),
+    );
+
+    // Folders have "Search here" instead.
+    let related_btn = Either::new(
+        |(_, item): &ResultRow, _env| !item.is_dir,
+        Button::new("Related").on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(FIND_RELATED.with(item.path()));
+        }),
+        SizedBox::empty(),
+    );
+
+    let copy_listing_btn = Either::new(
+        |(_, item): &ResultRow, _env| item.is_dir,
+        Button::new("Copy listing").on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(COPY_LISTING.with(item.path()));
+        }),
+        SizedBox::empty(),
+    );
+
+    let new_window_btn = Either::new(
+        |(_, item): &ResultRow, _env| item.is_dir,
+        Button::new("New window").on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(OPEN_IN_NEW_WINDOW.with(item.path()));
+        }),
+        SizedBox::empty(),
+    );
+
+    let new_item_btn = Either::new(
+        |(_, item): &ResultRow, _env| item.is_dir,
+        Button::new("New…").on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(NEW_ITEM_IN.with(item.path()));
+        }),
+        SizedBox::empty(),
+    );
+
+    let run_command_btn = Button::new("Run command")
+        .on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(RUN_COMMAND.with(item.path()));
+        });
+
+    let mark_btn = Either::new(
+        |(_, item): &ResultRow, _env| !item.is_dir,
+        Button::new(|(settings, item): &ResultRow, _env: &_| {
+            if settings.is_marked(item) { "Unmark" } else { "Mark" }.to_string()
+        })
+        .on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(TOGGLE_MARK.with(item.path()));
+        }),
+        SizedBox::empty(),
+    );
+
+    let tag_btn = Button::new(|(settings, item): &ResultRow, _env: &_| {
+        if settings.has_tag(item) { "Untag" } else { "Tag" }.to_string()
+    })
+    .on_click(|ctx, (_, item): &mut ResultRow, _env| {
+        ctx.submit_command(TOGGLE_TAG.with(item.path()));
+    });
+
+    let bookmark_btn = Button::new("Bookmark")
+        .on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(ADD_BOOKMARK.with(item.path()));
+        });
+
+    // Only files have contents to hash.
+    let hash_btn = Either::new(
+        |(_, item): &ResultRow, _env| !item.is_dir,
+        Button::new("SHA-256").on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(HASH_FILE.with(item.path()));
+        }),
+        SizedBox::empty(),
+    );
+
+    let trash_btn = Button::new("Trash")
+        .on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(TRASH_PATH.with(item.path()));
+        });
+
+    let delete_btn = Button::new("Delete permanently…")
+        .on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(DELETE_PATH.with(item.path()));
+        });
+
+    Flex::row()
+        .with_flex_child(label, 1.0)
+        .with_child(open_btn)
+        .with_child(show_in_app_btn)
+        .with_child(reveal_btn)
+        .with_child(search_here_btn)
+        .with_child(related_btn)
+        .with_child(copy_listing_btn)
+        .with_child(new_window_btn)
+        .with_child(new_item_btn)
+        .with_child(run_command_btn)
+        .with_child(build_hard_links_button())
+        .with_child(build_eject_button())
+        .with_child(
+            Either::new(
+                |(_, item): &ResultRow, _env| is_container(item),
+                Button::new("Open container").on_click(|ctx, (_, item): &mut ResultRow, _env| {
+                    ctx.submit_command(OPEN_CONTAINER.with(item.path()));
+                }),
+                SizedBox::empty(),
+            ),
+        )
+        .with_child(mark_btn)
+        .with_child(tag_btn)
+        .with_child(bookmark_btn)
+        .with_child(hash_btn)
+        .with_child(trash_btn)
+        .with_child(delete_btn)
+        .padding(8.0)
+        .background(Painter::new(|ctx, (settings, item): &ResultRow, env| {
+            let color = if settings.is_highlighted(item) {
+                Color::rgb8(0x2a, 0x4d, 0x7a)
+            } else {
+                env.get(theme::SURFACE)
+            };
+            let rect = ctx.size().to_rect();
+            ctx.fill(rect, &color);
+            if settings.is_marked(item) {
+                ctx.fill(rect, &Color::rgb8(0x8e, 0x5c, 0xd9).with_alpha(0.35));
+            }
+            if item.link == Some(LinkTarget::Broken) {
+                ctx.fill(rect, &Color::rgb8(0xd9, 0x7a, 0x1e).with_alpha(0.35));
+            }
+            let git_tint = match settings.git_status(item) {
+                Some(GitStatus::Modified) => Some(Color::rgb8(0xd9, 0xb0, 0x1e)),
+                Some(GitStatus::Staged) => Some(Color::rgb8(0x2e, 0xa0, 0x43)),
+                Some(GitStatus::Untracked) => Some(Color::rgb8(0x3b, 0x8e, 0xd9)),
+                _ => None,
+            };
+            if let Some(tint) = git_tint {
+                ctx.fill(rect, &tint.with_alpha(0.25));
+            }
+            if settings.color_by_age {
+                if let Some(alpha) = item.modified.and_then(recency_alpha) {
+                    ctx.fill(rect, &Color::rgb8(0xf2, 0xa9, 0x3b).with_alpha(alpha));
+                }
+            }
+            // Tint rows a watch-mode refresh added (green) or removed (red), fading out.
+            if let Some((change, at)) = item.change {
+                let remaining = 1.0 - at.elapsed().as_secs_f64() / CHANGE_HIGHLIGHT.as_secs_f64();
+                if remaining > 0.0 {
+                    let tint = match change {
+                        RowChange::Added => Color::rgb8(0x2e, 0xa0, 0x43),
+                        RowChange::Removed => Color::rgb8(0xc0, 0x39, 0x2b),
+                    };
+                    ctx.fill(rect, &tint.with_alpha(0.5 * remaining));
+                }
+            }
+            // A stripe down the left edge in the file type's color.
+            if settings.color_by_type {
+                if let Some(color) = theme::file_type_color(&item.path(), item.is_dir) {
+                    ctx.fill(rect.with_size((4.0, rect.height())), &color);
+                }
+            }
+            // A stripe down the right edge, from teal at the top level to purple
+            // DEPTH_COLOR_LEVELS folders down and below.
+            if let Some(depth) = settings.depth(item) {
+                let deep = depth.min(DEPTH_COLOR_LEVELS) as f64 / DEPTH_COLOR_LEVELS as f64;
+                let mix = |shallow: u8, deep_end: u8| {
+                    (shallow as f64 + (deep_end as f64 - shallow as f64) * deep).round() as u8
+                };
+                let color = Color::rgb8(mix(0x2a, 0x8e), mix(0xb5, 0x3c), mix(0xa8, 0xd9));
+                let stripe = rect.with_origin((rect.x1 - 4.0, rect.y0)).with_size((4.0, rect.height()));
+                ctx.fill(stripe, &color);
+            }
+            // Drawn inside the row's own border, which it thickens.
+            if settings.mark_folders && item.is_dir {
+                if let Some(color) = theme::file_type_color(&item.path(), true) {
+                    ctx.stroke(rect.inset(-1.5).to_rounded_rect(4.0), &color, 3.0);
+                }
+            }
+        }))
+        .border(theme::ACCENT, 1.0)
+        .rounded(4.0)
+        .controller(ReportHover)
+        .controller(RequestChecksum)
+}
+
+// Mount points are told apart by device id, which only Unix exposes.
+#[cfg(unix)]
+fn build_stay_on_filesystem_box() -> impl Widget<AppState> {
+    Checkbox::new("Stay on this filesystem")
+        .padding(8.0)
+        .lens(AppState::stay_on_filesystem)
+}
+
+#[cfg(not(unix))]
+fn build_stay_on_filesystem_box() -> impl Widget<AppState> {
+    SizedBox::empty()
+}
+
+/// How strongly to tint a row modified at `modified`: bright within the hour, faint within the
+/// day, and not at all after that.
+fn recency_alpha(modified: SystemTime) -> Option<f64> {
+    // Times in the future (clock skew) count as just modified.
+    let age = modified.elapsed().unwrap_or_default();
+    if age < RECENT_HOUR {
+        Some(0.35)
+    } else if age < RECENT_DAY {
+        Some(0.15)
+    } else {
+        None
+    }
+}
+
+// Hard links are identified by (device, inode), which only Unix exposes.
+#[cfg(unix)]
+fn build_hard_links_button() -> impl Widget<ResultRow> {
+    Button::new("Hard links").on_click(|ctx, (_, item): &mut ResultRow, _env| {
+        ctx.submit_command(FIND_HARD_LINKS.with(item.path()));
+    })
+}
+
+#[cfg(not(unix))]
+fn build_hard_links_button() -> impl Widget<ResultRow> {
+    SizedBox::empty()
+}
+
+/// Whether an entry is a zero-byte file or a folder with nothing in it. Folders are read, so
+/// this is called when an entry is built, off the UI thread.
+fn is_empty(path: &Path, is_dir: bool, size: Option<u64>) -> bool {
+    if is_dir {
+        fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+    } else {
+        size == Some(0)
+    }
+}
+
+/// Whether `path` is a folder another drive is mounted on. Mount points are told apart by
+/// device id, which only Unix exposes. This stats the folder and its parent, so it is called
+/// when an entry is built, off the UI thread.
+#[cfg(unix)]
+fn is_mount_point(path: &Path) -> bool {
+    eject::is_mount_point(path)
+}
+
+#[cfg(not(unix))]
+fn is_mount_point(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn build_eject_button() -> impl Widget<ResultRow> {
+    Either::new(
+        |(_, item): &ResultRow, _env| item.mount_point && eject::is_removable(&item.path()),
+        Button::new("Eject").on_click(|ctx, (_, item): &mut ResultRow, _env| {
+            ctx.submit_command(EJECT_DRIVE.with(item.path()));
+        }),
+        SizedBox::empty(),
+    )
+}
+
+#[cfg(not(unix))]
+fn build_eject_button() -> impl Widget<ResultRow> {
+    SizedBox::empty()
+}
+
+/// Whether "Open container" applies to a result: a file named like a disk image or archive, or
+/// on Unix a folder another drive is mounted on.
+fn is_container(item: &ResultEntry) -> bool {
+    if item.is_dir {
+        return item.mount_point;
+    }
+    disk_image::Container::of(Path::new(&item.name)).is_some()
+}
+
+/// Reports the row's full path while the pointer is over it, standing in for a tooltip.
+struct ReportHover;
+
+impl<W: Widget<ResultRow>> Controller<ResultRow, W> for ReportHover {
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &ResultRow,
+        env: &Env,
+    ) {
+        if let LifeCycle::HotChanged(hot) = event {
+            ctx.submit_command(HOVER_PATH.with((data.0.shown(&data.1.display()), *hot)));
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
+
+/// Asks for a file row's checksum when it comes into view, or when "Checksums" is turned on
+/// or the row is reused for another file, unless it is already cached. Only rows the list has
+/// built are ever hashed, which keeps large result lists cheap.
+struct RequestChecksum;
+
+impl RequestChecksum {
+    fn request((settings, item): &ResultRow) -> Option<Command> {
+        let wanted =
+            settings.checksums.is_some() && !item.is_dir && settings.checksum(item).is_none();
+        wanted.then(|| WANT_CHECKSUM.with((item.path(), item.modified)))
+    }
+}
+
+impl<W: Widget<ResultRow>> Controller<ResultRow, W> for RequestChecksum {
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &ResultRow,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            if let Some(request) = Self::request(data) {
+                ctx.submit_command(request);
+            }
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &ResultRow,
+        data: &ResultRow,
+        env: &Env,
+    ) {
+        let (old_settings, old_item) = old_data;
+        let (settings, item) = data;
+        let changed = old_settings.checksums.is_none() != settings.checksums.is_none()
+            || old_item.parent != item.parent
+            || old_item.name != item.name
+            || old_item.modified != item.modified;
+        if let Some(request) = Self::request(data).filter(|_| changed) {
+            ctx.submit_command(request);
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/// Starts a fresh search from the current state, clearing the old results and browse view.
+fn start_search(sink: WindowSink, data: &mut AppState) {
+    // Show the terms as they'll be searched for.
+    if data.trim_terms {
+        if data.search_term.trim() != data.search_term {
+            data.search_term = data.search_term.trim().to_string();
+        }
+        if data.or_terms.iter().any(|term| term.trim() != term) {
+            data.or_terms = Arc::new(data.or_terms.iter().map(|t| t.trim().to_string()).collect());
+        }
+    }
+    // Boxes that were left empty are dropped.
+    if data.or_terms.iter().any(String::is_empty) {
+        data.or_terms = Arc::new(data.or_terms.iter().filter(|t| !t.is_empty()).cloned().collect());
+    }
+    if data.other_roots.iter().any(|root| root.trim().is_empty()) {
+        data.other_roots =
+            Arc::new(data.other_roots.iter().filter(|r| !r.trim().is_empty()).cloned().collect());
+    }
+    data.notice.clear();
+    for date in [&data.created_after, &data.created_before] {
+        if !date.trim().is_empty() && parse_date(date).is_none() {
+            data.error_message = format!("Couldn't read the date \"{}\"; use YYYY-MM-DD.", date);
+            return;
+        }
+    }
+    data.tidy_root();
+    if !data.clamp_to_sandbox() {
+        return;
+    }
+    // The root may have been typed in since it was last chosen.
+    data.probe_creation_times();
+    data.resume_watch_search = false;
+    if data.watch && data.remember_watch_terms && !data.search_term.is_empty() {
+        let key = folder_key(&data.root_path);
+        Arc::make_mut(&mut data.watch_terms).insert(key, data.search_term.clone());
+    }
+    let outside: Vec<String> = data
+        .other_roots
+        .iter()
+        .filter(|root| !data.sandbox_allows(Path::new(root)))
+        .cloned()
+        .collect();
+    if !outside.is_empty() {
+        data.other_roots = Arc::new(
+            data.other_roots.iter().filter(|root| !outside.contains(root)).cloned().collect(),
+        );
+        data.notice =
+            format!("Left out {}, outside the folder this app is limited to.", outside.join(", "));
+    }
+    let mut options = data.search_options();
+    if let Some(root) = whole_disk_root(&options).filter(|_| data.warn_whole_disk) {
+        match ask_whole_disk_search(root) {
+            WholeDiskChoice::AddExcludes => {
+                data.exclude_paths = with_default_excludes(&data.exclude_paths);
+                options = data.search_options();
+            }
+            WholeDiskChoice::SearchAnyway => {}
+            WholeDiskChoice::Cancel => return,
+        }
+    }
+
+    // Clear any previous search results, unless this search adds to them.
+    if !data.append_results {
+        data.set_results(Arc::new(Vec::new()));
+    }
+    data.browse_mode = false;
+    data.highlighted = PathBuf::new();
+    data.widen_levels_left = if data.auto_widen { AUTO_WIDEN_LEVELS } else { 0 };
+
+    spawn_search(sink, data, options);
+}
+
+/// The root of a disk that `options` would walk to the bottom, if any: nothing limits the
+/// depth, no folders are skipped by name and the system folders aren't all excluded.
+fn whole_disk_root(options: &SearchOptions) -> Option<&Path> {
+    if options.max_depth.is_some() || !options.exclude_dirs.is_empty() {
+        return None;
+    }
+    if search::default_exclude_paths().iter().all(|path| options.exclude_paths.contains(path)) {
+        return None;
+    }
+    std::iter::once(&options.root)
+        .chain(&options.other_roots)
+        .map(PathBuf::as_path)
+        .find(|root| root.parent().is_none())
+}
+
+/// What to do about a search of a whole disk.
+enum WholeDiskChoice {
+    // Skip the platform's system folders, then search.
+    AddExcludes,
+    SearchAnyway,
+    Cancel,
+}
+
+/// Warns that searching all of `root` may take a long time and offers to skip the system
+/// folders first.
+fn ask_whole_disk_search(root: &Path) -> WholeDiskChoice {
+    const ADD: &str = "Skip system folders";
+    const ANYWAY: &str = "Search anyway";
+    let description = format!(
+        "This searches all of {} with no depth limit, which can take a long time.\n\n\
+        Skipping the system folders ({}) usually helps. This warning can be turned off under Settings.",
+        root.display(),
+        config::default_exclude_paths()
+    );
+    let answer = rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Search the whole disk?")
+        .set_description(description)
+        .set_buttons(rfd::MessageButtons::YesNoCancelCustom(
+            ADD.to_string(),
+            ANYWAY.to_string(),
+            "Cancel".to_string(),
+        ))
+        .show();
+    // Some platforms report custom buttons by position instead of by label.
+    match answer {
+        rfd::MessageDialogResult::Yes => WholeDiskChoice::AddExcludes,
+        rfd::MessageDialogResult::No => WholeDiskChoice::SearchAnyway,
+        rfd::MessageDialogResult::Custom(label) if label == ADD => WholeDiskChoice::AddExcludes,
+        rfd::MessageDialogResult::Custom(label) if label == ANYWAY => WholeDiskChoice::SearchAnyway,
+        _ => WholeDiskChoice::Cancel,
+    }
+}
+
+/// The comma-separated `exclude_paths` with any of the platform's system folders it lacks added.
+fn with_default_excludes(exclude_paths: &str) -> String {
+    let mut paths = split_list(exclude_paths);
+    for path in search::default_exclude_paths() {
+        let path = path.to_string_lossy().to_string();
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths.join(", ")
+}
+
+/// Runs a search on a background thread and sends the results back to the UI thread,
+/// cancelling whichever search was running before.
+fn spawn_search(sink: WindowSink, data: &mut AppState, mut options: SearchOptions) {
+    // The engine refuses roots outside the sandbox too, whatever led to them.
+    options.sandbox_root = data.sandbox_root.clone();
+    let (generation, cancel) = data.begin_search();
+    let roots = std::iter::once(&options.root).chain(&options.other_roots).cloned().collect();
+    data.searched_roots = Arc::new(roots);
+    data.searched_recursively = options.max_depth != Some(1);
+    // A fresh set of results can't be refined back to the old ones.
+    data.refined_from = Arc::new(Vec::new());
+    let found = Arc::new(AtomicUsize::new(0));
+    let dirs_scanned = Arc::new(AtomicUsize::new(0));
+    let retried = AtomicUsize::new(0);
+    let done = Arc::new(AtomicBool::new(false));
+    let key = coverage_key(&options);
+    data.dirs_estimate = data.walk_sizes.get(&key).copied();
+    if data.live_count || data.show_coverage {
+        let interval = data.update_interval();
+        let counters = (found.clone(), dirs_scanned.clone());
+        spawn_count_reporter(sink.clone(), generation, counters, done.clone(), interval);
+    }
+    thread::spawn(move || {
+        let results = search::run_with_coverage(&options, &cancel, &found, &dirs_scanned, &retried);
+        done.store(true, Ordering::Relaxed);
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        if results.is_ok() {
+            let walked = dirs_scanned.load(Ordering::Relaxed);
+            let _ = sink.submit_command(WALK_SIZE, (key, walked));
+        }
+        let retried = retried.load(Ordering::Relaxed);
+        if retried > 0 {
+            let _ = sink.submit_command(WALK_RETRIES, retried);
+        }
+        let submitted = match results {
+            Ok(results) => {
+                let mut parents = SharedParents::default();
+                let results = results
+                    .into_iter()
+                    .map(|result| ResultEntry::from_search(result, &mut parents))
+                    .collect();
+                let results = Arc::new(results);
+                sink.submit_command(UPDATE_SEARCH_RESULTS, (generation, results))
+            }
+            Err(err) => sink.submit_command(SEARCH_FAILED, (generation, err.to_string())),
+        };
+        submitted.expect("Failed to submit command");
+    });
+}
+
+/// What a walk's size is remembered under: its folders and depth limit, since a listing of a
+/// folder reads far fewer directories than a search of it.
+fn coverage_key(options: &SearchOptions) -> String {
+    let mut key = format!("{:?}", options.max_depth);
+    for root in std::iter::once(&options.root).chain(&options.other_roots) {
+        key.push('\n');
+        key.push_str(&root.to_string_lossy());
+    }
+    key
+}
+
+/// Reports `found` and the directories walked every `interval` while they change, until the
+/// search sets `done`.
+/// Ticking rather than reporting every match keeps a fast search from flooding the UI.
+fn spawn_count_reporter(
+    sink: WindowSink,
+    generation: u64,
+    (found, dirs_scanned): (Arc<AtomicUsize>, Arc<AtomicUsize>),
+    done: Arc<AtomicBool>,
+    interval: Duration,
+) {
+    thread::spawn(move || {
+        let mut reported = (0, 0);
+        while !done.load(Ordering::Relaxed) {
+            thread::sleep(interval);
+            let counts = (found.load(Ordering::Relaxed), dirs_scanned.load(Ordering::Relaxed));
+            if counts != reported {
+                reported = counts;
+                let progress = (generation, counts.0, counts.1);
+                if sink.submit_command(SEARCH_PROGRESS, progress).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// Hashes the most recently requested file in `checksum_queue` on a background thread, if none
+/// is being hashed already. Files hashed since they were queued are skipped.
+fn hash_next_file(sink: WindowSink, data: &mut AppState) {
+    if data.hashing_checksum {
+        return;
+    }
+    let queue = Arc::make_mut(&mut data.checksum_queue);
+    let next = std::iter::from_fn(|| queue.pop()).find(|(path, modified)| {
+        data.checksums.get(path).is_none_or(|(hashed_at, _)| hashed_at != modified)
+    });
+    let Some((path, modified)) = next else {
+        return;
+    };
+    data.hashing_checksum = true;
+    data.checksum_cancel = Arc::new(AtomicBool::new(false));
+    let cancel = data.checksum_cancel.clone();
+    thread::spawn(move || {
+        let hash = match digest::sha256_file(&path, &cancel, |_, _| {}) {
+            Ok(mut hash) => {
+                hash.truncate(CHECKSUM_CHARS);
+                Some(hash)
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => None,
+            Err(_) => Some(String::new()),
+        };
+        let _ = sink.submit_command(CHECKSUM_READY, (path, modified, hash));
+    });
+}
+
+/// Stops hashing the current file as soon as the checksums are hidden.
+struct CancelHiddenChecksums;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for CancelHiddenChecksums {
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if old_data.show_checksums && !data.show_checksums {
+            data.checksum_cancel.store(true, Ordering::Relaxed);
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/// Counts each directory's entries, up to `ITEM_COUNT_LIMIT`, on a background thread.
+/// Directories that can't be read are left out.
+fn spawn_count_dir_items(sink: WindowSink, dirs: Vec<PathBuf>) {
+    thread::spawn(move || {
+        let counts: Vec<(PathBuf, usize)> = dirs
+            .into_iter()
+            .filter_map(|dir| {
+                let count = fs::read_dir(&dir).ok()?.take(ITEM_COUNT_LIMIT).count();
+                Some((dir, count))
+            })
+            .collect();
+        let _ = sink.submit_command(DIR_ITEM_COUNTS, counts);
+    });
+}
+
+/// Opens each path in turn on a background thread, pausing between spawns and reporting progress.
+fn spawn_open_all(sink: WindowSink, open_command: String, paths: Vec<PathBuf>) {
+    thread::spawn(move || {
+        let total = paths.len();
+        for (i, path) in paths.iter().enumerate() {
+            if sink.submit_command(OPEN_ALL_PROGRESS, Some((i + 1, total))).is_err() {
+                // The app has shut down; don't keep opening files.
+                return;
+            }
+            open_path(&open_command, path);
+            thread::sleep(OPEN_ALL_DELAY);
+        }
+        let _ = sink.submit_command(OPEN_ALL_PROGRESS, None);
+    });
+}
+
+/// Computes a file's SHA-256 on a background thread, reporting progress in whole percents.
+fn spawn_hash_file(sink: WindowSink, path: PathBuf) {
+    thread::spawn(move || {
+        let mut reported = None;
+        // Nothing stops a single file's hash once asked for; the row shows how far it got.
+        let digest = digest::sha256_file(&path, &AtomicBool::new(false), |hashed, total| {
+            let percent = (hashed * 100).checked_div(total).unwrap_or(100) as usize;
+            if reported != Some(percent) {
+                reported = Some(percent);
+                let _ = sink.submit_command(HASH_PROGRESS, (percent, 100));
+            }
+        });
+        let digest = digest.map_err(|err| err.to_string());
+        let _ = sink.submit_command(FILE_HASHED, (path, digest));
+    });
+}
+
+/// Hashes same-sized files on a background thread and reports the identical clusters, with
+/// progress at most once per `interval` apart from the last step.
+fn spawn_find_duplicates(sink: WindowSink, paths: Vec<PathBuf>, interval: Duration) {
+    thread::spawn(move || {
+        let mut last_report: Option<Instant> = None;
+        let clusters = duplicates::find_duplicates(&paths, |hashed, total| {
+            if hashed < total && last_report.is_some_and(|at| at.elapsed() < interval) {
+                return;
+            }
+            last_report = Some(Instant::now());
+            let _ = sink.submit_command(DUPLICATE_PROGRESS, (hashed, total));
+        });
+        let groups = clusters
+            .into_iter()
+            .map(|(size, paths)| DuplicateGroup {
+                size,
+                files: Arc::new(paths.into_iter().map(ResultEntry::new).collect()),
+            })
+            .collect();
+        let _ = sink.submit_command(DUPLICATES_FOUND, Arc::new(groups));
+    });
+}
+
+/// A tab-separated `path, size, modified` line for spreadsheets. Size is in bytes and left
+/// blank for folders; both columns are blank if the entry can't be read any more.
+fn table_row(entry: &ResultEntry) -> String {
+    // Tabs and newlines in a name would break the columns.
+    let path = entry.display().replace(['\t', '\n', '\r'], " ");
+    let metadata = match fs::metadata(entry.path()) {
+        Ok(metadata) => metadata,
+        Err(_) => return format!("{}\t\t", path),
+    };
+    let size = if metadata.is_dir() {
+        String::new()
+    } else {
+        metadata.len().to_string()
+    };
+    let modified = metadata.modified().map(format_timestamp).unwrap_or_default();
+    format!("{}\t{}\t{}", path, size, modified)
+}
+
+/// What the preview pane shows for `path`: the first lines of a text file, the picture of an
+/// image with a line about it, or a line saying what it is for folders and other files. Only
+/// regular files are opened, since reading a FIFO or a device can block forever. At most
+/// PREVIEW_BYTES of a text file are read.
+fn file_preview(path: &Path) -> (String, Option<ImageBuf>) {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) => return (format!("Couldn't read {}: {}", path.display(), err), None),
+    };
+    if metadata.is_dir() {
+        return ("Folder".to_string(), None);
+    }
+    if !metadata.is_file() {
+        return ("Not a regular file".to_string(), None);
+    }
+    let size = format_size(metadata.len());
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_uppercase())
+        .unwrap_or_default();
+    if PREVIEW_IMAGE_TYPES.contains(&extension.as_str()) {
+        if metadata.len() > PREVIEW_IMAGE_MAX_BYTES {
+            return (format!("{} image, {}. Open it to view.", extension, size), None);
+        }
+        return match ImageBuf::from_file(path) {
+            Ok(image) => {
+                let text = format!("{} image, {}×{}, {}", extension, image.width(), image.height(), size);
+                (text, Some(image))
+            }
+            Err(err) => (format!("{} image, {}. Couldn't decode it: {}", extension, size, err), None),
+        };
+    }
+    let mut start = Vec::new();
+    let read = fs::File::open(path)
+        .and_then(|file| file.take(PREVIEW_BYTES).read_to_end(&mut start));
+    if let Err(err) = read {
+        return (format!("Couldn't read {}: {}", path.display(), err), None);
+    }
+    if start.contains(&0) {
+        return (format!("Binary file, {}", size), None);
+    }
+    let text = String::from_utf8_lossy(&start);
+    // The read may have stopped partway through a character.
+    let text = text.trim_end_matches('\u{fffd}');
+    // Escape sequences and bidi overrides in the text are shown, not obeyed; tabs are spaced.
+    let mut lines: Vec<String> = text
+        .lines()
+        .take(PREVIEW_LINES)
+        .map(|line| escape_controls(&line.replace('\t', "    ")))
+        .collect();
+    if lines.is_empty() {
+        lines.push("(empty)".to_string());
+    }
+    (lines.join("\n"), None)
+}
+
+/// A size in bytes for display, e.g. `812 B` or `4.2 MB`, in powers of 1000.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Formats a time as `YYYY-MM-DD HH:MM:SS` in UTC, which spreadsheets read as a date.
+fn format_timestamp(time: SystemTime) -> String {
+    let secs = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    };
+    let (days, time_of_day) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    // Days since 1970-01-01 to a civil date (Howard Hinnant's `civil_from_days`).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day % 3600 / 60,
+        time_of_day % 60
+    )
+}
+
+/// Whether the filesystem holding `dir` records creation times, which some Linux ones don't.
+fn creation_times_supported(dir: &Path) -> bool {
+    fs::metadata(dir).and_then(|metadata| metadata.created()).is_ok()
+}
+
+/// Quotes `arg` so a POSIX shell passes it through as one literal argument.
+#[cfg(not(windows))]
+fn shell_quote(arg: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "-_./,:@%+=".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_plain) {
+        return arg.to_string();
+    }
+    // Nothing is special inside single quotes, so only a quote itself needs splicing in.
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Quotes `arg` the way the Windows command-line parser (CommandLineToArgvW) splits it.
+#[cfg(windows)]
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Backslashes before a quote are escaped, and so is the quote.
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    // Backslashes before the closing quote would escape it.
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+// The extended attribute Finder keeps a file's tags in, as a property list of strings.
+#[cfg(target_os = "macos")]
+const FINDER_TAGS_ATTRIBUTE: &str = "com.apple.metadata:_kMDItemUserTags";
+
+/// Adds `tag` to the Finder tags of `path`, or with `add` false takes it off, on a background
+/// thread, so tags given in the app show in Finder and Spotlight too. Tags set in Finder are
+/// read first and kept.
+#[cfg(target_os = "macos")]
+fn spawn_set_finder_tags(sink: WindowSink, path: PathBuf, tag: String, add: bool) {
+    thread::spawn(move || {
+        let result = read_finder_tags(&path).and_then(|mut tags| {
+            // Finder stores a tag's color after its name, as in "Work\n6".
+            let position = tags.iter().position(|t| t.split('\n').next() == Some(tag.as_str()));
+            match (position, add) {
+                (None, true) => tags.push(tag.clone()),
+                (Some(index), false) => {
+                    tags.remove(index);
+                }
+                _ => return Ok(()),
+            }
+            write_finder_tags(&path, &tags)
+        });
+        if let Err(err) = result {
+            let message = format!("Couldn't set Finder tags on {}: {}", path.display(), err);
+            let _ = sink.submit_command(REPORT_ERROR, message);
+        }
+    });
+}
+
+/// The Finder tags of `path`, none if it has never been tagged. The attribute holds a binary
+/// property list, which `plutil` turns into XML to read the strings from.
+#[cfg(target_os = "macos")]
+fn read_finder_tags(path: &Path) -> std::io::Result<Vec<String>> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    let output = Command::new("xattr").args(["-px", FINDER_TAGS_ATTRIBUTE]).arg(path).output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if message.contains("No such xattr") {
+            return Ok(Vec::new());
+        }
+        return Err(std::io::Error::other(message));
+    }
+    let hex: Vec<u8> = output.stdout.into_iter().filter(u8::is_ascii_hexdigit).collect();
+    let bytes: Vec<u8> = hex
+        .chunks(2)
+        .filter_map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect();
+    let mut plutil = Command::new("plutil")
+        .args(["-convert", "xml1", "-o", "-", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = plutil.stdin.take() {
+        stdin.write_all(&bytes)?;
+    }
+    let output = plutil.wait_with_output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(std::io::Error::other(message));
+    }
+    let xml = String::from_utf8_lossy(&output.stdout);
+    let unescape = |text: &str| {
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&")
+    };
+    Ok(xml
+        .split("<string>")
+        .skip(1)
+        .filter_map(|rest| rest.split_once("</string>"))
+        .map(|(tag, _)| unescape(tag))
+        .collect())
+}
+
+/// Replaces the Finder tags of `path` with `tags`, removing the attribute when there are none.
+#[cfg(target_os = "macos")]
+fn write_finder_tags(path: &Path, tags: &[String]) -> std::io::Result<()> {
+    let mut command = std::process::Command::new("xattr");
+    if tags.is_empty() {
+        command.args(["-d", FINDER_TAGS_ATTRIBUTE]);
+    } else {
+        let escape = |tag: &String| {
+            tag.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+        };
+        let strings: String =
+            tags.iter().map(|tag| format!("<string>{}</string>", escape(tag))).collect();
+        let plist = format!("<plist version=\"1.0\"><array>{}</array></plist>", strings);
+        command.args(["-w", FINDER_TAGS_ATTRIBUTE, &plist]);
+    }
+    let output = command.arg(path).output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(std::io::Error::other(message))
+}
+
+/// Reveals `path` in the file manager on a background thread, since asking the file manager
+/// can take a moment. Says so if the entry is gone and only a folder above it could be shown.
+fn spawn_reveal(sink: WindowSink, path: PathBuf) {
+    thread::spawn(move || {
+        let message = match reveal::reveal_in_file_manager(&path) {
+            Ok(Revealed::Entry) => return,
+            Ok(Revealed::Ancestor(dir)) => {
+                format!("{} no longer exists; showing {} instead.", path.display(), dir.display())
+            }
+            Err(err) => format!("Couldn't reveal {}: {}", path.display(), err),
+        };
+        let _ = sink.submit_command(REPORT_ERROR, message);
+    });
+}
+
+/// Runs the command template on a background thread, reporting failures and non-zero exits.
+fn spawn_user_command(sink: WindowSink, template: String, path: PathBuf) {
+    thread::spawn(move || {
+        if template.trim().is_empty() {
+            let message = "No command set; add one under Settings.".to_string();
+            let _ = sink.submit_command(REPORT_ERROR, message);
+            return;
+        }
+        let (program, args) = match template::expand(&template, &[("{}", path.as_os_str())], &["{}"]) {
+            Ok(command) => command,
+            Err(err) => {
+                let _ = sink.submit_command(REPORT_ERROR, err);
+                return;
+            }
+        };
+        let name = program.to_string_lossy().to_string();
+        let error = match std::process::Command::new(&program).args(&args).status() {
+            Ok(status) if status.success() => return,
+            Ok(status) => format!("`{}` exited with {}", name, status),
+            Err(err) => format!("Couldn't run `{}`: {}", name, err),
+        };
+        let _ = sink.submit_command(REPORT_ERROR, error);
+    });
+}
+
+/// Walks `root` on a background thread for every file sharing `target`'s (device, inode)
+/// and shows that set as the results.
+#[cfg(unix)]
+fn spawn_find_hard_links(sink: WindowSink, data: &mut AppState, target: PathBuf) {
+    use std::os::unix::fs::MetadataExt;
+
+    let root = PathBuf::from(&data.root_path);
+    let (generation, cancel) = data.begin_search();
+    thread::spawn(move || {
+        let metadata = match fs::metadata(&target) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                let message = format!("Couldn't read {}: {}", target.display(), err);
+                let _ = sink.submit_command(REPORT_ERROR, message);
+                return;
+            }
+        };
+        let key = (metadata.dev(), metadata.ino());
+        let results: Vec<ResultEntry> = walkdir::WalkDir::new(&root)
+            .into_iter()
+            .take_while(|_| !cancel.load(Ordering::Relaxed))
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .filter(|entry| {
+                entry
+                    .metadata()
+                    .map(|m| (m.dev(), m.ino()) == key)
+                    .unwrap_or(false)
+            })
+            .map(|entry| ResultEntry::new(entry.into_path()))
+            .collect();
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        let _ = sink.submit_command(UPDATE_SEARCH_RESULTS, (generation, Arc::new(results)));
+    });
+}
+
+/// Switches to the browse view listing `dir`, optionally highlighting one of its entries.
+fn browse_to(sink: WindowSink, data: &mut AppState, dir: &Path, highlight: PathBuf) {
+    if !data.set_root(dir) {
+        return;
+    }
+    data.resume_watch_search = false;
+    data.browse_mode = true;
+    data.highlighted = highlight;
+    spawn_search(sink, data, SearchOptions::listing(dir));
+}
+
+/// How a content search can take file names into account, as offered in the UI.
+fn content_modes() -> Vec<(&'static str, SearchMode)> {
+    vec![
+        ("Contents only", SearchMode::Contents),
+        ("Name or contents", SearchMode::NameOrContents),
+        ("Name and contents", SearchMode::NameAndContents),
+    ]
+}
+
+/// The permission filters offered in the UI. Only Unix exposes permission bits.
+#[cfg(unix)]
+fn permission_filters() -> Vec<(&'static str, PermissionFilter)> {
+    vec![
+        ("Any", PermissionFilter::Any),
+        ("Executable", PermissionFilter::Executable),
+        ("World-writable", PermissionFilter::WorldWritable),
+        ("Setuid", PermissionFilter::Setuid),
+        ("Read-only", PermissionFilter::ReadOnly),
+    ]
+}
+
+#[cfg(not(unix))]
+fn permission_filters() -> Vec<(&'static str, PermissionFilter)> {
+    vec![("Any", PermissionFilter::Any), ("Read-only", PermissionFilter::ReadOnly)]
+}
+
+/// Drops the `\\?\` prefix Windows' canonicalize adds, for drive paths that don't need it.
+#[cfg(windows)]
+fn without_verbatim_prefix(path: &Path) -> PathBuf {
+    let text = path.to_string_lossy();
+    match text.strip_prefix(r"\\?\") {
+        Some(rest) if rest.as_bytes().get(1) == Some(&b':') => PathBuf::from(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(not(windows))]
+fn without_verbatim_prefix(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// The form of the folder `root` that things remembered for it are stored under, so
+/// `~/src/`, `~/src` and a link to it all find the same entry: resolved where it exists,
+/// otherwise as typed without trailing separators.
+fn folder_key(root: &str) -> String {
+    match fs::canonicalize(root) {
+        Ok(path) => without_verbatim_prefix(&path).to_string_lossy().to_string(),
+        Err(_) => {
+            let trimmed = root.trim_end_matches(std::path::is_separator);
+            // The filesystem root is nothing but a separator.
+            if trimmed.is_empty() { root.to_string() } else { trimmed.to_string() }
+        }
+    }
+}
+
+/// Splits a comma-separated setting into its trimmed, non-empty items.
+fn split_list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Returns the longest directory that contains every result, or an empty path if there is none.
+fn common_dir_prefix(results: &[ResultEntry]) -> PathBuf {
+    let mut parents = results.iter().map(|r| r.parent.as_path());
+    let mut prefix = match parents.next() {
+        Some(parent) => parent.to_path_buf(),
+        None => return PathBuf::new(),
+    };
+    for parent in parents {
+        while !parent.starts_with(&prefix) {
+            if !prefix.pop() {
+                return PathBuf::new();
+            }
+        }
+    }
+    prefix
+}
+
+/// Carries out a confirmed batch, collecting failures into one error message.
+fn run_batch(sink: WindowSink, data: &mut AppState, batch: &PendingBatch) {
+    if let Some(folder) = &batch.destination {
+        let files = batch.files.iter().map(|file| file.path()).collect();
+        data.notice = format!("Moving {} files into {}…", batch.files.len(), folder.display());
+        spawn_move_into(sink, folder.clone(), batch.structure_root.clone(), files);
+        return;
+    }
+    let mut failures = Vec::new();
+    for file in batch.files.iter() {
+        let result = if batch.permanent {
+            delete_permanently(&file.path()).map_err(|err| err.to_string())
+        } else {
+            trash::delete(file.path()).map_err(|err| err.to_string())
+        };
+        match result {
+            Ok(()) => {
+                data.remove_result(&file.path());
+                let done = if batch.permanent { "Deleted" } else { "Moved to the trash:" };
+                data.log(format!("{} {}", done, file.display()));
+            }
+            Err(err) => failures.push(format!("{}: {}", file.display(), err)),
+        }
+    }
+    if !failures.is_empty() {
+        let verb = if batch.permanent { "delete" } else { "trash" };
+        data.error_message = format!("Couldn't {} {}", verb, failures.join("; "));
+    }
+    // The cluster is resolved whether or not every copy could be removed.
+    if let Some(files) = &batch.group {
+        let groups = data
+            .duplicate_groups
+            .iter()
+            .filter(|group| !Arc::ptr_eq(&group.files, files))
+            .cloned()
+            .collect();
+        data.duplicate_groups = Arc::new(groups);
+    }
+}
+
+/// The time before which entries count as stale when `days` old. Day counts too large to
+/// subtract from now reach back to the epoch instead of overflowing.
+fn stale_cutoff(days: u64) -> SystemTime {
+    let age = Duration::from_secs(days.saturating_mul(86_400));
+    SystemTime::now().checked_sub(age).unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Asks a yes/no question in a native dialog and returns true for yes.
+fn confirm(title: &str, description: &str) -> bool {
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title(title)
+        .set_description(description)
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        == rfd::MessageDialogResult::Yes
+}
+
+/// Removes a file or a whole directory tree, bypassing the trash.
+fn delete_permanently(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// A path in `folder` for `name` that isn't taken yet. If `name` is, a number is added before
+/// the extension, e.g. `main (2).rs`.
+fn free_path(folder: &Path, name: &Path) -> PathBuf {
+    let mut path = folder.join(name);
+    let mut n = 2;
+    // symlink_metadata so an existing broken link counts as taken too.
+    while fs::symlink_metadata(&path).is_ok() {
+        let mut numbered = name.file_stem().unwrap_or_default().to_os_string();
+        numbered.push(format!(" ({})", n));
+        if let Some(extension) = name.extension() {
+            numbered.push(".");
+            numbered.push(extension);
+        }
+        path = folder.join(numbered);
+        n += 1;
+    }
+    path
+}
+
+/// Creates a symlink to `target` inside `folder`, named after the target unless that is taken.
+fn link_into(folder: &Path, target: &Path) -> std::io::Result<()> {
+    let target = std::path::absolute(target)?;
+    let link = free_path(folder, Path::new(target.file_name().unwrap_or(target.as_os_str())));
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(&target, &link);
+    #[cfg(windows)]
+    return if target.is_dir() {
+        std::os::windows::fs::symlink_dir(&target, &link)
+    } else {
+        std::os::windows::fs::symlink_file(&target, &link)
+    };
+}
+
+/// Copies the file `source` into `folder`, named after the source unless that is taken. With a
+/// `root`, the copy goes into the same subfolders of `folder` as `source` is in under `root`
+/// (like `rsync --relative`), creating them as needed; files outside `root` go in at the top.
+fn copy_into(folder: &Path, source: &Path, root: Option<&Path>) -> std::io::Result<()> {
+    let relative_dir = root
+        .and_then(|root| source.parent()?.strip_prefix(root).ok())
+        .unwrap_or(Path::new(""));
+    let dir = folder.join(relative_dir);
+    fs::create_dir_all(&dir)?;
+    let name = source.file_name().unwrap_or(source.as_os_str());
+    fs::copy(source, free_path(&dir, Path::new(name))).map(|_| ())
+}
+
+/// Like `copy_into`, but moves the file, copying and removing it when it is on another volume.
+fn move_into(folder: &Path, source: &Path, root: Option<&Path>) -> std::io::Result<()> {
+    let relative_dir = root
+        .and_then(|root| source.parent()?.strip_prefix(root).ok())
+        .unwrap_or(Path::new(""));
+    let dir = folder.join(relative_dir);
+    fs::create_dir_all(&dir)?;
+    let name = source.file_name().unwrap_or(source.as_os_str());
+    let target = free_path(&dir, Path::new(name));
+    // Only a move to another disk needs copying; any other failure is reported as it is.
+    match fs::rename(source, &target) {
+        Err(err) if err.kind() == std::io::ErrorKind::CrossesDevices => {
+            fs::copy(source, &target)?;
+            fs::remove_file(source)
+        }
+        moved => moved,
+    }
+}
+
+/// Replaces control characters, and the invisible marks that reorder text, with visible
+/// escapes: `\n`, `\t` and `\r`, or `\u{..}` for the rest. A name holding a newline would
+/// otherwise break its row, and one with a right-to-left override can pose as another file.
+fn escape_controls(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}' => {
+                escaped.push_str(&format!("\\u{{{:x}}}", c as u32))
+            }
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Cuts `text` down to about `max_chars` by replacing its middle with `…`, keeping the leading
+/// directories and the trailing file name, e.g. `/Users/me/…/project/main.rs`.
+/// Cuts snap to path separators when possible so directory names aren't split.
+fn shorten_middle(text: &str, max_chars: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max_chars {
+        return text.to_string();
+    }
+    let is_separator = |c: &char| *c == '/' || *c == std::path::MAIN_SEPARATOR;
+    // One character goes to the ellipsis; the tail gets the larger share since it holds the name.
+    let budget = max_chars.saturating_sub(1);
+    let head_len = budget / 3;
+    let head = &chars[..head_len];
+    let tail = &chars[chars.len() - (budget - head_len)..];
+    let head_end = head
+        .iter()
+        .rposition(is_separator)
+        .map(|i| i + 1)
+        .filter(|&end| end > 1)
+        .unwrap_or(head.len());
+    let tail_start = tail.iter().position(is_separator).unwrap_or(0);
+    let head: String = head[..head_end].iter().collect();
+    let tail: String = tail[tail_start..].iter().collect();
+    format!("{}…{}", head, tail)
+}
+
+/// A handle for background threads to report to the window that set them off, so each
+/// window's results land in its own state.
+#[derive(Clone)]
+struct WindowSink {
+    sink: ExtEventSink,
+    window: WindowId,
+}
+
+impl WindowSink {
+    fn submit_command<T: Any + Send>(
+        &self,
+        selector: Selector<T>,
+        payload: impl Into<Box<T>>,
+    ) -> Result<(), druid::ExtEventError> {
+        self.sink.submit_command(selector, payload, Target::Window(self.window))
+    }
+}
+
+/// Contexts that can hand out a `WindowSink` for the window they are in.
+trait WindowSinkCtx {
+    fn window_sink(&self) -> WindowSink;
+}
+
+impl WindowSinkCtx for EventCtx<'_, '_> {
+    fn window_sink(&self) -> WindowSink {
+        WindowSink { sink: self.get_external_handle(), window: self.window_id() }
+    }
+}
+
+impl WindowSinkCtx for UpdateCtx<'_, '_> {
+    fn window_sink(&self) -> WindowSink {
+        WindowSink { sink: self.get_external_handle(), window: self.window_id() }
+    }
+}
+
+impl WindowSinkCtx for LifeCycleCtx<'_, '_> {
+    fn window_sink(&self) -> WindowSink {
+        WindowSink { sink: self.get_external_handle(), window: self.window_id() }
+    }
+}
+
+/// What handling a command needs from where it was delivered: the delegate for the first
+/// window, `OwnWindowState` for the others.
+trait CommandCtx: WindowSinkCtx {
+    fn submit_command(&mut self, cmd: impl Into<Command>);
+    fn new_window(&mut self, desc: WindowDesc<AppState>);
+}
+
+impl CommandCtx for EventCtx<'_, '_> {
+    fn submit_command(&mut self, cmd: impl Into<Command>) {
+        EventCtx::submit_command(self, cmd)
+    }
+
+    fn new_window(&mut self, desc: WindowDesc<AppState>) {
+        EventCtx::new_window(self, desc)
+    }
+}
+
+/// The delegate's context along with the window a command was meant for.
+struct DelegateWindow<'a, 'b> {
+    ctx: &'a mut DelegateCtx<'b>,
+    window: WindowId,
+}
+
+impl WindowSinkCtx for DelegateWindow<'_, '_> {
+    fn window_sink(&self) -> WindowSink {
+        WindowSink { sink: self.ctx.get_external_handle(), window: self.window }
+    }
+}
+
+impl CommandCtx for DelegateWindow<'_, '_> {
+    fn submit_command(&mut self, cmd: impl Into<Command>) {
+        self.ctx.submit_command(cmd)
+    }
+
+    fn new_window(&mut self, desc: WindowDesc<AppState>) {
+        self.ctx.new_window(desc)
+    }
+}
+
+/// A delegate to handle the first window's commands, most of them coming from background
+/// threads. Those for other windows are left to their `OwnWindowState`.
+struct Delegate {
+    main_window: WindowId,
+}
+
+impl AppDelegate<AppState> for Delegate {
+    fn command(
+        &mut self,
+        ctx: &mut DelegateCtx,
+        target: Target,
+        cmd: &Command,
+        data: &mut AppState,
+        _env: &Env,
+    ) -> druid::Handled {
+        let window = match target {
+            Target::Window(window) if window != self.main_window => return druid::Handled::No,
+            Target::Window(window) => window,
+            _ => self.main_window,
+        };
+        Delegate::run_command(&mut DelegateWindow { ctx, window }, cmd, data)
+    }
+}
+
+impl Delegate {
+    /// Handles one command for the window whose state `data` is, logging any error it raises
+    /// to the activity log as well.
+    fn run_command(ctx: &mut impl CommandCtx, cmd: &Command, data: &mut AppState) -> druid::Handled {
+        let previous_error = data.error_message.clone();
+        let handled = Delegate::handle_command(ctx, cmd, data);
+        if data.error_message != previous_error && !data.error_message.is_empty() {
+            let error = data.error_message.clone();
+            data.log(format!("Error: {}", error));
+        }
+        handled
+    }
+
+    /// Handles one command; `run_command` wraps it to log any error it raises.
+    fn handle_command(ctx: &mut impl CommandCtx, cmd: &Command, data: &mut AppState) -> druid::Handled {
+        if let Some((generation, results)) = cmd.get(UPDATE_SEARCH_RESULTS) {
+            if *generation == data.search_generation {
+                if results.is_empty() && !data.browse_mode && data.widen_levels_left > 0 {
+                    let parent = Path::new(&data.root_path).parent().map(Path::to_path_buf);
+                    // The same search carries on, so a term remembered for the folder is unused.
+                    let term = data.search_term.clone();
+                    let widened = parent
+                        .filter(|parent| data.sandbox_allows(parent))
+                        .is_some_and(|parent| data.set_root(&parent));
+                    if widened {
+                        data.widen_levels_left -= 1;
+                        data.search_term = term;
+                        data.resume_watch_search = false;
+                        data.notice = format!("Expanded search to {}.", data.root_path);
+                        let options = data.search_options();
+                        spawn_search(ctx.window_sink(), data, options);
+                        return druid::Handled::Yes;
+                    }
+                }
+                data.widen_levels_left = 0;
+                data.searching = false;
+                data.search_outcome = SearchOutcome::Completed;
+                // Refreshes re-run a search behind the user's back, e.g. for watch mode.
+                let refreshed = data.diff_results;
+                if data.diff_results {
+                    data.diff_results = false;
+                    data.merge_refreshed_results(results);
+                } else if data.append_results && !data.browse_mode {
+                    data.add_results(results);
+                } else {
+                    data.set_results(results.clone());
+                }
+                if !data.browse_mode {
+                    let plural = if results.len() == 1 { "" } else { "s" };
+                    data.log(format!(
+                        "Searched {} for \"{}\": {} result{}",
+                        data.root_path,
+                        data.search_term,
+                        results.len(),
+                        plural
+                    ));
+                }
+                // A revealed entry may have been moved or deleted since it was found.
+                let highlighted = data.highlighted.clone();
+                if data.browse_mode
+                    && !highlighted.as_os_str().is_empty()
+                    && !data.search_results.iter().any(|r| r.is_at(&highlighted))
+                {
+                    data.error_message = format!(
+                        "{} is no longer in {}",
+                        highlighted.file_name().unwrap_or_default().to_string_lossy(),
+                        data.root_path
+                    );
+                    data.highlighted = PathBuf::new();
+                }
+                if data.refocus_search_box && !data.browse_mode && !refreshed {
+                    ctx.submit_command(FOCUS_SEARCH_BOX);
+                }
+                // The highlight is scrolled into view like any other. A refresh keeps whatever
+                // the user has highlighted since.
+                if data.auto_select_first && !data.browse_mode && !refreshed {
+                    if let Some(first) = data.visible_results.iter().find(|r| !r.is_removed()) {
+                        data.highlighted = first.path();
+                    }
+                }
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(&(generation, count, dirs)) = cmd.get(SEARCH_PROGRESS) {
+            if generation == data.search_generation && data.searching {
+                data.found_so_far = count;
+                data.dirs_scanned = dirs;
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some((key, dirs)) = cmd.get(WALK_SIZE) {
+            Arc::make_mut(&mut data.walk_sizes).insert(key.clone(), *dirs);
+            return druid::Handled::Yes;
+        }
+        if let Some(&count) = cmd.get(WALK_RETRIES) {
+            match count {
+                1 => data.log("Read 1 folder or file only after retrying"),
+                _ => data.log(format!("Read {} folders and files only after retrying", count)),
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some((generation, message)) = cmd.get(SEARCH_FAILED) {
+            if *generation == data.search_generation {
+                data.searching = false;
+                data.diff_results = false;
+                // Old results would look like the answer to the failed search.
+                data.search_outcome = SearchOutcome::Failed(message.clone());
+                data.set_results(Arc::new(Vec::new()));
+                data.log(format!("Search failed: {}", message));
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some((path, text, image)) = cmd.get(FILE_PREVIEW) {
+            // A preview that arrives after moving on to another row is dropped.
+            if *path == data.highlighted {
+                data.preview_path = path.clone();
+                data.preview_text = text.clone();
+                data.preview_image = image.clone();
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some((generation, statuses)) = cmd.get(GIT_STATUSES) {
+            if *generation == data.search_generation {
+                Arc::make_mut(&mut data.git_statuses).extend(statuses.iter().cloned());
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some((path, modified)) = cmd.get(WANT_CHECKSUM) {
+            let entry = (path.clone(), *modified);
+            Arc::make_mut(&mut data.checksum_queue).retain(|queued| *queued != entry);
+            Arc::make_mut(&mut data.checksum_queue).push(entry);
+            hash_next_file(ctx.window_sink(), data);
+            return druid::Handled::Yes;
+        }
+        if let Some((path, modified, hash)) = cmd.get(CHECKSUM_READY) {
+            if let Some(hash) = hash {
+                data.remember_checksum(path.clone(), *modified, hash.clone());
+            }
+            data.hashing_checksum = false;
+            if !data.show_checksums {
+                data.checksum_queue = Arc::new(Vec::new());
+            }
+            hash_next_file(ctx.window_sink(), data);
+            return druid::Handled::Yes;
+        }
+        if let Some(counts) = cmd.get(DIR_ITEM_COUNTS) {
+            Arc::make_mut(&mut data.dir_item_counts).extend(counts.iter().cloned());
+            return druid::Handled::Yes;
+        }
+        if let Some((path, entered)) = cmd.get(HOVER_PATH) {
+            if *entered {
+                data.hover_path = path.clone();
+            } else if data.hover_path == *path {
+                // Only clear it if the pointer hasn't already moved onto another row.
+                data.hover_path.clear();
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(progress) = cmd.get(OPEN_ALL_PROGRESS) {
+            data.open_progress = *progress;
+            return druid::Handled::Yes;
+        }
+        if let Some(dir) = cmd.get(SEARCH_IN) {
+            let step = SearchStep {
+                root: data.root_path.clone(),
+                term: data.search_term.clone(),
+            };
+            if !data.set_root(dir) {
+                return druid::Handled::Yes;
+            }
+            Arc::make_mut(&mut data.drill_stack).push(step);
+            if data.rerun_on_drill_down && !data.search_term.is_empty() {
+                start_search(ctx.window_sink(), data);
+            } else {
+                data.search_term.clear();
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(file) = cmd.get(FIND_RELATED) {
+            let (Some(dir), Some(stem)) = (file.parent(), file.file_stem()) else {
+                return druid::Handled::Yes;
+            };
+            let step = SearchStep {
+                root: data.root_path.clone(),
+                term: data.search_term.clone(),
+            };
+            if !data.set_root(dir) {
+                return druid::Handled::Yes;
+            }
+            Arc::make_mut(&mut data.drill_stack).push(step);
+            data.search_term = regex::escape(&stem.to_string_lossy());
+            start_search(ctx.window_sink(), data);
+            return druid::Handled::Yes;
+        }
+        if let Some(&index) = cmd.get(POP_SEARCH_STEP) {
+            let mut stack = data.drill_stack.to_vec();
+            stack.truncate(index + 1);
+            if let Some(step) = stack.pop() {
+                if !data.set_root(Path::new(&step.root)) {
+                    return druid::Handled::Yes;
+                }
+                data.drill_stack = Arc::new(stack);
+                // The step's own search wins over one remembered for watching.
+                data.search_term = step.term;
+                data.resume_watch_search = false;
+                if !data.search_term.is_empty() {
+                    start_search(ctx.window_sink(), data);
+                }
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(workspace) = cmd.get(LOAD_WORKSPACE) {
+            data.load_workspace(workspace);
+            return druid::Handled::Yes;
+        }
+        if let Some(preset) = cmd.get(LOAD_PRESET) {
+            data.load_preset(preset);
+            return druid::Handled::Yes;
+        }
+        if cmd.is(RUN_SEARCH) {
+            start_search(ctx.window_sink(), data);
+            return druid::Handled::Yes;
+        }
+        if cmd.is(REFRESH_SEARCH) {
+            // Results are replaced when the new ones arrive, so the list doesn't flash empty.
+            // The folders are the ones watched, whatever the folder box holds by now.
+            let Some((root, other_roots)) = data.searched_roots.split_first() else {
+                return druid::Handled::Yes;
+            };
+            let options = if data.browse_mode {
+                SearchOptions::listing(root)
+            } else {
+                SearchOptions {
+                    root: root.clone(),
+                    other_roots: other_roots.to_vec(),
+                    ..data.search_options()
+                }
+            };
+            spawn_search(ctx.window_sink(), data, options);
+            data.diff_results = true;
+            return druid::Handled::Yes;
+        }
+        if let Some(message) = cmd.get(REPORT_ERROR) {
+            data.error_message = message.clone();
+            return druid::Handled::Yes;
+        }
+        #[cfg(unix)]
+        if let Some(path) = cmd.get(FIND_HARD_LINKS) {
+            data.browse_mode = false;
+            data.highlighted = PathBuf::new();
+            data.set_results(Arc::new(Vec::new()));
+            spawn_find_hard_links(ctx.window_sink(), data, path.clone());
+            return druid::Handled::Yes;
+        }
+        #[cfg(unix)]
+        if let Some(path) = cmd.get(EJECT_DRIVE) {
+            let question = format!(
+                "Eject the drive mounted at {}? Files open on it should be closed first.",
+                path.display()
+            );
+            if !eject::is_removable(path) || !confirm("Eject drive", &question) {
+                return druid::Handled::Yes;
+            }
+            // Unmounting waits for pending writes, which can take a while.
+            let sink = ctx.window_sink();
+            let path = path.clone();
+            data.notice = format!("Ejecting {}…", path.display());
+            thread::spawn(move || {
+                let _ = match eject::eject(&path) {
+                    Ok(()) => sink.submit_command(DRIVE_EJECTED, path),
+                    Err(err) => {
+                        let message = format!("Couldn't eject {}: {}", path.display(), err);
+                        sink.submit_command(REPORT_ERROR, message)
+                    }
+                };
+            });
+            return druid::Handled::Yes;
+        }
+        #[cfg(unix)]
+        if let Some(path) = cmd.get(DRIVE_EJECTED) {
+            data.notice = format!("Ejected {}. It can be removed now.", path.display());
+            data.log(format!("Ejected {}", path.display()));
+            return druid::Handled::Yes;
+        }
+        if let Some(path) = cmd.get(OPEN_CONTAINER) {
+            match disk_image::Container::of(path).filter(|_| !path.is_dir()) {
+                Some(disk_image::Container::DiskImage) => {
+                    // Mounting can prompt or verify a checksum, so it runs off the UI thread.
+                    let sink = ctx.window_sink();
+                    let path = path.clone();
+                    data.notice = format!("Mounting {}…", path.display());
+                    thread::spawn(move || {
+                        let _ = match disk_image::mount(&path) {
+                            Ok(mount_point) => {
+                                sink.submit_command(DISK_IMAGE_MOUNTED, (path, mount_point))
+                            }
+                            Err(err) => {
+                                let message = format!("Couldn't mount {}: {}", path.display(), err);
+                                sink.submit_command(REPORT_ERROR, message)
+                            }
+                        };
+                    });
+                }
+                Some(disk_image::Container::Archive) => ctx.submit_command(OPEN_PATH.with(path.clone())),
+                None => browse_to(ctx.window_sink(), data, &path.clone(), PathBuf::new()),
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some((image, mount_point)) = cmd.get(DISK_IMAGE_MOUNTED) {
+            data.notice = format!("Mounted {} at {}.", image.display(), mount_point.display());
+            data.log(format!("Mounted {} at {}", image.display(), mount_point.display()));
+            browse_to(ctx.window_sink(), data, &mount_point.clone(), PathBuf::new());
+            return druid::Handled::Yes;
+        }
+        if let Some(path) = cmd.get(OPEN_PATH) {
+            open_path(&data.open_command, path);
+            data.record_opened(path);
+            data.log(format!("Opened {}", path.display()));
+            return druid::Handled::Yes;
+        }
+        if let Some((path, line)) = cmd.get(OPEN_AT_LINE) {
+            open_at_line(&data.editor_command, &data.open_command, path, *line);
+            data.record_opened(path);
+            data.log(format!("Opened {} at line {}", path.display(), line));
+            return druid::Handled::Yes;
+        }
+        if let Some(path) = cmd.get(ADD_BOOKMARK) {
+            let bookmark = path.to_string_lossy().to_string();
+            if !data.bookmarks.contains(&bookmark) {
+                Arc::make_mut(&mut data.bookmarks).push(bookmark);
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(bookmark) = cmd.get(REMOVE_BOOKMARK) {
+            Arc::make_mut(&mut data.bookmarks).retain(|b| b != bookmark);
+            return druid::Handled::Yes;
+        }
+        if let Some(bookmark) = cmd.get(OPEN_BOOKMARK) {
+            let path = Path::new(bookmark);
+            if path.is_dir() {
+                if data.set_root(path) {
+                    data.drill_stack = Arc::new(Vec::new());
+                }
+            } else if path.exists() {
+                open_path(&data.open_command, path);
+            } else {
+                data.error_message = format!("Bookmark {} no longer exists", bookmark);
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(kind) = cmd.get(CLEAR_FILTER) {
+            kind.clear(data);
+            if data.has_searched() {
+                start_search(ctx.window_sink(), data);
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(path) = cmd.get(TOGGLE_TAG) {
+            let tag = data.tag_name.trim().to_string();
+            if tag.is_empty() {
+                data.error_message = "Type a tag next to \"Only tagged\" first.".to_string();
+                return druid::Handled::Yes;
+            }
+            // The config file is JSON, which can only hold paths that are valid UTF-8.
+            if path.to_str().is_none() {
+                data.error_message =
+                    format!("Can't tag {}: its name isn't valid UTF-8.", path.display());
+                return druid::Handled::Yes;
+            }
+            let tags = Arc::make_mut(&mut data.tags);
+            let file_tags = tags.entry(path.clone()).or_default();
+            let added = match file_tags.iter().position(|t| *t == tag) {
+                Some(index) => {
+                    file_tags.remove(index);
+                    false
+                }
+                None => {
+                    file_tags.push(tag.clone());
+                    true
+                }
+            };
+            if file_tags.is_empty() {
+                tags.remove(path);
+            }
+            #[cfg(target_os = "macos")]
+            spawn_set_finder_tags(ctx.window_sink(), path.clone(), tag, added);
+            #[cfg(not(target_os = "macos"))]
+            let _ = added;
+            data.update_visible_results();
+            return druid::Handled::Yes;
+        }
+        if let Some(path) = cmd.get(TOGGLE_MARK) {
+            let marked = Arc::make_mut(&mut data.marked);
+            match marked.iter().position(|marked| marked == path) {
+                Some(index) => {
+                    marked.remove(index);
+                }
+                None => marked.push(path.clone()),
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(path) = cmd.get(RUN_COMMAND) {
+            let template = data.command_template.clone();
+            spawn_user_command(ctx.window_sink(), template, path.clone());
+            return druid::Handled::Yes;
+        }
+        if let Some(progress) = cmd.get(DUPLICATE_PROGRESS) {
+            data.duplicate_progress = Some(*progress);
+            return druid::Handled::Yes;
+        }
+        if let Some(dir) = cmd.get(COPY_LISTING) {
+            match dir_listing(dir, data.listing_sizes) {
+                Ok(listing) => {
+                    let count = listing.len();
+                    druid::Application::global().clipboard().put_string(listing.join("\n"));
+                    data.notice = format!("Copied {} entries of {}.", count, dir.display());
+                }
+                Err(err) => data.error_message = format!("Couldn't list {}: {}", dir.display(), err),
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(dir) = cmd.get(NEW_ITEM_IN) {
+            data.pending_create = Some(PendingCreate {
+                dir: dir.clone(),
+                name: String::new(),
+                folder: false,
+            });
+            return druid::Handled::Yes;
+        }
+        if let Some(dir) = cmd.get(OPEN_IN_NEW_WINDOW) {
+            let state = data.new_window_state(dir);
+            ctx.new_window(WindowDesc::new(OwnWindowState::new(state)).title("macOS File Explorer"));
+            return druid::Handled::Yes;
+        }
+        if let Some(path) = cmd.get(HASH_FILE) {
+            if data.hash_progress.is_none() {
+                data.hash_progress = Some((0, 100));
+                spawn_hash_file(ctx.window_sink(), path.clone());
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(progress) = cmd.get(HASH_PROGRESS) {
+            // A late progress report mustn't bring the row back after the result arrived.
+            if data.hash_progress.is_some() {
+                data.hash_progress = Some(*progress);
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some((path, digest)) = cmd.get(FILE_HASHED) {
+            data.hash_progress = None;
+            let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+            match digest {
+                Ok(digest) => {
+                    druid::Application::global().clipboard().put_string(digest);
+                    data.notice = format!("SHA-256 of {}: {} (copied)", name, digest);
+                }
+                Err(err) => data.error_message = format!("Couldn't hash {}: {}", name, err),
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(groups) = cmd.get(DUPLICATES_FOUND) {
+            data.duplicate_progress = None;
+            data.duplicate_groups = groups.clone();
+            data.show_duplicates = true;
+            return druid::Handled::Yes;
+        }
+        if let Some((folder, moved, failures)) = cmd.get(FILES_MOVED) {
+            for file in moved {
+                data.remove_result(file);
+                data.log(format!("Moved {} to {}", file.display(), folder.display()));
+            }
+            data.notice = format!("Moved {} files into {}.", moved.len(), folder.display());
+            if !failures.is_empty() {
+                data.error_message = format!("Couldn't move {}", failures.join("; "));
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some((folder, copied, failures)) = cmd.get(FILES_COPIED) {
+            data.log(format!("Copied {} files to {}", copied, folder.display()));
+            data.notice = format!("Copied {} files into {}.", copied, folder.display());
+            if !failures.is_empty() {
+                data.error_message = format!("Couldn't copy {}", failures.join("; "));
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(files) = cmd.get(TRASH_DUPLICATES) {
+            if let Some((_keep, rest)) = files.split_first() {
+                data.pending_batch = Some(PendingBatch {
+                    permanent: false,
+                    destination: None,
+                    structure_root: None,
+                    files: Arc::new(rest.to_vec()),
+                    group: Some(files.clone()),
+                });
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(path) = cmd.get(TRASH_PATH) {
+            match trash::delete(path) {
+                Ok(()) => {
+                    data.remove_result(path);
+                    data.log(format!("Moved {} to the trash", path.display()));
+                }
+                // Some paths (network shares, other volumes) can't be trashed.
+                Err(err) => {
+                    data.error_message =
+                        format!("Couldn't move {} to the trash: {}", path.display(), err);
+                }
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(path) = cmd.get(DELETE_PATH) {
+            let question = format!(
+                "Permanently delete {}?\n\nThis can't be undone; it won't go to the trash.",
+                path.display()
+            );
+            if confirm("Delete permanently", &question) {
+                match delete_permanently(path) {
+                    Ok(()) => {
+                        data.remove_result(path);
+                        data.log(format!("Deleted {}", path.display()));
+                    }
+                    Err(err) => {
+                        data.error_message = format!("Couldn't delete {}: {}", path.display(), err);
+                    }
+                }
+            }
+            return druid::Handled::Yes;
+        }
+        if let Some(path) = cmd.get(REVEAL_IN_FILE_MANAGER) {
+            spawn_reveal(ctx.window_sink(), path.clone());
+            return druid::Handled::Yes;
+        }
+        if let Some(path) = cmd.get(SELECT_PATH) {
+            data.highlighted = path.clone();
+            return druid::Handled::Yes;
+        }
+        if let Some(dir) = cmd.get(BROWSE_TO) {
+            browse_to(ctx.window_sink(), data, dir, PathBuf::new());
+            return druid::Handled::Yes;
+        }
+        if let Some(path) = cmd.get(REVEAL_IN_APP) {
+            if let Some(parent) = path.parent() {
+                browse_to(ctx.window_sink(), data, parent, path.clone());
+            }
+            return druid::Handled::Yes;
+        }
+        if cmd.is(commands::SHOW_OPEN_PANEL) {
+            let mut dialog = rfd::FileDialog::new();
+            if let Some(sandbox) = &data.sandbox_root {
+                dialog = dialog.set_directory(sandbox);
+            }
+            if let Some(folder) = dialog.pick_folder() {
+                if data.set_root(&folder) {
+                    data.drill_stack = Arc::new(Vec::new());
+                }
+                return druid::Handled::Yes;
+            }
+            // Removed file selection to force folder-only selection.
+        }
+        druid::Handled::No
+    }
+}
+
+/// The root of each window opened with "New window". It keeps the window's own `AppState`, so
+/// its searches, results and options are independent of the other windows', and handles the
+/// commands sent to the window the way the delegate does for the first one.
+struct OwnWindowState {
+    state: AppState,
+    root: WidgetPod<AppState, Box<dyn Widget<AppState>>>,
+}
+
+impl OwnWindowState {
+    fn new(state: AppState) -> Self {
+        OwnWindowState { state, root: WidgetPod::new(Box::new(build_ui())) }
+    }
+}
+
+// The app's state is the first window's, so it is ignored throughout.
+impl Widget<AppState> for OwnWindowState {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut AppState, env: &Env) {
+        let mut state = self.state.clone();
+        let handled = match event {
+            Event::Command(cmd) => Delegate::run_command(ctx, cmd, &mut state).is_handled(),
+            _ => false,
+        };
+        if handled {
+            ctx.set_handled();
+        } else {
+            self.root.event(ctx, event, &mut state, env);
+        }
+        if !state.same(&self.state) {
+            self.state = state;
+            ctx.request_update();
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &AppState, env: &Env) {
+        // Like the first window at launch, before anything is shown.
+        if matches!(event, LifeCycle::WidgetAdded) && self.state.list_on_start {
+            let dir = PathBuf::from(&self.state.root_path);
+            browse_to(ctx.window_sink(), &mut self.state, &dir, PathBuf::new());
+        }
+        self.root.lifecycle(ctx, event, &self.state, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &AppState, _data: &AppState, env: &Env) {
+        self.root.update(ctx, &self.state, env)
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &AppState, env: &Env) -> Size {
+        let size = self.root.layout(ctx, bc, &self.state, env);
+        self.root.set_origin(ctx, Point::ORIGIN);
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &AppState, env: &Env) {
+        self.root.paint(ctx, &self.state, env)
+    }
+}
+
+/// Runs one search without the UI and prints how many results it found and how long it took,
+/// for comparing the engine's speed between builds.
+fn bench(options: SearchOptions) {
+    let started = Instant::now();
+    match search::run(&options) {
+        Ok(results) => println!(
+            "{} results in {:.3}s",
+            results.len(),
+            started.elapsed().as_secs_f64()
+        ),
+        Err(err) => {
+            eprintln!("search failed: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    // `--serve [port]` runs the search engine behind a localhost HTTP endpoint instead of the UI.
+    // `--bench <root> <term>` times one search with the default options and prints the result.
+    // `--no-persist` anywhere leaves the config file alone, for shared machines.
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let no_persist = args.iter().any(|arg| arg == "--no-persist");
+    args.retain(|arg| arg != "--no-persist");
+    let mut args = args.into_iter();
+    let first_arg = args.next();
+    if first_arg.as_deref() == Some("--serve") {
+        let port = match args.next().map(|port| port.parse()) {
+            None => serve::DEFAULT_PORT,
+            Some(Ok(port)) => port,
+            Some(Err(err)) => {
+                eprintln!("invalid port: {}", err);
+                std::process::exit(2);
+            }
+        };
+        if let Err(err) = serve::run(port) {
+            eprintln!("couldn't serve on port {}: {}", port, err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if first_arg.as_deref() == Some("--bench") {
+        let (Some(root), Some(term)) = (args.next(), args.next()) else {
+            eprintln!("usage: --bench <root> <term>");
+            std::process::exit(2);
+        };
+        // Like the window and `--serve`, benchmarks stay inside the configured sandbox.
+        let options = SearchOptions {
+            sandbox_root: Config::load().sandbox_root,
+            ..SearchOptions::new(root, term)
+        };
+        bench(options);
+        return;
+    }
+
+    // Create the main window.
+    let main_window = WindowDesc::new(build_ui()).title("macOS File Explorer");
+    let main_window_id = main_window.id;
+
+    // Initialize the state with the current directory.
+    let root_path = std::env::current_dir()
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .display()
+        .to_string();
+    let mut initial_state = AppState::new(root_path);
+    initial_state.no_persist = no_persist;
+    // The sandbox is always read, so leaving the config alone can't be used to escape it.
+    let config = Config::load();
+    if no_persist {
+        initial_state.sandbox_root = config.sandbox_root;
+    } else {
+        initial_state.apply_config(config);
+    }
+    // A folder given on the command line wins over a restored one.
+    if let Some(dir) = first_arg {
+        if dir.starts_with('-') {
+            eprintln!("unknown option: {}", dir);
+            eprintln!("usage: [--no-persist] [folder] | --serve [port] | --bench <root> <term>");
+            std::process::exit(2);
+        }
+        if !Path::new(&dir).is_dir() {
+            eprintln!("not a folder: {}", dir);
+            std::process::exit(2);
+        }
+        initial_state.root_path = dir;
+    }
+    initial_state.clamp_to_sandbox();
+    initial_state.probe_creation_times();
+    let theme = initial_state.theme.clone();
+
+    let launcher = AppLauncher::with_window(main_window);
+    // The listing arrives through the delegate like any other, once the window is up.
+    if initial_state.list_on_start {
+        let dir = PathBuf::from(&initial_state.root_path);
+        let sink = WindowSink { sink: launcher.get_external_handle(), window: main_window_id };
+        browse_to(sink, &mut initial_state, &dir, PathBuf::new());
+    }
+
+    // Launch the application with the delegate to handle background commands.
+    launcher
+        .configure_env(move |env: &mut Env, _| theme.apply(env))
+        .delegate(Delegate { main_window: main_window_id })
         .launch(initial_state)
         .expect("Failed to launch application");
 }
@@ -0,0 +1,99 @@
+//! Telling a file's type from its first bytes rather than its extension, so mislabeled and
+//! extensionless files are still recognized.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+// Bytes read from the start of a file to tell its type; enough for every signature below.
+pub const SNIFF_BYTES: u64 = 512;
+
+/// The MIME type of the file at `path` from its first SNIFF_BYTES, see [`sniff`].
+pub fn sniff_file(path: &Path) -> io::Result<&'static str> {
+    let mut head = Vec::new();
+    File::open(path)?.take(SNIFF_BYTES).read_to_end(&mut head)?;
+    Ok(sniff(&head))
+}
+
+/// The MIME type `head`, the start of a file, looks like: a common binary format recognized by
+/// its signature, then markup and plain text if there are no NUL bytes, and
+/// `application/octet-stream` for anything else.
+pub fn sniff(head: &[u8]) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"II*\0", "image/tiff"),
+        (b"MM\0*", "image/tiff"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+        (b"Rar!\x1a\x07", "application/vnd.rar"),
+        (b"\xfd7zXZ\0", "application/x-xz"),
+        (b"ID3", "audio/mpeg"),
+        (b"OggS", "audio/ogg"),
+        (b"fLaC", "audio/flac"),
+        (b"\x1a\x45\xdf\xa3", "video/x-matroska"),
+        (b"\x7fELF", "application/x-elf"),
+        (b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+        (b"\xce\xfa\xed\xfe", "application/x-mach-binary"),
+        (b"MZ", "application/x-msdownload"),
+        (b"SQLite format 3\0", "application/vnd.sqlite3"),
+        (b"\0asm", "application/wasm"),
+    ];
+    if head.is_empty() {
+        return "application/x-empty";
+    }
+    if let Some(&(_, mime)) = SIGNATURES.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return mime;
+    }
+    // RIFF and ISO media files say what they hold after a length.
+    if head.starts_with(b"RIFF") && head.len() >= 12 {
+        match &head[8..12] {
+            b"WEBP" => return "image/webp",
+            b"WAVE" => return "audio/wav",
+            b"AVI " => return "video/x-msvideo",
+            _ => {}
+        }
+    }
+    if head.len() >= 12 && &head[4..8] == b"ftyp" {
+        return match &head[8..12] {
+            b"qt  " => "video/quicktime",
+            b"M4A " => "audio/mp4",
+            b"heic" | b"heix" | b"mif1" => "image/heic",
+            _ => "video/mp4",
+        };
+    }
+    // Two letters are too common a start for text to go on alone; the reserved bytes help.
+    if head.starts_with(b"BM") && head.len() >= 14 && head[6..10] == [0; 4] {
+        return "image/bmp";
+    }
+    if head.contains(&0) {
+        return "application/octet-stream";
+    }
+    let text = String::from_utf8_lossy(head);
+    let start = text.trim_start_matches('\u{feff}').trim_start().to_ascii_lowercase();
+    if start.starts_with("<svg") || (start.starts_with("<?xml") && start.contains("<svg")) {
+        "image/svg+xml"
+    } else if start.starts_with("<?xml") {
+        "application/xml"
+    } else if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        "text/html"
+    } else {
+        "text/plain"
+    }
+}
+
+/// Whether `mime` is one of `wanted`: a full type like `image/png`, or a family like `image`
+/// for every image type. Case is ignored.
+pub fn matches(mime: &str, wanted: &[String]) -> bool {
+    wanted.iter().any(|wanted| {
+        let wanted = wanted.trim();
+        mime.eq_ignore_ascii_case(wanted)
+            || mime
+                .split_once('/')
+                .is_some_and(|(family, _)| family.eq_ignore_ascii_case(wanted.trim_end_matches('/')))
+    })
+}
@@ -0,0 +1,198 @@
+use druid::widget::{Controller, Either, Flex, Label, List, Painter, SizedBox, TextBox};
+use druid::{
+    lens, Data, Env, Event, EventCtx, KbKey, Lens, LensExt, RenderContext, Selector,
+    Widget, WidgetExt,
+};
+use std::sync::Arc;
+
+use crate::{theme, AppState};
+
+// Moves keyboard focus into the palette's text box once it is showing.
+const FOCUS_PALETTE: Selector<()> = Selector::new("palette.focus");
+
+// Runs the action at this index in the palette's action list.
+const RUN_ACTION: Selector<usize> = Selector::new("palette.run_action");
+
+/// Whether the command palette is open, what has been typed into it, and which match is picked.
+#[derive(Clone, Data, Default, Lens)]
+pub struct PaletteState {
+    pub open: bool,
+    pub query: String,
+    // Index into the current matches, not into the action list.
+    pub selected: usize,
+}
+
+/// One entry of the palette.
+#[derive(Clone, Copy)]
+pub struct PaletteAction {
+    pub name: &'static str,
+    pub run: fn(&mut EventCtx, &mut AppState),
+}
+
+/// A match as shown in the list: its name, where it is in the action list, and if it is picked.
+type PaletteRow = (String, usize, bool);
+
+/// Opens the palette with an empty query.
+pub fn open(ctx: &mut EventCtx, data: &mut AppState) {
+    data.palette = PaletteState {
+        open: true,
+        ..PaletteState::default()
+    };
+    ctx.submit_command(FOCUS_PALETTE);
+}
+
+/// Scores `name` against `query` as a case-insensitive subsequence: every query character has
+/// to appear in order. Lower is better; gaps between the matched characters cost more.
+/// None if `name` doesn't match.
+fn fuzzy_score(query: &str, name: &str) -> Option<usize> {
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let offset = name[position..].iter().position(|&c| c == wanted)?;
+        score += offset;
+        position += offset + 1;
+    }
+    Some(score)
+}
+
+/// Indices of the actions matching `query`, best first; everything, in order, when it's empty.
+fn matches(actions: &[PaletteAction], query: &str) -> Vec<usize> {
+    let mut scored: Vec<(usize, usize)> = actions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, action)| Some((fuzzy_score(query, action.name)?, index)))
+        .collect();
+    scored.sort();
+    scored.into_iter().map(|(_, index)| index).collect()
+}
+
+/// The palette overlay: a filter box above the matching actions. Up/Down pick a match,
+/// Enter runs it, and Escape closes the palette.
+pub fn build_palette(actions: Vec<PaletteAction>) -> impl Widget<AppState> {
+    let rows_actions = actions.clone();
+    let rows = List::new(|| {
+        Label::new(|(name, _, _): &PaletteRow, _env: &_| name.clone())
+            .padding((8.0, 4.0))
+            .expand_width()
+            .background(Painter::new(|ctx, (_, _, selected): &PaletteRow, env| {
+                if *selected {
+                    let rect = ctx.size().to_rect();
+                    ctx.fill(rect, &env.get(theme::ACCENT));
+                }
+            }))
+            .on_click(|ctx, (_, index, _): &mut PaletteRow, _env| {
+                ctx.submit_command(RUN_ACTION.with(*index));
+            })
+    })
+    .lens(lens::Map::new(
+        move |data: &AppState| {
+            let rows: Vec<PaletteRow> = matches(&rows_actions, &data.palette.query)
+                .into_iter()
+                .enumerate()
+                .map(|(i, index)| {
+                    (rows_actions[index].name.to_string(), index, i == data.palette.selected)
+                })
+                .collect();
+            Arc::new(rows)
+        },
+        // Rows are rebuilt from the query; clicks go through RUN_ACTION instead.
+        |_data: &mut AppState, _rows: Arc<Vec<PaletteRow>>| {},
+    ));
+
+    let query_box = TextBox::new()
+        .with_placeholder("Type a command…")
+        .with_text_color(theme::TEXT)
+        .expand_width()
+        .controller(FocusOnOpen)
+        .lens(AppState::palette.then(PaletteState::query));
+
+    let panel = Flex::column()
+        .with_child(query_box)
+        .with_child(rows)
+        .padding(8.0)
+        .background(theme::SURFACE)
+        .border(theme::ACCENT, 1.0)
+        .rounded(4.0)
+        .padding(4.0)
+        .controller(PaletteKeys { actions });
+
+    Either::new(|data: &AppState, _env| data.palette.open, panel, SizedBox::empty())
+}
+
+/// Focuses the text box it wraps when the palette opens.
+struct FocusOnOpen;
+
+impl<W: Widget<String>> Controller<String, W> for FocusOnOpen {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut String, env: &Env) {
+        if let Event::Command(cmd) = event {
+            if cmd.is(FOCUS_PALETTE) {
+                ctx.request_focus();
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+/// Keyboard handling for the open palette, and running the picked action.
+struct PaletteKeys {
+    actions: Vec<PaletteAction>,
+}
+
+impl PaletteKeys {
+    fn run(&self, ctx: &mut EventCtx, data: &mut AppState, index: usize) {
+        data.palette.open = false;
+        if let Some(action) = self.actions.get(index) {
+            (action.run)(ctx, data);
+        }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for PaletteKeys {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        match event {
+            Event::Command(cmd) if cmd.is(RUN_ACTION) => {
+                let index = *cmd.get_unchecked(RUN_ACTION);
+                self.run(ctx, data, index);
+                ctx.set_handled();
+                return;
+            }
+            Event::KeyDown(key) => {
+                let found = matches(&self.actions, &data.palette.query);
+                let handled = match &key.key {
+                    KbKey::Escape => {
+                        data.palette.open = false;
+                        true
+                    }
+                    KbKey::ArrowDown => {
+                        let last = found.len().saturating_sub(1);
+                        data.palette.selected = (data.palette.selected + 1).min(last);
+                        true
+                    }
+                    KbKey::ArrowUp => {
+                        data.palette.selected = data.palette.selected.saturating_sub(1);
+                        true
+                    }
+                    KbKey::Enter => {
+                        if let Some(&index) = found.get(data.palette.selected) {
+                            self.run(ctx, data, index);
+                        }
+                        true
+                    }
+                    _ => false,
+                };
+                if handled {
+                    ctx.set_handled();
+                    return;
+                }
+            }
+            _ => {}
+        }
+        let old_query = data.palette.query.clone();
+        child.event(ctx, event, data, env);
+        // A new query means a new list of matches, so start again from the best one.
+        if data.palette.query != old_query {
+            data.palette.selected = 0;
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use druid::widget::prelude::*;
+use druid::{Point, WidgetPod};
+
+/// A full-height column of three parts: a header that always gets its natural height, a band
+/// of options allowed at most `max_share` of the height left over, and a body that fills the
+/// rest.
+///
+/// Wrap the band in a `Scroll` so it scrolls once it is capped. However many options are
+/// added, the header stays in view and the body keeps the remaining space, which a plain
+/// `Flex` column can't promise: its fixed children push flex ones out of the window.
+pub struct Pinned<T> {
+    header: WidgetPod<T, Box<dyn Widget<T>>>,
+    band: WidgetPod<T, Box<dyn Widget<T>>>,
+    body: WidgetPod<T, Box<dyn Widget<T>>>,
+    max_share: f64,
+}
+
+impl<T: Data> Pinned<T> {
+    pub fn new(
+        header: impl Widget<T> + 'static,
+        band: impl Widget<T> + 'static,
+        body: impl Widget<T> + 'static,
+        max_share: f64,
+    ) -> Self {
+        Pinned {
+            header: WidgetPod::new(Box::new(header)),
+            band: WidgetPod::new(Box::new(band)),
+            body: WidgetPod::new(Box::new(body)),
+            max_share,
+        }
+    }
+
+    fn parts(&mut self) -> [&mut WidgetPod<T, Box<dyn Widget<T>>>; 3] {
+        [&mut self.header, &mut self.band, &mut self.body]
+    }
+}
+
+impl<T: Data> Widget<T> for Pinned<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        for part in self.parts() {
+            part.event(ctx, event, data, env);
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        for part in self.parts() {
+            part.lifecycle(ctx, event, data, env);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        for part in self.parts() {
+            part.update(ctx, data, env);
+        }
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        let width = bc.max().width;
+        let height = bc.max().height;
+        let column = |max_height: f64| {
+            BoxConstraints::new(Size::new(width, 0.0), Size::new(width, max_height.max(0.0)))
+        };
+        let header = self.header.layout(ctx, &column(f64::INFINITY), data, env).height;
+        let left = (height - header).max(0.0);
+        let band = self.band.layout(ctx, &column(left * self.max_share), data, env).height;
+        let body_height = (left - band).max(0.0);
+        let body_bc = BoxConstraints::tight(Size::new(width, body_height));
+        self.body.layout(ctx, &body_bc, data, env);
+
+        self.header.set_origin(ctx, Point::ORIGIN);
+        self.band.set_origin(ctx, Point::new(0.0, header));
+        self.body.set_origin(ctx, Point::new(0.0, header + band));
+        bc.constrain(Size::new(width, height))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        for part in self.parts() {
+            part.paint(ctx, data, env);
+        }
+    }
+}
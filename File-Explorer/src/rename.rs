@@ -0,0 +1,160 @@
+//! Working out the new names for a batch rename and checking them before anything on disk is
+//! touched.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Each of `files` with the path it would be renamed to, in order; unchanged names map to
+/// themselves. `find` is replaced by `replace` every time it appears in a name, unless it is
+/// empty, and with `number` each name gets `_1`, `_2`, … before its extension, padded to the
+/// same width. Names that aren't valid UTF-8 can't be edited as text, so they are refused.
+pub fn plan(
+    files: &[PathBuf],
+    find: &str,
+    replace: &str,
+    number: bool,
+) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let width = files.len().to_string().len();
+    files
+        .iter()
+        .enumerate()
+        .map(|(index, path)| {
+            let name = path.file_name().unwrap_or_default();
+            let Some(name) = name.to_str() else {
+                return Err(format!("{} has a name that isn't valid UTF-8", path.display()));
+            };
+            let mut name = if find.is_empty() { name.to_string() } else { name.replace(find, replace) };
+            if number {
+                let suffix = format!("_{:0width$}", index + 1);
+                // Dotfiles such as `.env` have no extension to keep after the number.
+                match name.rfind('.').filter(|&dot| dot > 0) {
+                    Some(dot) => name.insert_str(dot, &suffix),
+                    None => name.push_str(&suffix),
+                }
+            }
+            Ok((path.clone(), path.with_file_name(name)))
+        })
+        .collect()
+}
+
+/// Why `plan` can't be carried out, if it can't: a name that isn't valid, two entries ending
+/// up with the same path, or a path that is already taken by another entry.
+pub fn problem(plan: &[(PathBuf, PathBuf)]) -> Option<String> {
+    let mut targets = HashSet::new();
+    for (source, target) in plan {
+        // An empty name, `..` or one with a separator would leave the folder.
+        if target.file_name().is_none() || target.parent() != source.parent() {
+            return Some(format!("{} would get a name that isn't valid", source.display()));
+        }
+        if !targets.insert(target) {
+            return Some(format!("More than one entry would be named {}", target.display()));
+        }
+        if is_taken(source, target) {
+            return Some(format!("{} already exists", target.display()));
+        }
+    }
+    None
+}
+
+/// Whether renaming `source` to `target` would replace another entry. A target that is
+/// `source` itself under another case, as on case-insensitive filesystems, isn't taken.
+pub fn is_taken(source: &Path, target: &Path) -> bool {
+    target != source && target.exists() && !same_file(source, target)
+}
+
+/// Whether both paths lead to the same file, by device and inode.
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::symlink_metadata(a), fs::symlink_metadata(b)) {
+        (Ok(a), Ok(b)) => (a.dev(), a.ino()) == (b.dev(), b.ino()),
+        _ => false,
+    }
+}
+
+/// Whether both paths lead to the same file, by where they resolve to.
+#[cfg(not(unix))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(plan: &[(PathBuf, PathBuf)]) -> Vec<String> {
+        plan.iter()
+            .map(|(_, target)| target.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn numbers_go_before_the_extension_padded_to_one_width() {
+        let files: Vec<PathBuf> =
+            (0..10).map(|i| PathBuf::from(format!("/photos/img{}.jpg", i))).collect();
+        let plan = plan(&files, "img", "beach", true).unwrap();
+        let names = names(&plan);
+        assert_eq!(names[0], "beach0_01.jpg");
+        assert_eq!(names[9], "beach9_10.jpg");
+        assert!(plan.iter().all(|(source, target)| source.parent() == target.parent()));
+    }
+
+    #[test]
+    fn dotfiles_and_extensionless_names_get_the_number_at_the_end() {
+        let files = [PathBuf::from("/p/.env"), PathBuf::from("/p/Makefile")];
+        let plan = plan(&files, "", "", true).unwrap();
+        assert_eq!(names(&plan), [".env_1", "Makefile_2"]);
+    }
+
+    #[test]
+    fn an_empty_find_leaves_names_alone() {
+        let files = [PathBuf::from("/p/a.txt")];
+        let plan = plan(&files, "", "x", false).unwrap();
+        assert_eq!(plan[0].0, plan[0].1);
+        assert_eq!(problem(&plan), None);
+    }
+
+    #[test]
+    fn separators_and_empty_names_are_refused() {
+        let files = [PathBuf::from("/p/a.txt")];
+        let plan_with = |replace| plan(&files, "a.txt", replace, false).unwrap();
+        assert!(problem(&plan_with("sub/a.txt")).unwrap().contains("isn't valid"));
+        assert!(problem(&plan_with("")).unwrap().contains("isn't valid"));
+        assert!(problem(&plan_with("..")).unwrap().contains("isn't valid"));
+    }
+
+    #[test]
+    fn two_entries_with_one_new_name_collide() {
+        let files = [PathBuf::from("/p/a-1.txt"), PathBuf::from("/p/a-2.txt")];
+        let plan = plan(&files, "-1", "-2", false).unwrap();
+        assert!(problem(&plan).unwrap().contains("More than one entry"));
+    }
+
+    #[test]
+    fn existing_files_are_taken_but_the_entry_itself_is_not() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+        let plan = plan(std::slice::from_ref(&a), "a", "b", false).unwrap();
+        assert!(problem(&plan).unwrap().contains("already exists"));
+
+        // On case-insensitive filesystems `A.txt` exists already, as `a.txt` itself.
+        let plan = super::plan(&[a], "a", "A", false).unwrap();
+        assert_eq!(problem(&plan), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn names_that_are_not_utf8_are_refused() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        let file = Path::new("/p").join(OsStr::from_bytes(b"caf\xe9.txt"));
+        assert!(plan(&[file], "caf", "cafe", false).unwrap_err().contains("valid UTF-8"));
+    }
+}
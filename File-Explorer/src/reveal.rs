@@ -0,0 +1,95 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// What `reveal_in_file_manager` managed to show.
+pub enum Revealed {
+    // The entry, selected in its folder where the platform allows it.
+    Entry,
+    // The entry no longer exists, so the nearest folder above it that does was opened instead.
+    Ancestor(PathBuf),
+}
+
+/// Shows `path` in the platform's file manager with the entry selected: `open -R` on macOS,
+/// `explorer /select,` on Windows, and elsewhere the freedesktop `ShowItems` call over D-Bus,
+/// falling back to opening the parent folder with `xdg-open` when no file manager answers it.
+pub fn reveal_in_file_manager(path: &Path) -> io::Result<Revealed> {
+    if path.symlink_metadata().is_err() {
+        let ancestor = path
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.is_dir())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no part of the path exists"))?;
+        open_folder(ancestor)?;
+        return Ok(Revealed::Ancestor(ancestor.to_path_buf()));
+    }
+    select_entry(path)?;
+    Ok(Revealed::Entry)
+}
+
+#[cfg(target_os = "macos")]
+fn select_entry(path: &Path) -> io::Result<()> {
+    Command::new("open").arg("-R").arg(path).spawn().map(|_| ())
+}
+
+#[cfg(windows)]
+fn select_entry(path: &Path) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    // Explorer parses its own command line and wants `/select,"C:\a b"` as one argument, which
+    // the usual argument quoting would wrap in quotes of its own. Its exit code means nothing.
+    Command::new("explorer")
+        .raw_arg(format!("/select,\"{}\"", path.display()))
+        .spawn()
+        .map(|_| ())
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn select_entry(path: &Path) -> io::Result<()> {
+    let path = path.canonicalize()?;
+    let shown = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", file_uri(&path)))
+        .arg("string:")
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if shown {
+        return Ok(());
+    }
+    open_folder(path.parent().unwrap_or(&path))
+}
+
+/// Opens a folder itself in the file manager.
+fn open_folder(dir: &Path) -> io::Result<()> {
+    let program = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    Command::new(program).arg(dir).spawn().map(|_| ())
+}
+
+/// A `file://` URI for an absolute path, with every byte outside the unreserved set
+/// percent-encoded, so spaces, `#` and non-UTF-8 names survive the trip.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn file_uri(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
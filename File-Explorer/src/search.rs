@@ -0,0 +1,2166 @@
+use regex::{RegexSet, RegexSetBuilder};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use unicode_normalization::UnicodeNormalization;
+use walkdir::WalkDir;
+
+use crate::mime;
+
+// Longest matching line kept for a content-search preview, in characters.
+pub const MATCH_LINE_MAX_CHARS: usize = 160;
+
+// How much of each file content search reads by default, so huge files can't exhaust memory.
+pub const DEFAULT_CONTENT_MAX_BYTES: u64 = 5 * 1024 * 1024;
+
+// Pause before each directory is read in `low_impact` mode, and before each file is scanned.
+const LOW_IMPACT_PAUSE: Duration = Duration::from_millis(5);
+
+// Wait before the first retry of a folder that couldn't be read; each further one waits longer.
+const READ_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+// How much of a file is checked for NUL bytes when deciding whether it is binary.
+const BINARY_CHECK_BYTES: usize = 8 * 1024;
+
+/// What the search terms are matched against.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchMode {
+    /// The entry's file name.
+    Name,
+    /// The whole path, with separators normalized to `/`.
+    FullPath,
+    /// Each line of a file's contents; directories never match.
+    Contents,
+    /// Either the file name or a line of the contents, so directories match by name alone.
+    NameOrContents,
+    /// Both the file name and a line of the contents; directories never match.
+    NameAndContents,
+    /// The file name, allowing typos: a term within `max_edit_distance` edits of some part of
+    /// the name matches. Terms are plain text here, and results come closest first.
+    Approximate,
+}
+
+impl SearchMode {
+    /// Whether file contents are read, which makes results carry their matching lines.
+    pub fn scans_contents(self) -> bool {
+        matches!(
+            self,
+            SearchMode::Contents | SearchMode::NameOrContents | SearchMode::NameAndContents
+        )
+    }
+}
+
+/// Everything a search needs. Build one with [`SearchOptions::new`] and adjust the fields.
+#[derive(Clone, Debug)]
+pub struct SearchOptions {
+    pub root: PathBuf,
+    // Further folders searched with the same options; their results follow `root`'s, in order.
+    pub other_roots: Vec<PathBuf>,
+    pub term: String,
+    // Further terms OR-ed with `term`: an entry matches if any of them matches.
+    pub or_terms: Vec<String>,
+    pub case_sensitive: bool,
+    // Insertions, deletions and substitutions allowed in `SearchMode::Approximate`.
+    pub max_edit_distance: usize,
+    // Ignore spaces before and after each term, which pasted text often carries. Spaces inside
+    // a term are kept.
+    pub trim_terms: bool,
+    // Split each term on whitespace and require every word to match, in any order, so
+    // `main config` finds `config_main.rs`. Each word is still a pattern of its own.
+    pub all_words: bool,
+    // Match names without their extension, so `txt` doesn't find `notes.txt`. Applies wherever
+    // the file name is matched.
+    pub stem_only: bool,
+    // In a case-insensitive search, list matches with the exact typed casing first.
+    pub exact_case_first: bool,
+    // Compare names, paths and terms in Unicode NFC form, so a name stored decomposed (as
+    // macOS does) matches the same text typed precomposed.
+    pub normalize_unicode: bool,
+    // In a case-insensitive search, also fold the letters the regex's simple case folding
+    // misses: German `ß` matches `ss`, Turkish dotted `İ` and dotless `ı` match `i`, and
+    // ligatures like `ﬁ` match their letters.
+    pub full_case_folding: bool,
+    pub mode: SearchMode,
+    // Only files with one of these extensions (without the dot, any case) match; empty for all.
+    pub extensions: Vec<String>,
+    // Only files whose contents look like one of these MIME types match, e.g. `image` or
+    // `application/pdf`; empty for all, see [`mime::matches`]. Each file's start is read to
+    // tell, so this is slower than `extensions`, and folders never match.
+    pub mime_types: Vec<String>,
+    // Directories with one of these names are not descended into.
+    pub exclude_dirs: Vec<String>,
+    // Include hidden entries: names starting with a dot, and on Windows anything with the
+    // hidden attribute. Hidden folders are skipped whole when this is off.
+    pub show_hidden: bool,
+    // Directories not descended into either: absolute paths match exactly and bare names (like
+    // `$Recycle.Bin`) at any depth. The root itself is always searched. Defaults to
+    // `default_exclude_paths()`.
+    pub exclude_paths: Vec<PathBuf>,
+    // Don't descend into directories on another filesystem than the root, like `find -xdev`.
+    // Only supported on Unix; ignored elsewhere.
+    pub stay_on_filesystem: bool,
+    // How many levels below the root to look; 1 means its direct entries only. None is unlimited.
+    // Symlinked directories are never descended into, so the depth is always the number of
+    // real directories between the root and an entry, and a link counts as an entry at its level.
+    pub max_depth: Option<usize>,
+    // Only entries modified after this time match; None for any time.
+    pub modified_after: Option<SystemTime>,
+    // Only entries last modified before this time match, for finding stale files; None for any
+    // time. Folders are judged by their own modification time like files.
+    pub modified_before: Option<SystemTime>,
+    // Only entries created at or after `created_after` and before `created_before` match; None
+    // for no bound. Where creation times can't be read, as on some Linux filesystems, nothing
+    // passes while either is set.
+    pub created_after: Option<SystemTime>,
+    pub created_before: Option<SystemTime>,
+    // Only files of at least `min_size` and at most `max_size` bytes match; None for no bound.
+    // Folders never match while either is set.
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub permissions: PermissionFilter,
+    // Resolve symlink results to their real targets.
+    pub resolve_links: bool,
+    // Let a symlink match by its target's file name as well as its own, wherever names are
+    // matched. A broken link's target is named all the same, so it can still match.
+    pub match_link_targets: bool,
+    // Only symlinks whose target doesn't exist match; they come back with `LinkTarget::Broken`.
+    pub broken_links_only: bool,
+    // Only zero-byte files and folders with nothing in them match, for cleaning them up. The
+    // terms still filter by name; an empty term takes every empty entry.
+    pub empty_only: bool,
+    // Leave out files that look binary (a NUL byte near the start) from content search, like grep.
+    pub skip_binary: bool,
+    // Content search stops reading a file after this many bytes; None reads whole files.
+    pub content_max_bytes: Option<u64>,
+    // Worker threads used to scan file contents.
+    pub thread_count: usize,
+    // Go easy on the disk for busy machines and laptops: pause briefly before reading each
+    // directory and before scanning each file, on a single content worker whatever
+    // `thread_count` says. Searches take a good deal longer.
+    pub low_impact: bool,
+    // Try listing a folder, or reading an entry's metadata, that failed this many more times,
+    // waiting a little longer each time, before skipping it. Network drives sometimes fail a
+    // read that works a moment later; missing entries and denied permissions are never retried.
+    pub read_retries: usize,
+    // Keep just this many of the biggest matching files, biggest first; folders are left out.
+    // None keeps every match.
+    pub largest_files: Option<usize>,
+    // Refuse to search any root outside this folder, see [`sandbox_allows`]. The walk never
+    // follows symlinked folders, so nothing below an allowed root leads out of it.
+    pub sandbox_root: Option<PathBuf>,
+}
+
+impl SearchOptions {
+    /// A case-insensitive name search for `term` under `root`, with no filters or depth limit
+    /// apart from skipping the platform's system folders.
+    /// Content search skips binary files and reads at most [`DEFAULT_CONTENT_MAX_BYTES`] of each.
+    pub fn new(root: impl Into<PathBuf>, term: impl Into<String>) -> Self {
+        SearchOptions {
+            root: root.into(),
+            other_roots: Vec::new(),
+            term: term.into(),
+            or_terms: Vec::new(),
+            case_sensitive: false,
+            all_words: false,
+            stem_only: false,
+            trim_terms: true,
+            max_edit_distance: 2,
+            exact_case_first: false,
+            normalize_unicode: cfg!(target_os = "macos"),
+            full_case_folding: false,
+            mode: SearchMode::Name,
+            extensions: Vec::new(),
+            mime_types: Vec::new(),
+            exclude_dirs: Vec::new(),
+            show_hidden: true,
+            exclude_paths: default_exclude_paths(),
+            stay_on_filesystem: false,
+            max_depth: None,
+            modified_after: None,
+            modified_before: None,
+            created_after: None,
+            created_before: None,
+            min_size: None,
+            max_size: None,
+            permissions: PermissionFilter::Any,
+            resolve_links: false,
+            match_link_targets: false,
+            broken_links_only: false,
+            empty_only: false,
+            skip_binary: true,
+            content_max_bytes: Some(DEFAULT_CONTENT_MAX_BYTES),
+            thread_count: 1,
+            low_impact: false,
+            read_retries: 2,
+            largest_files: None,
+            sandbox_root: None,
+        }
+    }
+
+    /// Options that list every entry of `dir`: an empty name search matches everything.
+    pub fn listing(dir: &Path) -> Self {
+        SearchOptions {
+            max_depth: Some(1),
+            ..SearchOptions::new(dir, "")
+        }
+    }
+
+    /// Takes the words `size>N`, `size<N`, `modified>DATE` and `modified<DATE` out of `term`
+    /// and applies them as filters, tightening any bounds already set, so one box can hold a
+    /// whole query like `report size>10MB modified<2024-01-01`. Sizes take a unit of B, KB, MB,
+    /// GB or TB (powers of 1000, as shown in the results) and dates are `YYYY-MM-DD`. Words
+    /// that don't parse as one of these are left in the term as plain text, and the rest of
+    /// the term is kept as typed: each word taken goes with the spaces after it, or before it
+    /// at the end.
+    pub fn apply_term_filters(&mut self) {
+        let term = std::mem::take(&mut self.term);
+        let mut kept = String::with_capacity(term.len());
+        let mut copied = 0;
+        for word in term.split_whitespace() {
+            let applied = word
+                .split_once(['>', '<'])
+                .is_some_and(|(field, value)| {
+                    let more = word.as_bytes()[field.len()] == b'>';
+                    self.apply_term_filter(&field.to_lowercase(), more, value)
+                });
+            if !applied {
+                continue;
+            }
+            let start = word.as_ptr() as usize - term.as_ptr() as usize;
+            kept.push_str(&term[copied..start]);
+            let after = &term[start + word.len()..];
+            copied = term.len() - after.trim_start().len();
+            if copied == term.len() {
+                kept.truncate(kept.trim_end().len());
+            }
+        }
+        kept.push_str(&term[copied..]);
+        self.term = kept;
+    }
+
+    /// Applies one `field>value` (`more`) or `field<value` word; false if it isn't one.
+    fn apply_term_filter(&mut self, field: &str, more: bool, value: &str) -> bool {
+        match field {
+            "size" => {
+                let Some(bytes) = parse_size(value) else {
+                    return false;
+                };
+                // A bound no size can meet, like `size<0`, is left as text like one that
+                // doesn't parse.
+                if more {
+                    let Some(min) = bytes.checked_add(1) else {
+                        return false;
+                    };
+                    self.min_size = Some(self.min_size.map_or(min, |old| old.max(min)));
+                } else {
+                    let Some(max) = bytes.checked_sub(1) else {
+                        return false;
+                    };
+                    self.max_size = Some(self.max_size.map_or(max, |old| old.min(max)));
+                }
+            }
+            "modified" => {
+                let Some(date) = parse_date(value) else {
+                    return false;
+                };
+                if more {
+                    self.modified_after = Some(self.modified_after.map_or(date, |old| old.max(date)));
+                } else {
+                    self.modified_before =
+                        Some(self.modified_before.map_or(date, |old| old.min(date)));
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Every non-empty term to match, trimmed with `trim_terms`; an empty list of terms
+    /// matches everything.
+    pub fn patterns(&self) -> Vec<&str> {
+        let terms: Vec<&str> = std::iter::once(&self.term)
+            .chain(&self.or_terms)
+            .map(|term| if self.trim_terms { term.trim() } else { term.as_str() })
+            .filter(|term| !term.is_empty())
+            .collect();
+        if terms.is_empty() {
+            vec![""]
+        } else {
+            terms
+        }
+    }
+
+    /// The part of `path`'s file name that is matched: the stem with `stem_only`, else the
+    /// whole name. Converted lossily so non-UTF-8 names aren't dropped.
+    fn matched_name<'a>(&self, path: &'a Path) -> Cow<'a, str> {
+        let name = if self.stem_only {
+            path.file_stem()
+        } else {
+            path.file_name()
+        };
+        name.unwrap_or_default().to_string_lossy()
+    }
+
+    fn is_excluded_path(&self, path: &Path) -> bool {
+        self.exclude_paths.iter().any(|excluded| {
+            let candidate = if excluded.is_absolute() {
+                path.as_os_str()
+            } else {
+                path.file_name().unwrap_or_default()
+            };
+            // Windows paths are case-insensitive.
+            if cfg!(windows) {
+                candidate.eq_ignore_ascii_case(excluded)
+            } else {
+                candidate == excluded.as_os_str()
+            }
+        })
+    }
+
+    /// `text` in NFC form when `normalize_unicode` is on, and with `full_case_folding`'s
+    /// letters folded when that applies; else unchanged.
+    fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let text = if self.normalize_unicode && !unicode_normalization::is_nfc(text) {
+            Cow::Owned(text.nfc().collect())
+        } else {
+            Cow::Borrowed(text)
+        };
+        if self.full_case_folding && !self.case_sensitive && !text.is_ascii() {
+            Cow::Owned(fold_special_cases(&text))
+        } else {
+            text
+        }
+    }
+
+    /// `text` lowercased unless the search is case-sensitive.
+    fn fold_case<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.case_sensitive {
+            Cow::Borrowed(text)
+        } else {
+            Cow::Owned(text.to_lowercase())
+        }
+    }
+
+    /// Entries whose modification time can't be read never pass a date filter.
+    fn modified_allowed(&self, path: &Path) -> bool {
+        if self.modified_after.is_none() && self.modified_before.is_none() {
+            return true;
+        }
+        self.modified_within(fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+    }
+
+    /// Whether an entry modified at `modified` passes `modified_after` and `modified_before`;
+    /// an unknown time only passes without them.
+    pub fn modified_within(&self, modified: Option<SystemTime>) -> bool {
+        match modified {
+            Some(modified) => {
+                self.modified_after.is_none_or(|after| modified > after)
+                    && self.modified_before.is_none_or(|before| modified < before)
+            }
+            None => self.modified_after.is_none() && self.modified_before.is_none(),
+        }
+    }
+
+    fn size_allowed(&self, path: &Path) -> bool {
+        if self.min_size.is_none() && self.max_size.is_none() {
+            return true;
+        }
+        let metadata = fs::metadata(path).ok().filter(fs::Metadata::is_file);
+        self.size_within(metadata.map(|metadata| metadata.len()))
+    }
+
+    /// Whether a file of `len` bytes passes `min_size` and `max_size`; None, as for folders,
+    /// only passes without them.
+    pub fn size_within(&self, len: Option<u64>) -> bool {
+        match len {
+            Some(len) => {
+                self.min_size.is_none_or(|min| len >= min) && self.max_size.is_none_or(|max| len <= max)
+            }
+            None => self.min_size.is_none() && self.max_size.is_none(),
+        }
+    }
+
+    fn created_allowed(&self, path: &Path) -> bool {
+        if self.created_after.is_none() && self.created_before.is_none() {
+            return true;
+        }
+        fs::metadata(path).and_then(|metadata| metadata.created()).is_ok_and(|created| {
+            self.created_after.is_none_or(|after| created >= after)
+                && self.created_before.is_none_or(|before| created < before)
+        })
+    }
+
+    /// None if `path` fails the MIME type filter, else the type its contents were sniffed as,
+    /// which is None when no filter is set. Files that can't be read never pass. The file is
+    /// read, so this is checked only once an entry has matched otherwise.
+    fn sniffed_mime(&self, path: &Path) -> Option<Option<&'static str>> {
+        if self.mime_types.is_empty() {
+            return Some(None);
+        }
+        if !path.is_file() {
+            return None;
+        }
+        let mime = mime::sniff_file(path).ok().filter(|mime| mime::matches(mime, &self.mime_types))?;
+        Some(Some(mime))
+    }
+
+    /// Entries whose metadata can't be read never pass a permission filter.
+    fn permissions_allowed(&self, path: &Path) -> bool {
+        if self.permissions == PermissionFilter::Any {
+            return true;
+        }
+        fs::metadata(path).is_ok_and(|metadata| self.permissions.allows(&metadata))
+    }
+
+    fn extension_allowed(&self, path: &Path) -> bool {
+        if self.extensions.is_empty() {
+            return true;
+        }
+        let extension = match path.extension() {
+            Some(extension) => extension.to_string_lossy(),
+            None => return false,
+        };
+        self.extensions.iter().any(|allowed| {
+            allowed
+                .trim_start_matches('.')
+                .eq_ignore_ascii_case(&extension)
+        })
+    }
+}
+
+/// Limits results by their permission bits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PermissionFilter {
+    Any,
+    /// Files with any execute bit set. Unix only; ignored elsewhere.
+    Executable,
+    /// Entries anyone may write to. Unix only; ignored elsewhere.
+    WorldWritable,
+    /// Files that run with their owner's privileges. Unix only; ignored elsewhere.
+    Setuid,
+    ReadOnly,
+}
+
+impl PermissionFilter {
+    #[cfg(unix)]
+    fn allows(self, metadata: &fs::Metadata) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode();
+        match self {
+            PermissionFilter::Any => true,
+            PermissionFilter::Executable => metadata.is_file() && mode & 0o111 != 0,
+            PermissionFilter::WorldWritable => mode & 0o002 != 0,
+            PermissionFilter::Setuid => metadata.is_file() && mode & 0o4000 != 0,
+            PermissionFilter::ReadOnly => metadata.permissions().readonly(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn allows(self, metadata: &fs::Metadata) -> bool {
+        match self {
+            PermissionFilter::ReadOnly => metadata.permissions().readonly(),
+            _ => true,
+        }
+    }
+}
+
+/// A single search hit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    // First line that matched in content search, already cleaned up for display.
+    pub match_line: Option<String>,
+    // 1-based number of that line in content search.
+    pub match_line_number: Option<usize>,
+    // How many lines matched in content search; 0 otherwise.
+    pub match_count: usize,
+    // Size in bytes of a file; None for directories and unreadable entries.
+    pub size: Option<u64>,
+    // Last modification time, if the entry could be read.
+    pub modified: Option<SystemTime>,
+    // Last access time, where the platform and filesystem record one.
+    pub accessed: Option<SystemTime>,
+    // Where a symlink points, filled in only when resolving links is on.
+    pub link: Option<LinkTarget>,
+    // Edits between the closest term and the name in approximate search; None otherwise.
+    pub edit_distance: Option<usize>,
+    // The type sniffed from the file's contents, filled in only when filtering on `mime_types`.
+    pub mime: Option<&'static str>,
+}
+
+impl SearchResult {
+    pub fn new(path: PathBuf) -> Self {
+        let metadata = fs::metadata(&path).ok();
+        let is_dir = metadata.as_ref().is_some_and(fs::Metadata::is_dir);
+        let size = metadata.as_ref().filter(|_| !is_dir).map(fs::Metadata::len);
+        let modified = metadata.as_ref().and_then(|metadata| metadata.modified().ok());
+        let accessed = metadata.and_then(|metadata| metadata.accessed().ok());
+        SearchResult {
+            path,
+            is_dir,
+            size,
+            modified,
+            accessed,
+            match_line: None,
+            match_line_number: None,
+            match_count: 0,
+            link: None,
+            edit_distance: None,
+            mime: None,
+        }
+    }
+
+    /// Fills in `link` if this entry is a symlink; dangling links are marked broken.
+    pub fn resolve_link(&mut self) {
+        let is_symlink = fs::symlink_metadata(&self.path)
+            .map(|metadata| metadata.file_type().is_symlink())
+            .unwrap_or(false);
+        if is_symlink {
+            self.link = Some(match fs::canonicalize(&self.path) {
+                Ok(target) => LinkTarget::Resolved(target),
+                Err(_) => LinkTarget::Broken,
+            });
+        }
+    }
+}
+
+/// The resolved target of a symlink result.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkTarget {
+    Resolved(PathBuf),
+    // The link points at something that doesn't exist.
+    Broken,
+}
+
+/// Why a search couldn't run.
+#[derive(Debug)]
+pub enum SearchError {
+    InvalidPattern(regex::Error),
+    NotADirectory(PathBuf),
+    Unreadable(PathBuf, io::Error),
+    // A root outside `sandbox_root`, with the sandbox.
+    OutsideSandbox(PathBuf, PathBuf),
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchError::InvalidPattern(err) => write!(f, "Invalid search pattern: {}", err),
+            SearchError::NotADirectory(path) => write!(f, "{} is not a directory", path.display()),
+            SearchError::Unreadable(path, err) => {
+                write!(f, "Couldn't read {}: {}", path.display(), err)
+            }
+            SearchError::OutsideSandbox(path, sandbox) => write!(
+                f,
+                "{} is outside {}, which searches are limited to",
+                path.display(),
+                sandbox.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+/// Searches files and directories under `options.root` and returns the matches in walk order,
+/// siblings sorted by name.
+pub fn run(options: &SearchOptions) -> Result<Vec<SearchResult>, SearchError> {
+    run_cancellable(options, &AtomicBool::new(false))
+}
+
+/// Like [`run`], but stops early, with partial results, once `cancel` is set.
+pub fn run_cancellable(
+    options: &SearchOptions,
+    cancel: &AtomicBool,
+) -> Result<Vec<SearchResult>, SearchError> {
+    run_counted(options, cancel, &AtomicUsize::new(0))
+}
+
+/// Like [`run_cancellable`], and adds one to `found` for each match as it turns up, so another
+/// thread can show how many there are so far. Options that trim the matches afterwards, like
+/// `largest_files`, can bring the final count below it.
+pub fn run_counted(
+    options: &SearchOptions,
+    cancel: &AtomicBool,
+    found: &AtomicUsize,
+) -> Result<Vec<SearchResult>, SearchError> {
+    run_with_coverage(options, cancel, found, &AtomicUsize::new(0), &AtomicUsize::new(0))
+}
+
+/// Like [`run_counted`], and also adds one to `dirs_scanned` for each directory below the roots,
+/// as the walk reaches it. A caller can compare that with an earlier walk of the same folders to
+/// estimate how far along a search is. `retried` counts the folders and entries that could only
+/// be read after retrying, see `read_retries`.
+pub fn run_with_coverage(
+    options: &SearchOptions,
+    cancel: &AtomicBool,
+    found: &AtomicUsize,
+    dirs_scanned: &AtomicUsize,
+    retried: &AtomicUsize,
+) -> Result<Vec<SearchResult>, SearchError> {
+    let terms: Vec<Cow<str>> = options
+        .patterns()
+        .into_iter()
+        .map(|term| options.normalize(term))
+        // Approximate terms are plain text, so they must not fail as patterns.
+        .map(|term| match options.mode {
+            SearchMode::Approximate => Cow::Owned(regex::escape(&term)),
+            _ => term,
+        })
+        .collect();
+    let patterns = Matcher::new(&terms, options.all_words, !options.case_sensitive)
+        .map_err(SearchError::InvalidPattern)?;
+    for root in std::iter::once(&options.root).chain(&options.other_roots) {
+        if let Some(sandbox) = &options.sandbox_root {
+            if !sandbox_allows(sandbox, root) {
+                return Err(SearchError::OutsideSandbox(root.clone(), sandbox.clone()));
+            }
+        }
+        if !root.is_dir() {
+            return Err(SearchError::NotADirectory(root.clone()));
+        }
+        fs::read_dir(root).map_err(|err| SearchError::Unreadable(root.clone(), err))?;
+    }
+
+    let counters = (found, dirs_scanned, retried);
+    let mut results = walk(options, &patterns, cancel, counters);
+    for root in &options.other_roots {
+        let options = SearchOptions {
+            root: root.clone(),
+            ..options.clone()
+        };
+        results.extend(walk(&options, &patterns, cancel, counters));
+    }
+    if options.exact_case_first && !options.case_sensitive {
+        let exact =
+            Matcher::new(&terms, options.all_words, false).map_err(SearchError::InvalidPattern)?;
+        rank_exact_case_first(&mut results, &exact, options);
+    }
+    if options.mode == SearchMode::Approximate {
+        // Stable, so equally close names keep the order above.
+        results.sort_by_key(|result| result.edit_distance);
+    }
+    if let Some(count) = options.largest_files {
+        results = largest_files(results, count);
+    }
+    if options.resolve_links || options.broken_links_only {
+        results.iter_mut().for_each(SearchResult::resolve_link);
+    }
+    Ok(results)
+}
+
+/// The compiled terms. An entry matches if any term does; with `all_words`, a term matches
+/// when every one of its words does.
+struct Matcher {
+    set: RegexSet,
+    // Indices into `set` of each term's patterns: one per term, or one per word.
+    terms: Vec<Range<usize>>,
+}
+
+impl Matcher {
+    fn new(terms: &[Cow<str>], all_words: bool, case_insensitive: bool) -> Result<Self, regex::Error> {
+        let mut patterns: Vec<&str> = Vec::new();
+        let mut ranges = Vec::new();
+        for term in terms {
+            let start = patterns.len();
+            if all_words {
+                patterns.extend(term.split_whitespace());
+            } else {
+                patterns.push(term);
+            }
+            ranges.push(start..patterns.len());
+        }
+        let set = RegexSetBuilder::new(&patterns).case_insensitive(case_insensitive).build()?;
+        Ok(Matcher { set, terms: ranges })
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        if self.terms.iter().all(|term| term.len() == 1) {
+            return self.set.is_match(text);
+        }
+        let matched = self.set.matches(text);
+        // A term without words, like an empty one, matches everything.
+        self.terms.iter().any(|term| term.clone().all(|index| matched.matched(index)))
+    }
+}
+
+/// Matches found, directories reached and entries retried, as passed to [`run_with_coverage`].
+type WalkCounters<'a> = (&'a AtomicUsize, &'a AtomicUsize, &'a AtomicUsize);
+
+fn walk(
+    options: &SearchOptions,
+    patterns: &Matcher,
+    cancel: &AtomicBool,
+    counters: WalkCounters,
+) -> Vec<SearchResult> {
+    let (found, dirs_scanned, retried) = counters;
+    let mut walker = WalkDir::new(&options.root).min_depth(1).sort_by_file_name();
+    if let Some(depth) = options.max_depth {
+        walker = walker.max_depth(depth);
+    }
+    let root_device = if options.stay_on_filesystem {
+        device_id(&options.root)
+    } else {
+        None
+    };
+    // The last folder let into the walk. The walk lists a folder as it yields it, so an error
+    // for that same path right after is about its listing rather than its metadata.
+    let last_dir: RefCell<Option<PathBuf>> = RefCell::new(None);
+    // Entries that couldn't be read, with their depth below the root and whether it was a
+    // folder's listing that failed.
+    let mut unreadable = Vec::new();
+    let entries = walker
+        .into_iter()
+        .filter_entry(|entry| {
+            if !options.show_hidden && is_hidden(entry.path()) {
+                return false;
+            }
+            if !entry.file_type().is_dir() {
+                return true;
+            }
+            let excluded = options
+                .exclude_dirs
+                .iter()
+                .any(|name| entry.file_name().to_string_lossy() == name.as_str())
+                || options.is_excluded_path(entry.path());
+            let other_device = root_device.is_some_and(|root| device_id(entry.path()) != Some(root));
+            let walked = !(excluded || other_device);
+            if walked {
+                *last_dir.borrow_mut() = Some(entry.path().to_path_buf());
+            }
+            walked
+        })
+        // Unreadable subdirectories are skipped rather than failing the whole search, once any
+        // retries are used up.
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                let transient = err.io_error().is_some_and(|err| {
+                    !matches!(err.kind(), io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied)
+                });
+                if let (true, Some(path)) = (transient, err.path()) {
+                    let listing = last_dir.borrow().as_deref() == Some(path);
+                    unreadable.push((path.to_path_buf(), err.depth(), listing));
+                }
+                None
+            }
+        });
+
+    let typo_terms: Vec<Vec<char>> = match options.mode {
+        SearchMode::Approximate => options
+            .patterns()
+            .into_iter()
+            .map(|term| options.fold_case(&options.normalize(term)).chars().collect())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut results = Vec::new();
+    // Entries to scan, and whether each already matched by name and is kept either way.
+    let mut content_candidates = Vec::new();
+    // Matches one entry the walk reached by name, or queues it for its contents to be scanned.
+    let mut consider = |path: PathBuf| {
+        if !(path.is_file() || path.is_dir() || path.is_symlink()) {
+            return;
+        }
+        if !options.extension_allowed(&path)
+            || !options.modified_allowed(&path)
+            || !options.created_allowed(&path)
+            || !options.size_allowed(&path)
+            || !options.permissions_allowed(&path)
+            || (options.broken_links_only && !is_broken_link(&path))
+            || (options.empty_only && !is_empty_entry(&path))
+        {
+            return;
+        }
+        let name_matches = || {
+            patterns.is_match(&options.normalize(&options.matched_name(&path)))
+                || (options.match_link_targets
+                    && link_target(&path).is_some_and(|target| {
+                        patterns.is_match(&options.normalize(&options.matched_name(&target)))
+                    }))
+        };
+        // Content modes only collect candidates here; they are counted once scanned.
+        let mut scan = |path: PathBuf, by_name: bool| {
+            if let Some(mime) = options.sniffed_mime(&path) {
+                content_candidates.push((path, by_name, mime));
+            }
+        };
+        let matched = match options.mode {
+            SearchMode::Contents => {
+                if path.is_file() {
+                    scan(path, false);
+                }
+                None
+            }
+            SearchMode::NameOrContents => {
+                let by_name = name_matches();
+                if by_name || path.is_file() {
+                    scan(path, by_name);
+                }
+                None
+            }
+            SearchMode::NameAndContents => {
+                if path.is_file() && name_matches() {
+                    scan(path, false);
+                }
+                None
+            }
+            SearchMode::FullPath => patterns
+                .is_match(&options.normalize(&normalized_path(&path)))
+                .then(|| SearchResult::new(path)),
+            SearchMode::Name => name_matches().then(|| SearchResult::new(path)),
+            SearchMode::Approximate => {
+                let name = options.matched_name(&path);
+                let name: Vec<char> = options.fold_case(&options.normalize(&name)).chars().collect();
+                typo_terms
+                    .iter()
+                    .map(|term| substring_edit_distance(term, &name))
+                    .min()
+                    .filter(|&distance| distance <= options.max_edit_distance)
+                    .map(|distance| {
+                        let mut result = SearchResult::new(path);
+                        result.edit_distance = Some(distance);
+                        result
+                    })
+            }
+        };
+        if let Some(mut result) = matched {
+            let Some(mime) = options.sniffed_mime(&result.path) else {
+                return;
+            };
+            result.mime = mime;
+            found.fetch_add(1, Ordering::Relaxed);
+            results.push(result);
+        }
+    };
+    for entry in entries {
+        if cancel.load(Ordering::Relaxed) {
+            return results;
+        }
+        // The walk reads a directory's entries right after yielding it.
+        if entry.file_type().is_dir() {
+            dirs_scanned.fetch_add(1, Ordering::Relaxed);
+            if options.low_impact {
+                thread::sleep(LOW_IMPACT_PAUSE);
+            }
+        }
+        consider(entry.into_path());
+    }
+    // An entry whose metadata couldn't be read is looked at once it can be, and a folder among
+    // them is listed below like those whose listing failed.
+    // Folders to list again, and whether they were already counted as retried.
+    let mut unlisted = Vec::new();
+    for (path, depth, listing) in unreadable {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        if listing {
+            unlisted.push((path, depth, false));
+            continue;
+        }
+        let Some(metadata) = retry_read(options.read_retries, || fs::symlink_metadata(&path)) else {
+            continue;
+        };
+        retried.fetch_add(1, Ordering::Relaxed);
+        if metadata.is_dir() {
+            dirs_scanned.fetch_add(1, Ordering::Relaxed);
+            unlisted.push((path.clone(), depth, true));
+        }
+        consider(path);
+    }
+    if options.mode.scans_contents() {
+        results = search_contents_parallel(content_candidates, patterns, options, cancel, found);
+    }
+    for (dir, depth, counted) in unlisted {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        if fs::read_dir(&dir).is_err()
+            && retry_read(options.read_retries, || fs::read_dir(&dir)).is_none()
+        {
+            continue;
+        }
+        if !counted {
+            retried.fetch_add(1, Ordering::Relaxed);
+        }
+        // Searched on its own, so its matches come after the rest of this root's.
+        let options = SearchOptions {
+            root: dir,
+            other_roots: Vec::new(),
+            max_depth: options.max_depth.map(|max| max.saturating_sub(depth)),
+            ..options.clone()
+        };
+        results.extend(walk(&options, patterns, cancel, counters));
+    }
+    results
+}
+
+/// Tries `read` up to `retries` times, waiting a little longer before each attempt, and gives
+/// back the first thing it read.
+fn retry_read<T>(retries: usize, mut read: impl FnMut() -> io::Result<T>) -> Option<T> {
+    (1..=retries as u32).find_map(|attempt| {
+        thread::sleep(READ_RETRY_BACKOFF * attempt);
+        read().ok()
+    })
+}
+
+/// Splits a root list into the lines that name existing folders and the ones that don't,
+/// ignoring blank lines and `#` comments.
+pub fn parse_root_list(text: &str) -> (Vec<String>, Vec<String>) {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .partition(|line| Path::new(line).is_dir())
+}
+
+/// Whether `dir` lies inside `sandbox`, comparing real paths so `..` and symlinks can't lead
+/// out of it. A path that doesn't exist is judged as written, after dropping `..` steps the
+/// way the filesystem would resolve them.
+pub fn sandbox_allows(sandbox: &Path, dir: &Path) -> bool {
+    let real = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| lexically_normal(path));
+    real(dir).starts_with(real(sandbox))
+}
+
+/// `path` with `.` steps dropped and each `..` taking off the step before it.
+fn lexically_normal(path: &Path) -> PathBuf {
+    use std::path::Component;
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normal.pop();
+            }
+            other => normal.push(other),
+        }
+    }
+    normal
+}
+
+/// Reads a `YYYY-MM-DD` date as the start of that day in UTC, as the app shows times.
+/// None for anything else, including days that don't exist like `2026-02-30` and years outside
+/// 1 to 9999, which no file is dated and which would overflow the arithmetic.
+pub fn parse_date(text: &str) -> Option<SystemTime> {
+    let mut parts = text.trim().splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=9999).contains(&year) {
+        return None;
+    }
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = [31, if leap { 29 } else { 28 }, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+    if !(1..=12).contains(&month) || day < 1 || day > month_days[month as usize - 1] {
+        return None;
+    }
+    // A civil date to days since 1970-01-01 (Howard Hinnant's `days_from_civil`).
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    let secs = days * 86_400;
+    if secs >= 0 {
+        SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))
+    } else {
+        SystemTime::UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))
+    }
+}
+
+/// Folds the letters whose case-insensitive forms simple case folding misses, see
+/// `full_case_folding`. Only non-ASCII letters change, so ASCII is left to the regex's own case
+/// folding and a pattern's escapes and classes keep working.
+fn fold_special_cases(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    let mut previous = '\0';
+    for c in text.chars() {
+        match c {
+            'ß' | 'ẞ' => folded.push_str("ss"),
+            'İ' | 'ı' => folded.push('i'),
+            // The dot above left on `i` by decomposed text, or by lowercasing `İ`.
+            '\u{307}' if matches!(previous, 'i' | 'I') => {}
+            'ﬀ' => folded.push_str("ff"),
+            'ﬁ' => folded.push_str("fi"),
+            'ﬂ' => folded.push_str("fl"),
+            'ﬃ' => folded.push_str("ffi"),
+            'ﬄ' => folded.push_str("ffl"),
+            'ﬅ' | 'ﬆ' => folded.push_str("st"),
+            _ => folded.push(c),
+        }
+        previous = c;
+    }
+    folded
+}
+
+/// A size such as `10MB`, `1.5k` or `200` (bytes), in powers of 1000; None if it isn't one.
+fn parse_size(text: &str) -> Option<u64> {
+    let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let scale = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "k" | "kb" => 1e3,
+        "m" | "mb" => 1e6,
+        "g" | "gb" => 1e9,
+        "t" | "tb" => 1e12,
+        _ => return None,
+    };
+    Some((number * scale) as u64)
+}
+
+/// System locations not worth searching from the top of the disk: pseudo-filesystems and OS
+/// folders that are huge, slow or unreadable, and can hang a walk.
+pub fn default_exclude_paths() -> Vec<PathBuf> {
+    let paths: &[&str] = if cfg!(target_os = "macos") {
+        &["/System", "/dev", "/private/var/vm", "/.Spotlight-V100", "/.fseventsd"]
+    } else if cfg!(windows) {
+        &["C:\\Windows", "$Recycle.Bin", "System Volume Information"]
+    } else {
+        &["/proc", "/sys", "/dev", "/run"]
+    };
+    paths.iter().map(PathBuf::from).collect()
+}
+
+/// The `count` biggest files among `results`, biggest first, with ties in their earlier order.
+/// Only `count` candidates are held while going through them.
+fn largest_files(results: Vec<SearchResult>, count: usize) -> Vec<SearchResult> {
+    // A min-heap of (size, earlier first), so the least of the kept files is the one dropped.
+    let mut kept = BinaryHeap::with_capacity(count + 1);
+    for (index, result) in results.iter().enumerate().filter(|(_, result)| !result.is_dir) {
+        kept.push(Reverse((result.size.unwrap_or(0), Reverse(index))));
+        if kept.len() > count {
+            kept.pop();
+        }
+    }
+    let mut results: Vec<Option<SearchResult>> = results.into_iter().map(Some).collect();
+    kept.into_sorted_vec()
+        .into_iter()
+        .filter_map(|Reverse((_, Reverse(index)))| results[index].take())
+        .collect()
+}
+
+/// Where a symlink finally leads, or for a broken one where it points; None for other entries.
+fn link_target(path: &Path) -> Option<PathBuf> {
+    if !path.is_symlink() {
+        return None;
+    }
+    fs::canonicalize(path).or_else(|_| fs::read_link(path)).ok()
+}
+
+/// A zero-byte file or a folder without entries. Symlinks are neither, whatever they point to.
+fn is_empty_entry(path: &Path) -> bool {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_file() => metadata.len() == 0,
+        Ok(metadata) if metadata.is_dir() => {
+            fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+        }
+        _ => false,
+    }
+}
+
+/// A symlink whose target can't be reached, so following it fails.
+fn is_broken_link(path: &Path) -> bool {
+    path.is_symlink() && fs::metadata(path).is_err()
+}
+
+/// A dotfile, or on Windows an entry with the hidden attribute set.
+fn is_hidden(path: &Path) -> bool {
+    let dotted = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    dotted || has_hidden_attribute(path)
+}
+
+#[cfg(windows)]
+fn has_hidden_attribute(path: &Path) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    fs::symlink_metadata(path)
+        .is_ok_and(|metadata| metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+}
+
+#[cfg(not(windows))]
+fn has_hidden_attribute(_path: &Path) -> bool {
+    false
+}
+
+/// The fewest single-character insertions, deletions and substitutions that turn `term` into
+/// some part of `text`, so a term found in the text as-is is 0 edits away.
+fn substring_edit_distance(term: &[char], text: &[char]) -> usize {
+    // Distances from each prefix of the term to the best part of `text` ending at each
+    // position; the empty prefix fits anywhere for free.
+    let mut previous = vec![0; text.len() + 1];
+    for (i, &wanted) in term.iter().enumerate() {
+        let mut current = vec![i + 1; text.len() + 1];
+        for (j, &found) in text.iter().enumerate() {
+            let substitute = previous[j] + usize::from(wanted != found);
+            current[j + 1] = substitute.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous.into_iter().min().unwrap_or(0)
+}
+
+/// The id of the device a path lives on, for telling mount points apart.
+#[cfg(unix)]
+fn device_id(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|metadata| metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device_id(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Stably moves results whose matched text also matches `exact` (the case-sensitive terms)
+/// ahead of the rest, so each group keeps walk order.
+fn rank_exact_case_first(
+    results: &mut [SearchResult],
+    exact: &Matcher,
+    options: &SearchOptions,
+) {
+    results.sort_by_key(|result| {
+        let matched = match options.mode {
+            SearchMode::Name | SearchMode::Approximate => options.matched_name(&result.path),
+            SearchMode::FullPath => normalized_path(&result.path).into(),
+            SearchMode::Contents => result.match_line.as_deref().unwrap_or_default().into(),
+            // Rank by the line when the contents matched, else by the name.
+            SearchMode::NameOrContents | SearchMode::NameAndContents => match &result.match_line {
+                Some(line) => line.as_str().into(),
+                None => options.matched_name(&result.path),
+            },
+        };
+        !exact.is_match(&options.normalize(&matched))
+    });
+}
+
+/// The full path with separators normalized to `/`, so a pattern like `src/main`
+/// matches on Windows too. Elsewhere `\` is a legal file name character and is left alone.
+fn normalized_path(path: &Path) -> String {
+    let text = path.to_string_lossy();
+    if std::path::MAIN_SEPARATOR == '/' {
+        text.into_owned()
+    } else {
+        text.replace(std::path::MAIN_SEPARATOR, "/")
+    }
+}
+
+/// Scans the files' contents on up to `thread_count` workers, keeping the input order in the
+/// results. An entry flagged as already matched is kept even if its contents don't match, and
+/// directories are never read. Each carries the type it was sniffed as, if it was. Workers
+/// check `cancel` between files and between lines, so a cancelled scan ends promptly.
+fn search_contents_parallel(
+    files: Vec<(PathBuf, bool, Option<&'static str>)>,
+    patterns: &Matcher,
+    options: &SearchOptions,
+    cancel: &AtomicBool,
+    found: &AtomicUsize,
+) -> Vec<SearchResult> {
+    let thread_count = if options.low_impact { 1 } else { options.thread_count.max(1) };
+    let chunk_size = files.len().div_ceil(thread_count).max(1);
+    let low_impact = options.low_impact;
+    thread::scope(|scope| {
+        let workers: Vec<_> = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .take_while(|_| !cancel.load(Ordering::Relaxed))
+                        .filter_map(|(path, matched, mime)| {
+                            if low_impact {
+                                thread::sleep(LOW_IMPACT_PAUSE);
+                            }
+                            let matches = path
+                                .is_file()
+                                .then(|| {
+                                    scan_matches(path, patterns, options, cancel)
+                                })
+                                .flatten();
+                            if matches.is_none() && !matched {
+                                return None;
+                            }
+                            let mut result = SearchResult::new(path.clone());
+                            result.mime = *mime;
+                            if let Some(matches) = matches {
+                                result.match_line = Some(matches.first_line);
+                                result.match_line_number = Some(matches.first_line_number);
+                                result.match_count = matches.count;
+                            }
+                            found.fetch_add(1, Ordering::Relaxed);
+                            Some(result)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_default())
+            .collect()
+    })
+}
+
+/// Lines of one file that matched a content search.
+struct ContentMatches {
+    first_line: String,
+    first_line_number: usize,
+    count: usize,
+}
+
+/// Reads the whole file and counts its matching lines, keeping the first one cleaned up for
+/// display. Only the first `content_max_bytes` are read, and each line is normalized like the
+/// terms were. Returns None when nothing matched. Files that can't be read simply don't match,
+/// and neither do binary ones with `skip_binary`, or anything once `cancel` is set.
+fn scan_matches(
+    path: &Path,
+    patterns: &Matcher,
+    options: &SearchOptions,
+    cancel: &AtomicBool,
+) -> Option<ContentMatches> {
+    let max_bytes = options.content_max_bytes.unwrap_or(u64::MAX);
+    let file = fs::File::open(path).ok()?.take(max_bytes);
+    let mut reader = BufReader::with_capacity(BINARY_CHECK_BYTES, file);
+    // Like grep, call a file binary if its first block contains a NUL byte.
+    if options.skip_binary && reader.fill_buf().ok()?.contains(&0) {
+        return None;
+    }
+    let mut matches: Option<ContentMatches> = None;
+    for (index, line) in reader.split(b'\n').enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let line = line.ok()?;
+        let line = String::from_utf8_lossy(&line);
+        if !patterns.is_match(&options.normalize(&line)) {
+            continue;
+        }
+        match &mut matches {
+            Some(matches) => matches.count += 1,
+            None => {
+                matches = Some(ContentMatches {
+                    first_line: clean_match_line(&line),
+                    first_line_number: index + 1,
+                    count: 1,
+                })
+            }
+        }
+    }
+    matches
+}
+
+/// Replaces control characters (tabs included) with spaces, trims, and truncates long lines.
+fn clean_match_line(line: &str) -> String {
+    let cleaned: String = line
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().to_string();
+    if cleaned.chars().count() > MATCH_LINE_MAX_CHARS {
+        let truncated: String = cleaned.chars().take(MATCH_LINE_MAX_CHARS).collect();
+        format!("{}…", truncated)
+    } else {
+        cleaned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// root/
+    ///   README.md
+    ///   notes.txt
+    ///   docs/Guide.TXT
+    ///   src/main.rs
+    ///   src/nested/deep.rs
+    ///   target/build.log
+    fn sample_tree() -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for sub in ["docs", "src/nested", "target"] {
+            fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        let files = [
+            ("README.md", "# Sample\nhello world\n"),
+            ("notes.txt", "nothing to see\n"),
+            ("docs/Guide.TXT", "Read me first\n"),
+            ("src/main.rs", "fn main() {\n\tprintln!(\"hello\");\n}\n"),
+            ("src/nested/deep.rs", "// deep\n"),
+            ("target/build.log", "hello from the build\n"),
+        ];
+        for (path, contents) in files {
+            fs::write(root.join(path), contents).unwrap();
+        }
+        dir
+    }
+
+    /// Result paths relative to `root`, with `/` separators, in the order returned.
+    fn relative_paths(root: &Path, options: &SearchOptions) -> Vec<String> {
+        run(options)
+            .unwrap()
+            .into_iter()
+            .map(|result| {
+                let relative = result.path.strip_prefix(root).unwrap();
+                relative.to_string_lossy().replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_term_matches_every_entry() {
+        let dir = sample_tree();
+        let options = SearchOptions::new(dir.path(), "");
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            [
+                "README.md",
+                "docs",
+                "docs/Guide.TXT",
+                "notes.txt",
+                "src",
+                "src/main.rs",
+                "src/nested",
+                "src/nested/deep.rs",
+                "target",
+                "target/build.log",
+            ]
+        );
+    }
+
+    #[test]
+    fn names_match_case_insensitively_by_default() {
+        let dir = sample_tree();
+        let options = SearchOptions::new(dir.path(), "guide");
+        assert_eq!(relative_paths(dir.path(), &options), ["docs/Guide.TXT"]);
+    }
+
+    #[test]
+    fn exact_case_matches_can_be_ranked_first() {
+        let dir = sample_tree();
+        fs::write(dir.path().join("guide.md"), "").unwrap();
+        let mut options = SearchOptions::new(dir.path(), "guide");
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["docs/Guide.TXT", "guide.md"]
+        );
+
+        options.exact_case_first = true;
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["guide.md", "docs/Guide.TXT"]
+        );
+    }
+
+    #[test]
+    fn unicode_normalization_matches_decomposed_names() {
+        let dir = tempfile::tempdir().unwrap();
+        // "café" with a combining acute accent, as macOS stores it.
+        fs::write(dir.path().join("cafe\u{301}.txt"), "").unwrap();
+        let mut options = SearchOptions::new(dir.path(), "caf\u{e9}");
+        options.normalize_unicode = true;
+        assert_eq!(run(&options).unwrap().len(), 1);
+
+        options.normalize_unicode = false;
+        assert!(run(&options).unwrap().is_empty());
+    }
+
+    #[test]
+    fn full_case_folding_matches_sharp_s_and_turkish_i() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Straße.txt"), "").unwrap();
+        fs::write(dir.path().join("İstanbul.txt"), "").unwrap();
+        let mut options = SearchOptions::new(dir.path(), "strasse|ISTANBUL");
+        assert!(run(&options).unwrap().is_empty());
+
+        options.full_case_folding = true;
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["Straße.txt", "İstanbul.txt"]
+        );
+
+        options.case_sensitive = true;
+        assert!(run(&options).unwrap().is_empty());
+    }
+
+    #[test]
+    fn full_case_folding_applies_to_contents_too() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("street.txt"), "Hauptstraße 1\n").unwrap();
+        let mut options = SearchOptions::new(dir.path(), "straße");
+        options.mode = SearchMode::Contents;
+        assert_eq!(relative_paths(dir.path(), &options), ["street.txt"]);
+
+        options.full_case_folding = true;
+        let results = run(&options).unwrap();
+        assert_eq!(results.len(), 1);
+        // The line is shown as written.
+        assert_eq!(results[0].match_line.as_deref(), Some("Hauptstraße 1"));
+        options.term = "STRASSE".to_string();
+        assert_eq!(relative_paths(dir.path(), &options), ["street.txt"]);
+    }
+
+    #[test]
+    fn mime_types_match_contents_not_extensions() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("photo.dat"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        fs::write(dir.path().join("fake.png"), "not an image\n").unwrap();
+        fs::write(dir.path().join("paper.pdf"), "%PDF-1.7\n").unwrap();
+        let mut options = SearchOptions::new(dir.path(), "");
+        options.mime_types = vec!["image".to_string()];
+        let results = run(&options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].path.ends_with("photo.dat"));
+        assert_eq!(results[0].mime, Some("image/png"));
+
+        options.mime_types = vec!["application/pdf".to_string(), "text/plain".to_string()];
+        assert_eq!(relative_paths(dir.path(), &options), ["fake.png", "paper.pdf"]);
+
+        // Content matches carry the type they were filtered on too.
+        options.mode = SearchMode::Contents;
+        options.term = "image".to_string();
+        let results = run(&options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].path.ends_with("fake.png"));
+        assert_eq!(results[0].mime, Some("text/plain"));
+    }
+
+    #[test]
+    fn case_sensitive_search_respects_case() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path(), "guide");
+        options.case_sensitive = true;
+        assert!(relative_paths(dir.path(), &options).is_empty());
+
+        options.term = "Guide".to_string();
+        assert_eq!(relative_paths(dir.path(), &options), ["docs/Guide.TXT"]);
+    }
+
+    #[test]
+    fn extension_filter_ignores_case_and_leading_dot() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path(), "");
+        options.extensions = vec![".txt".to_string(), "RS".to_string()];
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            [
+                "docs/Guide.TXT",
+                "notes.txt",
+                "src/main.rs",
+                "src/nested/deep.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn depth_limit_stops_the_walk() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path(), r"\.rs$");
+        options.max_depth = Some(2);
+        assert_eq!(relative_paths(dir.path(), &options), ["src/main.rs"]);
+
+        options.max_depth = Some(1);
+        assert!(relative_paths(dir.path(), &options).is_empty());
+
+        options.max_depth = None;
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["src/main.rs", "src/nested/deep.rs"]
+        );
+    }
+
+    #[test]
+    fn listing_returns_only_direct_entries() {
+        let dir = sample_tree();
+        let options = SearchOptions::listing(dir.path());
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["README.md", "docs", "notes.txt", "src", "target"]
+        );
+    }
+
+    #[test]
+    fn excluded_directories_are_not_descended_into() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path(), "");
+        options.exclude_dirs = vec!["target".to_string(), "nested".to_string()];
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            [
+                "README.md",
+                "docs",
+                "docs/Guide.TXT",
+                "notes.txt",
+                "src",
+                "src/main.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn excluded_paths_match_exactly_or_by_name_but_never_the_root() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path(), "");
+        options.exclude_paths = vec![dir.path().join("target"), PathBuf::from("nested")];
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            [
+                "README.md",
+                "docs",
+                "docs/Guide.TXT",
+                "notes.txt",
+                "src",
+                "src/main.rs"
+            ]
+        );
+
+        options.root = dir.path().join("target");
+        assert_eq!(relative_paths(dir.path(), &options), ["target/build.log"]);
+    }
+
+    #[test]
+    fn or_terms_match_any_pattern() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path(), r"\.md$");
+        options.or_terms = vec![r"\.log$".to_string(), String::new()];
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["README.md", "target/build.log"]
+        );
+    }
+
+    #[test]
+    fn other_roots_are_searched_after_the_root() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path().join("target"), r"\.(log|rs)$");
+        options.other_roots = vec![dir.path().join("src/nested")];
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["target/build.log", "src/nested/deep.rs"]
+        );
+
+        options.other_roots.push(dir.path().join("missing"));
+        assert!(matches!(run(&options), Err(SearchError::NotADirectory(_))));
+    }
+
+    #[test]
+    fn all_words_requires_every_word_in_any_order() {
+        let dir = sample_tree();
+        fs::write(dir.path().join("src/config_main.rs"), "").unwrap();
+        fs::write(dir.path().join("src/config.rs"), "").unwrap();
+        let mut options = SearchOptions::new(dir.path(), "main config");
+        assert!(relative_paths(dir.path(), &options).is_empty());
+
+        options.all_words = true;
+        assert_eq!(relative_paths(dir.path(), &options), ["src/config_main.rs"]);
+
+        // Each word is a pattern, and other terms are still alternatives.
+        options.term = r"^main \.rs$".to_string();
+        options.or_terms = vec!["deep".to_string()];
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["src/main.rs", "src/nested/deep.rs"]
+        );
+    }
+
+    #[test]
+    fn approximate_mode_allows_typos_and_ranks_closest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["config.toml", "confetti.txt", "notes.md"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        let mut options = SearchOptions::new(dir.path(), "CNFIG");
+        options.mode = SearchMode::Approximate;
+        options.max_edit_distance = 1;
+        options.or_terms = vec!["notes".to_string()];
+        let results = run(&options).unwrap();
+        let found: Vec<(&str, Option<usize>)> = results
+            .iter()
+            .map(|r| (r.path.file_name().unwrap().to_str().unwrap(), r.edit_distance))
+            .collect();
+        assert_eq!(found, [("notes.md", Some(0)), ("config.toml", Some(1))]);
+
+        options.max_edit_distance = 0;
+        assert_eq!(relative_paths(dir.path(), &options), ["notes.md"]);
+    }
+
+    #[test]
+    fn terms_are_trimmed_unless_asked_not_to() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path(), " readme\t");
+        options.or_terms = vec!["  ".to_string()];
+        assert_eq!(relative_paths(dir.path(), &options), ["README.md"]);
+
+        options.trim_terms = false;
+        assert!(relative_paths(dir.path(), &options).is_empty());
+    }
+
+    #[test]
+    fn stem_only_ignores_extensions() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path(), "txt");
+        assert_eq!(relative_paths(dir.path(), &options), ["docs/Guide.TXT", "notes.txt"]);
+
+        options.stem_only = true;
+        assert!(relative_paths(dir.path(), &options).is_empty());
+        options.term = "^notes$".to_string();
+        assert_eq!(relative_paths(dir.path(), &options), ["notes.txt"]);
+    }
+
+    #[test]
+    fn hidden_entries_are_left_out_unless_shown() {
+        let dir = sample_tree();
+        fs::create_dir(dir.path().join(".cache")).unwrap();
+        fs::write(dir.path().join(".cache/notes.txt"), "").unwrap();
+        fs::write(dir.path().join(".notes.txt"), "").unwrap();
+        let mut options = SearchOptions::new(dir.path(), "notes");
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            [".cache/notes.txt", ".notes.txt", "notes.txt"]
+        );
+
+        options.show_hidden = false;
+        assert_eq!(relative_paths(dir.path(), &options), ["notes.txt"]);
+    }
+
+    #[test]
+    fn low_impact_mode_finds_the_same_matches() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path(), "hello");
+        options.mode = SearchMode::Contents;
+        options.thread_count = 4;
+        let full_speed = relative_paths(dir.path(), &options);
+        options.low_impact = true;
+        assert_eq!(relative_paths(dir.path(), &options), full_speed);
+    }
+
+    #[test]
+    fn coverage_counts_every_directory_walked() {
+        let dir = sample_tree();
+        let options = SearchOptions::new(dir.path(), "nothing matches this");
+        let (scanned, retried) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        run_with_coverage(&options, &AtomicBool::new(false), &AtomicUsize::new(0), &scanned, &retried)
+            .unwrap();
+        // docs, src, src/nested and target.
+        assert_eq!(scanned.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn run_counted_counts_matches_as_they_are_found() {
+        let dir = sample_tree();
+        let found = AtomicUsize::new(0);
+        let options = SearchOptions::new(dir.path(), r"\.rs$");
+        let results = run_counted(&options, &AtomicBool::new(false), &found).unwrap();
+        assert_eq!(found.load(Ordering::Relaxed), results.len());
+
+        let found = AtomicUsize::new(0);
+        let mut options = SearchOptions::new(dir.path(), "fn");
+        options.mode = SearchMode::Contents;
+        let results = run_counted(&options, &AtomicBool::new(false), &found).unwrap();
+        assert!(!results.is_empty());
+        assert_eq!(found.load(Ordering::Relaxed), results.len());
+    }
+
+    #[test]
+    fn largest_files_keeps_the_biggest_matches_biggest_first() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path(), "");
+        options.largest_files = Some(2);
+        // README.md and target/build.log are the same size; the one found first wins.
+        assert_eq!(relative_paths(dir.path(), &options), ["src/main.rs", "README.md"]);
+
+        options.term = r"\.(rs|log)$".to_string();
+        assert_eq!(relative_paths(dir.path(), &options), ["src/main.rs", "target/build.log"]);
+    }
+
+    #[test]
+    fn full_path_mode_matches_directories_in_the_path() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path(), "src/.*\\.rs$");
+        assert!(relative_paths(dir.path(), &options).is_empty());
+
+        options.mode = SearchMode::FullPath;
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["src/main.rs", "src/nested/deep.rs"]
+        );
+    }
+
+    #[test]
+    fn content_search_counts_matching_lines() {
+        let dir = sample_tree();
+        fs::write(dir.path().join("log.txt"), "start\nerror one\nok\nERROR two\n").unwrap();
+        let mut options = SearchOptions::new(dir.path(), "error");
+        options.mode = SearchMode::Contents;
+        let results = run(&options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].match_line.as_deref(), Some("error one"));
+        assert_eq!(results[0].match_line_number, Some(2));
+        assert_eq!(results[0].match_count, 2);
+    }
+
+    #[test]
+    fn name_and_content_modes_combine_both_matches() {
+        let dir = sample_tree();
+        fs::create_dir(dir.path().join("src/reader")).unwrap();
+        fs::write(dir.path().join("src/readme_notes.txt"), "read this\n").unwrap();
+        let mut options = SearchOptions::new(dir.path(), "read");
+        options.mode = SearchMode::Contents;
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["docs/Guide.TXT", "src/readme_notes.txt"]
+        );
+
+        options.mode = SearchMode::NameOrContents;
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["README.md", "docs/Guide.TXT", "src/reader", "src/readme_notes.txt"]
+        );
+
+        options.mode = SearchMode::NameAndContents;
+        assert_eq!(relative_paths(dir.path(), &options), ["src/readme_notes.txt"]);
+    }
+
+    #[test]
+    fn content_search_skips_binary_files_unless_asked_not_to() {
+        let dir = sample_tree();
+        fs::write(dir.path().join("app.bin"), b"\x7fELF\0\0hello\n").unwrap();
+        let mut options = SearchOptions::new(dir.path(), "hello");
+        options.mode = SearchMode::Contents;
+        assert!(!relative_paths(dir.path(), &options).contains(&"app.bin".to_string()));
+
+        options.skip_binary = false;
+        assert!(relative_paths(dir.path(), &options).contains(&"app.bin".to_string()));
+    }
+
+    #[test]
+    fn content_search_reads_only_up_to_the_size_limit() {
+        let dir = sample_tree();
+        fs::write(dir.path().join("big.log"), format!("{}\nneedle\n", "x".repeat(100))).unwrap();
+        let mut options = SearchOptions::new(dir.path(), "needle");
+        options.mode = SearchMode::Contents;
+        assert_eq!(relative_paths(dir.path(), &options), ["big.log"]);
+
+        options.content_max_bytes = Some(50);
+        assert!(relative_paths(dir.path(), &options).is_empty());
+    }
+
+    #[test]
+    fn content_search_reports_the_first_matching_line() {
+        let dir = sample_tree();
+        let mut options = SearchOptions::new(dir.path(), "hello");
+        options.mode = SearchMode::Contents;
+        options.thread_count = 3;
+        let results = run(&options).unwrap();
+        let found: Vec<(String, Option<String>)> = results
+            .into_iter()
+            .map(|result| {
+                let name = result
+                    .path
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                (name, result.match_line)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("README.md".to_string(), Some("hello world".to_string())),
+                // Tabs are replaced and the line is trimmed.
+                (
+                    "main.rs".to_string(),
+                    Some("println!(\"hello\");".to_string())
+                ),
+                (
+                    "build.log".to_string(),
+                    Some("hello from the build".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_pattern_is_an_error() {
+        let dir = sample_tree();
+        let options = SearchOptions::new(dir.path(), "*.png");
+        assert!(matches!(run(&options), Err(SearchError::InvalidPattern(_))));
+    }
+
+    #[test]
+    fn missing_root_is_an_error() {
+        let dir = sample_tree();
+        let options = SearchOptions::new(dir.path().join("missing"), "");
+        assert!(matches!(run(&options), Err(SearchError::NotADirectory(_))));
+    }
+
+    #[test]
+    fn modified_after_skips_older_entries() {
+        let dir = sample_tree();
+        let cutoff = SystemTime::now() - std::time::Duration::from_secs(3600);
+        let old = cutoff - std::time::Duration::from_secs(3600);
+        for path in ["README.md", "docs/Guide.TXT", "src/main.rs", "target/build.log"] {
+            let file = fs::File::options().write(true).open(dir.path().join(path)).unwrap();
+            file.set_modified(old).unwrap();
+        }
+        // Directories were just modified too, so keep to the files that were backdated.
+        let mut options = SearchOptions::new(dir.path(), r"\.(md|txt|log)$|main");
+        options.modified_after = Some(cutoff);
+        assert_eq!(relative_paths(dir.path(), &options), ["notes.txt"]);
+    }
+
+    #[test]
+    fn modified_before_finds_only_stale_files() {
+        let dir = sample_tree();
+        let cutoff = SystemTime::now() - std::time::Duration::from_secs(3600);
+        let file = fs::File::options().write(true).open(dir.path().join("notes.txt")).unwrap();
+        file.set_modified(cutoff - std::time::Duration::from_secs(3600)).unwrap();
+        let mut options = SearchOptions::new(dir.path(), "");
+        options.modified_before = Some(cutoff);
+        assert_eq!(relative_paths(dir.path(), &options), ["notes.txt"]);
+    }
+
+    #[test]
+    fn size_and_date_words_in_the_term_become_filters() {
+        let dir = sample_tree();
+        // Of the files over 20 bytes, only README.md has an "e" in its name.
+        let mut options = SearchOptions::new(dir.path(), "size>20 e");
+        options.apply_term_filters();
+        assert_eq!(options.term, "e");
+        assert_eq!(options.min_size, Some(21));
+        assert_eq!(relative_paths(dir.path(), &options), ["README.md"]);
+
+        let mut options = SearchOptions::new(dir.path(), "modified<2000-01-01 size<1kb");
+        options.apply_term_filters();
+        assert_eq!(options.term, "");
+        assert_eq!(options.max_size, Some(999));
+        assert!(relative_paths(dir.path(), &options).is_empty());
+
+        // Anything that doesn't parse is left to match names.
+        let mut options = SearchOptions::new(dir.path(), "size>lots");
+        options.apply_term_filters();
+        assert_eq!(options.term, "size>lots");
+        assert_eq!(options.min_size, None);
+    }
+
+    #[test]
+    fn term_filters_leave_the_rest_of_the_term_as_typed() {
+        let stripped = |term: &str| {
+            let mut options = SearchOptions::new(".", term);
+            options.apply_term_filters();
+            options.term
+        };
+        assert_eq!(stripped("report  size>10MB  draft"), "report  draft");
+        assert_eq!(stripped("size>1 two  spaces"), "two  spaces");
+        assert_eq!(stripped("  a b  size<1kb "), "  a b");
+        assert_eq!(stripped("modified>2024-01-01"), "");
+        // Nothing taken leaves every space alone, as `trim_terms` may want them.
+        assert_eq!(stripped(" a  b "), " a  b ");
+        assert_eq!(stripped("x size>big  y"), "x size>big  y");
+    }
+
+    #[test]
+    fn term_filter_bounds_tighten_and_exclude_the_value() {
+        let mut options = SearchOptions::new(".", "size>1kb size>2kb size<5kb SIZE<1MB");
+        options.apply_term_filters();
+        assert_eq!(options.min_size, Some(2001));
+        assert_eq!(options.max_size, Some(4999));
+        assert!(options.size_within(Some(2001)));
+        assert!(!options.size_within(Some(2000)));
+        assert!(!options.size_within(None));
+        assert!(SearchOptions::new(".", "").size_within(None));
+
+        // Nothing is smaller than 0 bytes, so that isn't a bound on empty files.
+        let mut options = SearchOptions::new(".", "size<0 report");
+        options.apply_term_filters();
+        assert_eq!(options.max_size, None);
+        assert_eq!(options.term, "size<0 report");
+    }
+
+    #[test]
+    fn dates_are_the_start_of_the_day_in_utc() {
+        let at = |secs: u64| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(parse_date("1970-01-01"), at(0));
+        assert_eq!(parse_date("2024-01-01"), at(1_704_067_200));
+        assert_eq!(parse_date(" 2000-03-01 "), at(951_868_800));
+        assert_eq!(parse_date("2024-1-2"), at(1_704_153_600));
+        assert_eq!(
+            parse_date("1969-12-31"),
+            SystemTime::UNIX_EPOCH.checked_sub(Duration::from_secs(86_400))
+        );
+    }
+
+    #[test]
+    fn dates_that_do_not_exist_are_refused() {
+        // Leap days only in leap years: every fourth, but not centuries unless divisible by 400.
+        assert!(parse_date("2024-02-29").is_some());
+        assert!(parse_date("2000-02-29").is_some());
+        assert!(parse_date("2023-02-29").is_none());
+        assert!(parse_date("1900-02-29").is_none());
+        assert!(parse_date("2024-12-31").is_some());
+        assert!(parse_date("2024-12-32").is_none());
+        assert!(parse_date("2024-04-31").is_none());
+        assert!(parse_date("2024-13-01").is_none());
+        assert!(parse_date("2024-00-10").is_none());
+        assert!(parse_date("2024-01-00").is_none());
+        // Years no file has, however well-formed, rather than an overflow.
+        assert!(parse_date("9999999999999999-01-01").is_none());
+        assert!(parse_date("0000-01-01").is_none());
+        assert!(parse_date("9999-12-31").is_some());
+        for text in ["", "2024", "2024-01", "2024/01/01", "01-01-2024x", "yesterday"] {
+            assert!(parse_date(text).is_none(), "{} parsed", text);
+        }
+    }
+
+    #[test]
+    fn reads_are_retried_until_one_works_or_the_retries_run_out() {
+        let attempts = std::cell::Cell::new(0);
+        let flaky = || {
+            attempts.set(attempts.get() + 1);
+            if attempts.get() < 3 {
+                Err(io::Error::other("busy"))
+            } else {
+                Ok(attempts.get())
+            }
+        };
+        assert_eq!(retry_read(5, flaky), Some(3));
+        attempts.set(0);
+        assert_eq!(retry_read(2, flaky), None);
+        assert_eq!(attempts.get(), 2);
+        assert_eq!(retry_read(0, || Ok(())), None);
+    }
+
+    #[test]
+    fn sizes_take_decimal_units() {
+        assert_eq!(parse_size("200"), Some(200));
+        assert_eq!(parse_size("200b"), Some(200));
+        assert_eq!(parse_size("1.5k"), Some(1500));
+        assert_eq!(parse_size("10MB"), Some(10_000_000));
+        assert_eq!(parse_size("2Gb"), Some(2_000_000_000));
+        assert_eq!(parse_size("1tb"), Some(1_000_000_000_000));
+        assert_eq!(parse_size(""), None);
+        assert_eq!(parse_size("MB"), None);
+        assert_eq!(parse_size("10 MB"), None);
+        assert_eq!(parse_size("10xb"), None);
+        assert_eq!(parse_size("1.2.3"), None);
+    }
+
+    #[test]
+    fn creation_time_bounds_filter_entries() {
+        let dir = sample_tree();
+        let created = match fs::metadata(dir.path().join("notes.txt")).and_then(|m| m.created()) {
+            Ok(created) => created,
+            // Nothing to check where the filesystem doesn't record creation times.
+            Err(_) => return,
+        };
+        let hour = std::time::Duration::from_secs(3600);
+        let mut options = SearchOptions::new(dir.path(), "notes");
+        options.created_after = Some(created - hour);
+        options.created_before = Some(created + hour);
+        assert_eq!(relative_paths(dir.path(), &options), ["notes.txt"]);
+
+        options.created_after = Some(created + hour);
+        assert!(relative_paths(dir.path(), &options).is_empty());
+        options.created_after = None;
+        options.created_before = Some(created - hour);
+        assert!(relative_paths(dir.path(), &options).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn staying_on_one_filesystem_leaves_out_other_mounts() {
+        // The top of the disk usually holds mount points such as `/proc` and `/dev`.
+        let root = Path::new("/");
+        let mut options = SearchOptions::new(root, "");
+        options.max_depth = Some(1);
+        options.exclude_paths.clear();
+        let all: Vec<PathBuf> = run(&options).unwrap().into_iter().map(|r| r.path).collect();
+        let mounts: Vec<&PathBuf> =
+            all.iter().filter(|path| path.is_dir() && device_id(path) != device_id(root)).collect();
+        // Nothing to check where everything is on one filesystem.
+        if mounts.is_empty() {
+            return;
+        }
+        options.stay_on_filesystem = true;
+        let kept: Vec<PathBuf> = run(&options).unwrap().into_iter().map(|r| r.path).collect();
+        assert!(mounts.iter().all(|mount| !kept.contains(mount)));
+        assert!(all.iter().filter(|path| !mounts.contains(path)).all(|path| kept.contains(path)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn permission_filter_finds_executables_and_world_writable_entries() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = sample_tree();
+        // Start from known modes rather than whatever the umask gave.
+        for entry in WalkDir::new(dir.path()).min_depth(1) {
+            let entry = entry.unwrap();
+            let mode = if entry.file_type().is_dir() { 0o755 } else { 0o644 };
+            fs::set_permissions(entry.path(), fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let set_mode = |path: &str, mode| {
+            fs::set_permissions(dir.path().join(path), fs::Permissions::from_mode(mode)).unwrap()
+        };
+        set_mode("src/main.rs", 0o755);
+        set_mode("notes.txt", 0o666);
+        let mut options = SearchOptions::new(dir.path(), "");
+
+        options.permissions = PermissionFilter::Executable;
+        assert_eq!(relative_paths(dir.path(), &options), ["src/main.rs"]);
+
+        options.permissions = PermissionFilter::WorldWritable;
+        assert_eq!(relative_paths(dir.path(), &options), ["notes.txt"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_directories_count_as_entries_at_their_own_depth() {
+        use std::os::unix::fs::symlink;
+        let dir = sample_tree();
+        symlink(dir.path().join("src"), dir.path().join("docs/src-link")).unwrap();
+        let mut options = SearchOptions::new(dir.path(), "src-link");
+        options.max_depth = Some(1);
+        assert!(relative_paths(dir.path(), &options).is_empty());
+        options.max_depth = Some(2);
+        assert_eq!(relative_paths(dir.path(), &options), ["docs/src-link"]);
+
+        // Nothing is found through the link, at any depth and with links resolved.
+        options.term = r"\.rs$".to_string();
+        options.max_depth = None;
+        options.resolve_links = true;
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["src/main.rs", "src/nested/deep.rs"]
+        );
+    }
+
+    #[test]
+    fn empty_only_finds_zero_byte_files_and_empty_folders() {
+        let dir = sample_tree();
+        fs::create_dir(dir.path().join("docs/drafts")).unwrap();
+        fs::write(dir.path().join("src/empty.rs"), "").unwrap();
+        let mut options = SearchOptions::new(dir.path(), "");
+        options.empty_only = true;
+        assert_eq!(relative_paths(dir.path(), &options), ["docs/drafts", "src/empty.rs"]);
+
+        options.term = r"\.rs$".to_string();
+        assert_eq!(relative_paths(dir.path(), &options), ["src/empty.rs"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn broken_links_only_finds_dangling_symlinks() {
+        use std::os::unix::fs::symlink;
+        let dir = sample_tree();
+        symlink(dir.path().join("notes.txt"), dir.path().join("docs/notes-link")).unwrap();
+        symlink(dir.path().join("gone.txt"), dir.path().join("src/gone-link")).unwrap();
+        let mut options = SearchOptions::new(dir.path(), "");
+        options.broken_links_only = true;
+        let results = run(&options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, dir.path().join("src/gone-link"));
+        assert_eq!(results[0].link, Some(LinkTarget::Broken));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_can_match_by_their_target_name() {
+        use std::os::unix::fs::symlink;
+        let dir = sample_tree();
+        symlink(dir.path().join("src/main.rs"), dir.path().join("docs/entry")).unwrap();
+        symlink(dir.path().join("gone-tool"), dir.path().join("docs/broken")).unwrap();
+        let mut options = SearchOptions::new(dir.path(), "^main|tool");
+        assert_eq!(relative_paths(dir.path(), &options), ["src/main.rs"]);
+
+        options.match_link_targets = true;
+        assert_eq!(
+            relative_paths(dir.path(), &options),
+            ["docs/broken", "docs/entry", "src/main.rs"]
+        );
+    }
+
+    // Only Unix lets a file name be arbitrary bytes.
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_names_match_through_a_lossy_conversion() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let name = OsStr::from_bytes(b"report-\xff.csv");
+        let path = dir.path().join(name);
+        // Some filesystems (e.g. on macOS) reject names that aren't valid UTF-8.
+        if fs::write(&path, "data").is_err() {
+            return;
+        }
+        let results = run(&SearchOptions::new(dir.path(), r"report-.*\.csv")).unwrap();
+        assert_eq!(results.len(), 1);
+        // The original bytes are kept so the file can still be opened.
+        assert_eq!(results[0].path, path);
+    }
+
+    #[test]
+    fn root_lists_keep_folders_and_report_the_rest() {
+        let dir = sample_tree();
+        let src = dir.path().join("src").display().to_string();
+        let docs = dir.path().join("docs").display().to_string();
+        let file = dir.path().join("notes.txt").display().to_string();
+        let missing = dir.path().join("missing").display().to_string();
+        let text = format!("# roots\n  {}  \n\n{}\n{}\n\t\n{}\n", src, file, docs, missing);
+        assert_eq!(parse_root_list(&text), (vec![src, docs], vec![file, missing]));
+    }
+
+    #[test]
+    fn sandbox_refuses_roots_that_climb_out_with_dot_dot() {
+        let dir = sample_tree();
+        let sandbox = dir.path().join("src");
+        assert!(sandbox_allows(&sandbox, &sandbox.join("nested")));
+        assert!(sandbox_allows(&sandbox, &sandbox.join("nested/..")));
+        assert!(!sandbox_allows(&sandbox, &sandbox.join("../docs")));
+        assert!(!sandbox_allows(&sandbox, &sandbox.join("nested/../../docs")));
+        // Paths that don't exist are resolved the same way.
+        assert!(!sandbox_allows(&sandbox, &sandbox.join("missing/../../docs")));
+
+        let mut options = SearchOptions::new(sandbox.join("../docs"), "");
+        options.sandbox_root = Some(sandbox.clone());
+        assert!(matches!(run(&options), Err(SearchError::OutsideSandbox(..))));
+        options.root = sandbox.join("nested");
+        options.other_roots = vec![dir.path().to_path_buf()];
+        assert!(matches!(run(&options), Err(SearchError::OutsideSandbox(..))));
+        options.other_roots.clear();
+        assert_eq!(run(&options).unwrap().len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sandbox_refuses_symlinks_leading_out_of_it() {
+        use std::os::unix::fs::symlink;
+
+        let dir = sample_tree();
+        let sandbox = dir.path().join("src");
+        symlink(dir.path().join("docs"), sandbox.join("docs-link")).unwrap();
+        assert!(!sandbox_allows(&sandbox, &sandbox.join("docs-link")));
+
+        let mut options = SearchOptions::new(sandbox.join("docs-link"), "");
+        options.sandbox_root = Some(sandbox.clone());
+        assert!(matches!(run(&options), Err(SearchError::OutsideSandbox(..))));
+        // Inside the sandbox the link is listed but never followed.
+        options.root = sandbox.clone();
+        let results = run(&options).unwrap();
+        assert!(results.iter().all(|result| !result.path.ends_with("Guide.TXT")));
+    }
+}
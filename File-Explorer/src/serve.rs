@@ -0,0 +1,334 @@
+use file_explorer::search::{self, PermissionFilter, SearchMode, SearchOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+
+use crate::config;
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+// Requests with larger bodies are rejected rather than buffered.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Body of `POST /search`. Only `root` is required.
+#[derive(Deserialize)]
+#[serde(default)]
+struct SearchRequest {
+    root: PathBuf,
+    other_roots: Vec<PathBuf>,
+    term: String,
+    or_terms: Vec<String>,
+    case_sensitive: bool,
+    all_words: bool,
+    stem_only: bool,
+    trim_terms: bool,
+    // Match names allowing typos, up to `max_edit_distance` of them; overrides the other modes.
+    approximate: bool,
+    max_edit_distance: usize,
+    exact_case_first: bool,
+    normalize_unicode: bool,
+    // Fold `ß`, Turkish `İ`/`ı` and ligatures too when ignoring case.
+    full_case_folding: bool,
+    search_contents: bool,
+    // With `search_contents`: "name_or_contents" or "name_and_contents" to match names too;
+    // empty for contents only.
+    content_mode: String,
+    match_full_path: bool,
+    extensions: Vec<String>,
+    // Types sniffed from the contents, e.g. "image" or "application/pdf".
+    mime_types: Vec<String>,
+    exclude_dirs: Vec<String>,
+    show_hidden: bool,
+    exclude_paths: Vec<PathBuf>,
+    stay_on_filesystem: bool,
+    max_depth: Option<usize>,
+    // One of "executable", "world_writable", "setuid" or "read_only"; empty for any.
+    permissions: String,
+    resolve_links: bool,
+    // Let symlinks match by their target's file name too.
+    match_link_targets: bool,
+    broken_links_only: bool,
+    empty_only: bool,
+    skip_binary: bool,
+    // 0 reads whole files.
+    content_max_bytes: u64,
+    thread_count: usize,
+    // Pause between reads to spare the disk; slower.
+    low_impact: bool,
+    // Times to retry an unreadable folder before skipping it.
+    read_retries: usize,
+    // Read `size>10MB`-style words in `term` as filters.
+    term_filters: bool,
+    // Keep just this many of the biggest matching files, biggest first.
+    largest_files: Option<usize>,
+}
+
+impl Default for SearchRequest {
+    fn default() -> Self {
+        SearchRequest {
+            root: PathBuf::new(),
+            other_roots: Vec::new(),
+            term: String::new(),
+            or_terms: Vec::new(),
+            case_sensitive: false,
+            all_words: false,
+            stem_only: false,
+            trim_terms: true,
+            approximate: false,
+            max_edit_distance: 2,
+            exact_case_first: false,
+            normalize_unicode: cfg!(target_os = "macos"),
+            full_case_folding: false,
+            search_contents: false,
+            content_mode: String::new(),
+            match_full_path: false,
+            extensions: Vec::new(),
+            mime_types: Vec::new(),
+            exclude_dirs: Vec::new(),
+            show_hidden: true,
+            exclude_paths: search::default_exclude_paths(),
+            stay_on_filesystem: false,
+            max_depth: None,
+            permissions: String::new(),
+            resolve_links: false,
+            match_link_targets: false,
+            broken_links_only: false,
+            empty_only: false,
+            skip_binary: true,
+            content_max_bytes: search::DEFAULT_CONTENT_MAX_BYTES,
+            thread_count: config::default_thread_count(),
+            low_impact: false,
+            read_retries: 2,
+            term_filters: false,
+            largest_files: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SearchResponse {
+    results: Vec<ResultJson>,
+}
+
+#[derive(Serialize)]
+struct ResultJson {
+    path: String,
+    is_dir: bool,
+    match_line: Option<String>,
+    match_line_number: Option<usize>,
+    match_count: usize,
+    edit_distance: Option<usize>,
+    mime: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Serves `POST /search` on localhost until the process is killed, one thread per connection.
+/// Searches are kept inside the config file's `sandbox_root` like the window's.
+///
+/// Requests must carry the token printed on start as `Authorization: Bearer <token>`, and a
+/// loopback `Host`, so a web page open in a browser can't search the disk through it, either
+/// directly or by rebinding its own domain to 127.0.0.1.
+pub fn run(port: u16) -> io::Result<()> {
+    let sandbox = config::Config::load().sandbox_root;
+    let token = new_token();
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    println!("Listening on http://{}", listener.local_addr()?);
+    println!("Send the header `Authorization: Bearer {}` with each request", token);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let sandbox = sandbox.clone();
+                let token = token.clone();
+                thread::spawn(move || {
+                    if let Err(err) = handle_connection(stream, &token, sandbox) {
+                        eprintln!("request failed: {}", err);
+                    }
+                });
+            }
+            Err(err) => eprintln!("failed to accept a connection: {}", err),
+        }
+    }
+    Ok(())
+}
+
+/// 128 random bits as hex, from the OS-seeded keys std uses against hash flooding.
+fn new_token() -> String {
+    (0..2)
+        .map(|_| format!("{:016x}", RandomState::new().build_hasher().finish()))
+        .collect()
+}
+
+/// Whether a `Host` header names this machine's loopback interface, with or without a port.
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !name.ends_with(':') && port.bytes().all(|b| b.is_ascii_digit()) => {
+            name
+        }
+        _ => host,
+    };
+    matches!(name, "127.0.0.1" | "localhost" | "[::1]")
+}
+
+fn handle_connection(stream: TcpStream, token: &str, sandbox: Option<PathBuf>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+
+    let mut content_length = 0;
+    let mut host = String::new();
+    let mut authorization = String::new();
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let name = name.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("host") {
+                host = value.trim().to_string();
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = value.trim().to_string();
+            }
+        }
+    }
+
+    let authorized = authorization.strip_prefix("Bearer ").is_some_and(|given| given == token);
+    let (status, body) = match (method, target) {
+        _ if !is_loopback_host(&host) => error("403 Forbidden", "the Host must be localhost"),
+        _ if !authorized => error(
+            "401 Unauthorized",
+            "send the token printed on start as `Authorization: Bearer <token>`",
+        ),
+        ("POST", "/search") if content_length > MAX_BODY_BYTES => {
+            error("413 Payload Too Large", "request body too large")
+        }
+        ("POST", "/search") => {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            handle_search(&body, sandbox)
+        }
+        (_, "/search") => error("405 Method Not Allowed", "use POST"),
+        _ => error("404 Not Found", "unknown path; use POST /search"),
+    };
+    respond(stream, status, &body)
+}
+
+fn handle_search(body: &[u8], sandbox: Option<PathBuf>) -> (&'static str, String) {
+    let request: SearchRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(err) => return error("400 Bad Request", &format!("invalid request: {}", err)),
+    };
+    let mode = if request.approximate {
+        SearchMode::Approximate
+    } else if request.search_contents {
+        match request.content_mode.as_str() {
+            "" => SearchMode::Contents,
+            "name_or_contents" => SearchMode::NameOrContents,
+            "name_and_contents" => SearchMode::NameAndContents,
+            other => return error("400 Bad Request", &format!("unknown content mode: {}", other)),
+        }
+    } else if request.match_full_path {
+        SearchMode::FullPath
+    } else {
+        SearchMode::Name
+    };
+    let permissions = match request.permissions.as_str() {
+        "" => PermissionFilter::Any,
+        "executable" => PermissionFilter::Executable,
+        "world_writable" => PermissionFilter::WorldWritable,
+        "setuid" => PermissionFilter::Setuid,
+        "read_only" => PermissionFilter::ReadOnly,
+        other => return error("400 Bad Request", &format!("unknown permissions filter: {}", other)),
+    };
+    let mut options = SearchOptions {
+        other_roots: request.other_roots,
+        or_terms: request.or_terms,
+        case_sensitive: request.case_sensitive,
+        all_words: request.all_words,
+        stem_only: request.stem_only,
+        trim_terms: request.trim_terms,
+        max_edit_distance: request.max_edit_distance,
+        exact_case_first: request.exact_case_first,
+        normalize_unicode: request.normalize_unicode,
+        full_case_folding: request.full_case_folding,
+        mode,
+        extensions: request.extensions,
+        mime_types: request.mime_types,
+        exclude_dirs: request.exclude_dirs,
+        show_hidden: request.show_hidden,
+        exclude_paths: request.exclude_paths,
+        stay_on_filesystem: request.stay_on_filesystem,
+        max_depth: request.max_depth,
+        permissions,
+        resolve_links: request.resolve_links,
+        match_link_targets: request.match_link_targets,
+        broken_links_only: request.broken_links_only,
+        empty_only: request.empty_only,
+        skip_binary: request.skip_binary,
+        content_max_bytes: Some(request.content_max_bytes).filter(|&bytes| bytes > 0),
+        // The machine's own thread count is the most a request may tie up.
+        thread_count: request.thread_count.clamp(1, config::default_thread_count()),
+        low_impact: request.low_impact,
+        read_retries: request.read_retries,
+        largest_files: request.largest_files,
+        sandbox_root: sandbox,
+        ..SearchOptions::new(request.root, request.term)
+    };
+    if request.term_filters {
+        options.apply_term_filters();
+    }
+    let results = match search::run(&options) {
+        Ok(results) => results
+            .into_iter()
+            .map(|result| ResultJson {
+                path: result.path.to_string_lossy().to_string(),
+                is_dir: result.is_dir,
+                match_line: result.match_line,
+                match_line_number: result.match_line_number,
+                match_count: result.match_count,
+                edit_distance: result.edit_distance,
+                mime: result.mime,
+            })
+            .collect(),
+        Err(err @ search::SearchError::OutsideSandbox(..)) => {
+            return error("403 Forbidden", &err.to_string())
+        }
+        Err(err) => return error("400 Bad Request", &err.to_string()),
+    };
+    let response = SearchResponse { results };
+    (
+        "200 OK",
+        serde_json::to_string(&response).unwrap_or_default(),
+    )
+}
+
+fn error(status: &'static str, message: &str) -> (&'static str, String) {
+    let body = ErrorResponse {
+        error: message.to_string(),
+    };
+    (status, serde_json::to_string(&body).unwrap_or_default())
+}
+
+fn respond(mut stream: TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
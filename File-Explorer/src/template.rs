@@ -0,0 +1,133 @@
+//! Turning the command settings, such as the open, editor and compare commands, into a program
+//! and its arguments.
+
+use std::ffi::{OsStr, OsString};
+
+/// Splits `template` into a program and its arguments, replacing every placeholder in
+/// `values`, e.g. `("{path}", path)`, with its value wherever it appears in a word.
+///
+/// Words are separated by whitespace, except inside double or single quotes, which are
+/// dropped, so `"C:\Program Files\Tool\tool.exe" {}` names one program. Backslashes are kept
+/// as they are. Values are put in whole, spaces and all, and are never split or quoted again.
+/// If the template uses none of the `appended` placeholders, their values are added at the
+/// end in that order.
+pub fn expand(
+    template: &str,
+    values: &[(&str, &OsStr)],
+    appended: &[&str],
+) -> Result<(OsString, Vec<OsString>), String> {
+    let mut used = false;
+    let mut words: Vec<OsString> = split_words(template)?
+        .iter()
+        .map(|word| {
+            let mut expanded = OsString::new();
+            let mut rest = word.as_str();
+            // The earliest placeholder each time, so values are never searched themselves.
+            while let Some((at, name, value)) = values
+                .iter()
+                .filter_map(|&(name, value)| Some((rest.find(name)?, name, value)))
+                .min_by_key(|&(at, _, _)| at)
+            {
+                used |= appended.contains(&name);
+                expanded.push(&rest[..at]);
+                expanded.push(value);
+                rest = &rest[at + name.len()..];
+            }
+            expanded.push(rest);
+            expanded
+        })
+        .collect();
+    if words.is_empty() {
+        return Err("The command is empty.".to_string());
+    }
+    if !used {
+        for name in appended {
+            if let Some(&(_, value)) = values.iter().find(|(placeholder, _)| placeholder == name) {
+                words.push(value.to_os_string());
+            }
+        }
+    }
+    let program = words.remove(0);
+    Ok((program, words))
+}
+
+/// The words of `template`, split on whitespace outside quotes and with the quotes removed.
+/// A quoted empty string is an empty word.
+fn split_words(template: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in template.chars() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => word.get_or_insert_with(String::new).push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            None if c.is_whitespace() => words.extend(word.take()),
+            None => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(open) = quote {
+        return Err(format!("The command has a {} that isn't closed.", open));
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_path(template: &str, path: &str) -> Result<(OsString, Vec<OsString>), String> {
+        expand(template, &[("{}", OsStr::new(path))], &["{}"])
+    }
+
+    #[test]
+    fn placeholders_are_replaced_inside_words() {
+        let path = OsStr::new("/src/main.rs");
+        let line = OsStr::new("12");
+        let (program, args) =
+            expand("code -g {path}:{line}", &[("{path}", path), ("{line}", line)], &["{path}"]).unwrap();
+        assert_eq!(program, "code");
+        assert_eq!(args, ["-g", "/src/main.rs:12"]);
+    }
+
+    #[test]
+    fn values_are_appended_only_without_their_placeholders() {
+        assert_eq!(expand_path("xdg-open", "/a b").unwrap().1, ["/a b"]);
+        assert_eq!(expand_path("xdg-open {} --new", "/a").unwrap().1, ["/a", "--new"]);
+
+        // `{line}` alone still leaves the path to be appended.
+        let values = [("{path}", OsStr::new("/f")), ("{line}", OsStr::new("3"))];
+        assert_eq!(expand("vim +{line}", &values, &["{path}"]).unwrap().1, ["+3", "/f"]);
+
+        let values = [("{a}", OsStr::new("/1")), ("{b}", OsStr::new("/2"))];
+        assert_eq!(expand("meld", &values, &["{a}", "{b}"]).unwrap().1, ["/1", "/2"]);
+        assert_eq!(expand("diff {b} {a}", &values, &["{a}", "{b}"]).unwrap().1, ["/2", "/1"]);
+    }
+
+    #[test]
+    fn values_are_never_split_or_expanded_again() {
+        let (_, args) = expand_path("open {}", "/My Files/{} and {b}.txt").unwrap();
+        assert_eq!(args, ["/My Files/{} and {b}.txt"]);
+    }
+
+    #[test]
+    fn quotes_group_words_and_are_dropped() {
+        let (program, args) =
+            expand_path(r#""C:\Program Files\Tool\tool.exe" --title 'two words' {}"#, "x").unwrap();
+        assert_eq!(program, r"C:\Program Files\Tool\tool.exe");
+        assert_eq!(args, ["--title", "two words", "x"]);
+
+        let (_, args) = expand_path(r#"tool "" "it's" {}"#, "x").unwrap();
+        assert_eq!(args, ["", "it's", "x"]);
+    }
+
+    #[test]
+    fn empty_templates_and_open_quotes_are_refused() {
+        assert!(expand_path("  ", "x").unwrap_err().contains("empty"));
+        assert!(expand_path("open \"{}", "x").unwrap_err().contains("isn't closed"));
+    }
+}
@@ -0,0 +1,105 @@
+use druid::{Color, Data, Env, FontDescriptor, FontFamily, Key, Lens};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Env keys the widgets read their colors from, so a theme change restyles the whole UI.
+pub const BACKGROUND: Key<Color> = Key::new("file_explorer.theme.background");
+pub const SURFACE: Key<Color> = Key::new("file_explorer.theme.surface");
+pub const ACCENT: Key<Color> = Key::new("file_explorer.theme.accent");
+pub const TEXT: Key<Color> = Key::new("file_explorer.theme.text");
+// Font of the text in the results list, set by `set_result_font`.
+pub const RESULT_FONT: Key<FontDescriptor> = Key::new("file_explorer.theme.result_font");
+
+/// User-adjustable colors, stored as `#rrggbb` strings so they can be edited and persisted as-is.
+#[derive(Clone, Data, Lens, PartialEq, Serialize, Deserialize)]
+pub struct ThemeSettings {
+    pub background: String,
+    pub surface: String,
+    pub accent: String,
+    pub text: String,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        ThemeSettings::new("#000000", "#333333", "#444444", "#ffffff")
+    }
+}
+
+/// Built-in presets, by display name. The first one is the default look.
+pub fn presets() -> Vec<(&'static str, ThemeSettings)> {
+    vec![
+        ("Dark", ThemeSettings::default()),
+        ("Midnight", ThemeSettings::new("#0b1020", "#1a2238", "#2f4a8a", "#e6ecff")),
+        ("Light", ThemeSettings::new("#f2f2f2", "#ffffff", "#c8c8c8", "#202020")),
+    ]
+}
+
+impl ThemeSettings {
+    fn new(background: &str, surface: &str, accent: &str, text: &str) -> Self {
+        ThemeSettings {
+            background: background.to_string(),
+            surface: surface.to_string(),
+            accent: accent.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    /// Writes the theme into `env`, falling back to the default for any color that doesn't parse.
+    pub fn apply(&self, env: &mut Env) {
+        let defaults = ThemeSettings::default();
+        let background = parse_color(&self.background, &defaults.background);
+        let surface = parse_color(&self.surface, &defaults.surface);
+        let accent = parse_color(&self.accent, &defaults.accent);
+        let text = parse_color(&self.text, &defaults.text);
+
+        env.set(BACKGROUND, background);
+        env.set(SURFACE, surface);
+        env.set(ACCENT, accent);
+        env.set(TEXT, text);
+
+        env.set(druid::theme::BACKGROUND_LIGHT, background);
+        env.set(druid::theme::TEXT_COLOR, text);
+        env.set(druid::theme::PLACEHOLDER_COLOR, Color::grey(0.6));
+        env.set(druid::theme::WINDOW_BACKGROUND_COLOR, surface);
+        env.set(druid::theme::BUTTON_DARK, accent);
+        env.set(druid::theme::BUTTON_LIGHT, accent);
+    }
+}
+
+/// Renders the results list in a monospaced font, or in the UI's own font.
+pub fn set_result_font(env: &mut Env, monospace: bool) {
+    let family = if monospace { FontFamily::MONOSPACE } else { FontFamily::SYSTEM_UI };
+    env.set(RESULT_FONT, FontDescriptor::new(family));
+}
+
+/// Accent for a result's category, derived from its extension; None for files of no known type.
+/// The colors are bright enough to stand out against the dark themes.
+pub fn file_type_color(path: &Path, is_dir: bool) -> Option<Color> {
+    if is_dir {
+        return Some(Color::rgb8(0xf2, 0xc1, 0x4e));
+    }
+    let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+    let color = match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "svg" | "webp" | "ico" | "tiff" => {
+            Color::rgb8(0xe0, 0x6c, 0xd8)
+        }
+        "rs" | "c" | "h" | "cpp" | "hpp" | "go" | "py" | "js" | "ts" | "java" | "kt" | "swift"
+        | "rb" | "sh" | "cs" => Color::rgb8(0x4e, 0xd0, 0xa8),
+        "md" | "txt" | "pdf" | "doc" | "docx" | "rtf" | "odt" => Color::rgb8(0x6c, 0xa6, 0xff),
+        "json" | "toml" | "yaml" | "yml" | "xml" | "ini" | "cfg" | "lock" => {
+            Color::rgb8(0xb0, 0x9c, 0xff)
+        }
+        "zip" | "tar" | "gz" | "xz" | "bz2" | "7z" | "rar" => Color::rgb8(0xff, 0x8c, 0x42),
+        "mp3" | "wav" | "flac" | "ogg" | "mp4" | "mkv" | "mov" | "avi" | "webm" => {
+            Color::rgb8(0xff, 0x6b, 0x6b)
+        }
+        _ => return None,
+    };
+    Some(color)
+}
+
+fn parse_color(hex: &str, fallback: &str) -> Color {
+    Color::from_hex_str(hex.trim())
+        .or_else(|_| Color::from_hex_str(fallback))
+        .unwrap_or(Color::BLACK)
+}
@@ -0,0 +1,67 @@
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+// How long the directory has to stay quiet before a burst of changes triggers a refresh.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watches directories, and with `recursive` everything below them, and calls back, debounced,
+/// when entries are created, removed, renamed or written to. Dropping it stops the watch and
+/// its debounce thread.
+pub struct DirWatcher {
+    // Held only to keep the watch alive.
+    _watcher: RecommendedWatcher,
+}
+
+impl DirWatcher {
+    pub fn new(
+        roots: &[PathBuf],
+        recursive: bool,
+        on_change: impl Fn() + Send + 'static,
+    ) -> notify::Result<DirWatcher> {
+        let (tx, rx) = mpsc::channel::<()>();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                if is_listing_change(&event.kind) {
+                    let _ = tx.send(());
+                }
+            }
+        })?;
+        let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+        for root in roots {
+            watcher.watch(root, mode).map_err(|err| err.add_path(root.clone()))?;
+        }
+
+        // The sender lives in the watcher, so this thread ends once the watcher is dropped.
+        thread::spawn(move || {
+            while rx.recv().is_ok() {
+                // Swallow the rest of the burst before refreshing once.
+                loop {
+                    match rx.recv_timeout(DEBOUNCE) {
+                        Ok(()) => continue,
+                        Err(mpsc::RecvTimeoutError::Timeout) => break,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                on_change();
+            }
+        });
+
+        Ok(DirWatcher { _watcher: watcher })
+    }
+}
+
+/// Only changes that can alter the results matter: entries coming and going, and writes,
+/// which change what content searches find and the sizes and times shown. Opening or reading
+/// files doesn't. Windows reports every write as an unspecified modification.
+fn is_listing_change(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_)
+            | EventKind::Remove(_)
+            | EventKind::Modify(ModifyKind::Name(_) | ModifyKind::Data(_) | ModifyKind::Any)
+    )
+}